@@ -0,0 +1,65 @@
+// Lexer throughput benchmark
+//
+// Lexes synthetic .bcc scripts of increasing size and reports throughput.
+// Lexing must stay linear in the input length: if quadrupling the input
+// makes lexing much more than four times slower, the run fails so that a
+// quadratic cursor cannot sneak back in. Run with `cargo bench --bench lexer`.
+
+use bcc::lexer::Lexer;
+use std::time::{Duration, Instant};
+
+/// Build a script resembling our generated data files: assignments,
+/// arithmetic, string and dict literals, comments and some non-ASCII text
+fn synthetic_script(lines: usize) -> String {
+    let mut source = String::with_capacity(lines * 48);
+    for i in 0..lines {
+        match i % 5 {
+            0 => source.push_str(&format!("value_{} = {} * 60 * 24 + {}.5\n", i, i, i % 7)),
+            1 => source.push_str(&format!("name_{} = \"record {} – café\"\n", i, i)),
+            2 => source.push_str(&format!("row_{} = {{\"id\": {}, \"tags\": [1, 2, 3]}}\n", i, i)),
+            3 => source.push_str("// generated comment line with some padding text\n"),
+            _ => source.push_str(&format!("if (value_{} >= 10) {{ total = total + 1; }}\n", i - 4)),
+        }
+    }
+    source
+}
+
+fn time_lexing(source: &str, iterations: u32) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let mut lexer = Lexer::new(source.to_string());
+        let tokens = lexer.scan_tokens().expect("synthetic script should lex");
+        std::hint::black_box(tokens);
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    let sizes = [2_500, 10_000, 40_000, 160_000];
+    let mut previous: Option<(usize, Duration)> = None;
+    let mut worst_ratio: f64 = 0.0;
+
+    println!("{:>10} {:>12} {:>12} {:>10}", "lines", "bytes", "time", "MB/s");
+    for &lines in &sizes {
+        let source = synthetic_script(lines);
+        // Warm up once, then average a few runs
+        time_lexing(&source, 1);
+        let elapsed = time_lexing(&source, 3);
+        let mb_per_sec = source.len() as f64 / elapsed.as_secs_f64() / 1_000_000.0;
+        println!("{:>10} {:>12} {:>12.2?} {:>10.1}", lines, source.len(), elapsed, mb_per_sec);
+
+        if let Some((previous_lines, previous_elapsed)) = previous {
+            let size_ratio = lines as f64 / previous_lines as f64;
+            let time_ratio = elapsed.as_secs_f64() / previous_elapsed.as_secs_f64();
+            worst_ratio = worst_ratio.max(time_ratio / size_ratio);
+        }
+        previous = Some((lines, elapsed));
+    }
+
+    // Allow generous noise; a quadratic lexer scores around 4x here
+    println!("worst scaling factor relative to linear: {:.2}", worst_ratio);
+    if worst_ratio > 3.0 {
+        eprintln!("lexing time grows faster than linearly with input size");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,84 @@
+// Block scope benchmark
+//
+// Runs scripts that enter blocks many times while large values are in scope,
+// and scripts whose blocks are nested very deeply. Entering a block must not
+// depend on how much data the surrounding scopes hold: if growing the outer
+// list a hundredfold makes the loop much slower, the run fails so that
+// per-block copies of the environment cannot sneak back in. Run with
+// `cargo bench --bench scopes`.
+
+use bcc::lexer::Lexer;
+use bcc::parser::Parser;
+use bcc::{Compiler, Evaluator, Program, Vm};
+use std::time::{Duration, Instant};
+
+fn parse(source: &str) -> Program {
+    let tokens = Lexer::new(source.to_string()).scan_tokens().expect("benchmark script should lex");
+    Parser::new(tokens).parse().expect("benchmark script should parse")
+}
+
+/// A loop entering a block on every iteration, with a list of `size` elements in scope
+fn loop_with_large_list(size: usize) -> String {
+    let elements: Vec<String> = (0..size).map(|i| i.to_string()).collect();
+    format!(
+        "data = [{}]\ntotal = 0\nfor (i = 0; i < 2000; i = i + 1) {{ {{ total = total + i }} }}\nassert total == 1999000",
+        elements.join(", ")
+    )
+}
+
+/// `depth` nested blocks, the innermost one reading and updating outer variables
+fn deeply_nested(depth: usize) -> String {
+    format!(
+        "total = 0\nfor (i = 0; i < 200; i = i + 1) {}total = total + i{}\nassert total == 19900",
+        "{ ".repeat(depth),
+        " }".repeat(depth)
+    )
+}
+
+fn time_tree(program: &Program, iterations: u32) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        Evaluator::new().evaluate_program(program).expect("benchmark script should run");
+    }
+    start.elapsed() / iterations
+}
+
+fn time_vm(program: &Program, iterations: u32) -> Duration {
+    let chunk = Compiler::compile(program);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        Vm::new().run(&chunk).expect("benchmark script should run");
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    println!("{:>12} {:>12} {:>12}", "list size", "tree", "vm");
+    let mut timings = Vec::new();
+    for size in [100, 1_000, 10_000] {
+        let program = parse(&loop_with_large_list(size));
+        time_tree(&program, 1);
+        let tree = time_tree(&program, 5);
+        let vm = time_vm(&program, 5);
+        println!("{:>12} {:>12.2?} {:>12.2?}", size, tree, vm);
+        timings.push(tree);
+    }
+
+    println!();
+    println!("{:>12} {:>12} {:>12}", "depth", "tree", "vm");
+    for depth in [1, 16, 64, 256] {
+        let program = parse(&deeply_nested(depth));
+        time_tree(&program, 1);
+        let tree = time_tree(&program, 5);
+        let vm = time_vm(&program, 5);
+        println!("{:>12} {:>12.2?} {:>12.2?}", depth, tree, vm);
+    }
+
+    // The list grows a hundredfold; copying it per block would scale with it
+    let ratio = timings[2].as_secs_f64() / timings[0].as_secs_f64();
+    println!("time ratio for a 100x larger outer scope: {:.2}", ratio);
+    if ratio > 3.0 {
+        eprintln!("entering a block gets slower as the surrounding scopes grow");
+        std::process::exit(1);
+    }
+}
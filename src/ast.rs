@@ -37,6 +37,12 @@ pub enum Stmt {
         body: Box<Stmt>,
         span: Span,
     },
+    /// Assertion: assert condition or assert condition, message
+    Assert {
+        condition: Expr,
+        message: Option<Expr>,
+        span: Span,
+    },
 }
 
 impl Stmt {
@@ -47,6 +53,7 @@ impl Stmt {
             Stmt::If { span, .. } => span,
             Stmt::While { span, .. } => span,
             Stmt::For { span, .. } => span,
+            Stmt::Assert { span, .. } => span,
         }
     }
 }
@@ -78,6 +85,14 @@ pub enum Expr {
         right: Box<Expr>,
         span: Span,
     },
+    /// Chained comparison such as `a < b <= c`: `operators[i]` compares `operands[i]`
+    /// with `operands[i + 1]`, each operand is evaluated at most once, and evaluation
+    /// stops at the first comparison that is false
+    Comparison {
+        operands: Vec<Expr>,
+        operators: Vec<BinaryOp>,
+        span: Span,
+    },
     Unary {
         operator: UnaryOp,
         operand: Box<Expr>,
@@ -89,6 +104,14 @@ pub enum Expr {
         right: Box<Expr>,
         span: Span,
     },
+    /// `then_branch if condition else else_branch`, or `condition ? then_branch : else_branch`;
+    /// only the chosen branch is evaluated
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+        span: Span,
+    },
     Call {
         callee: Box<Expr>,
         args: Vec<Expr>,
@@ -118,9 +141,17 @@ pub enum Expr {
         pairs: Vec<(Expr, Expr)>,
         span: Span,
     },
+    /// Set literal: {1, 2, 3}
+    Set {
+        elements: Vec<Expr>,
+        span: Span,
+    },
+    /// `object.property`, or `object?.property` when `optional`, which is nil
+    /// instead of an error when the object is nil
     PropertyAccess {
         object: Box<Expr>,
         property: String,
+        optional: bool,
         span: Span,
     },
     /// Tuple expression for multi-value contexts: (a, b, c)
@@ -128,13 +159,67 @@ pub enum Expr {
         elements: Vec<Expr>,
         span: Span,
     },
+    /// `match subject { pattern => body, ... }`: the body of the first arm whose
+    /// pattern matches and whose guard holds
+    Match {
+        subject: Box<Expr>,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
 }
 
-/// Represents a target in multi-assignment: variable name or underscore (ignore)
+/// One arm of a match: `pattern if guard => body`
 #[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+impl MatchArm {
+    /// Whether the arm accepts every value: an unguarded `_` or name
+    pub fn is_catch_all(&self) -> bool {
+        self.guard.is_none()
+            && matches!(self.pattern, Pattern::Target(AssignTarget::Variable { .. } | AssignTarget::Ignore { .. }))
+    }
+}
+
+/// What a match arm accepts
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `_` accepts anything; a name accepts anything and is assigned the value.
+    /// Inside a sequence, `*rest` takes the elements the other patterns leave over.
+    Target(AssignTarget),
+    /// A literal, compared with `==`
+    Literal { value: Value, span: Span },
+    /// `(a, b)` or `[a, b]`: a tuple or list with one element per pattern
+    Sequence { elements: Vec<Pattern>, span: Span },
+    /// `{"key": pattern}`: a dictionary with these keys whose values match; other keys are ignored
+    Dict { entries: Vec<(Value, Pattern)>, span: Span },
+}
+
+impl Pattern {
+    /// Names the pattern assigns and where, in the order `Evaluator::match_pattern` binds them
+    pub fn bindings(&self) -> Vec<(&str, &Span)> {
+        match self {
+            Pattern::Target(target) => target.bindings(),
+            Pattern::Literal { .. } => Vec::new(),
+            Pattern::Sequence { elements, .. } => elements.iter().flat_map(Pattern::bindings).collect(),
+            Pattern::Dict { entries, .. } => entries.iter().flat_map(|(_, pattern)| pattern.bindings()).collect(),
+        }
+    }
+}
+
+/// Represents a target in multi-assignment: variable name or underscore (ignore)
+#[derive(Debug, Clone, PartialEq)]
 pub enum AssignTarget {
     Variable { name: String, span: Span },
     Ignore { span: Span },  // For underscore targets: a, _, c = expr
+    /// `*rest`: a list of the values the other targets at its level leave over.
+    /// `target` is a variable or `_`.
+    Starred { target: Box<AssignTarget>, span: Span },
+    /// `(b, c)` or `[b, c]` inside a target list: destructures one value further
+    Nested { targets: Vec<AssignTarget>, span: Span },
 }
 
 impl AssignTarget {
@@ -142,6 +227,19 @@ impl AssignTarget {
         match self {
             AssignTarget::Variable { span, .. } => span,
             AssignTarget::Ignore { span } => span,
+            AssignTarget::Starred { span, .. } => span,
+            AssignTarget::Nested { span, .. } => span,
+        }
+    }
+
+    /// Names the target assigns and where, depth first; `Evaluator::destructure` and
+    /// `Evaluator::match_pattern` produce values in this order
+    pub fn bindings(&self) -> Vec<(&str, &Span)> {
+        match self {
+            AssignTarget::Variable { name, span } => vec![(name.as_str(), span)],
+            AssignTarget::Ignore { .. } => Vec::new(),
+            AssignTarget::Starred { target, .. } => target.bindings(),
+            AssignTarget::Nested { targets, .. } => targets.iter().flat_map(AssignTarget::bindings).collect(),
         }
     }
 }
@@ -162,21 +260,25 @@ impl Expr {
             Expr::Assign { span, .. } => span,
             Expr::MultiAssign { span, .. } => span,
             Expr::Binary { span, .. } => span,
+            Expr::Comparison { span, .. } => span,
             Expr::Unary { span, .. } => span,
             Expr::Logical { span, .. } => span,
+            Expr::Conditional { span, .. } => span,
             Expr::Call { span, .. } => span,
             Expr::CallWithKwargs { span, .. } => span,
             Expr::MultiReturn { span, .. } => span,
             Expr::Grouping { span, .. } => span,
             Expr::List { span, .. } => span,
             Expr::Dict { span, .. } => span,
+            Expr::Set { span, .. } => span,
             Expr::PropertyAccess { span, .. } => span,
             Expr::Tuple { span, .. } => span,
+            Expr::Match { span, .. } => span,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinaryOp {
     Add,
     Subtract,
@@ -184,21 +286,52 @@ pub enum BinaryOp {
     Divide,
     Equal,
     NotEqual,
+    /// Identity: the same list or dictionary, or equal immutable values
+    Is,
+    IsNot,
     Less,
     LessEqual,
     Greater,
     GreaterEqual,
     In,
+    NotIn,
+    /// `|` on sets
+    Union,
+    /// `&` on sets
+    Intersection,
+    /// `^` on sets
+    SymmetricDifference,
 }
 
-#[derive(Debug, Clone)]
+impl BinaryOp {
+    /// Operators that compare their operands and can be chained, as in `a < b < c`
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Equal
+                | BinaryOp::NotEqual
+                | BinaryOp::Is
+                | BinaryOp::IsNot
+                | BinaryOp::Less
+                | BinaryOp::LessEqual
+                | BinaryOp::Greater
+                | BinaryOp::GreaterEqual
+                | BinaryOp::In
+                | BinaryOp::NotIn
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UnaryOp {
     Negate,
     Not,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LogicalOp {
     And,
     Or,
+    /// `a ?? b`: `a` unless it is nil
+    Coalesce,
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+use crate::ast::{AssignTarget, BinaryOp, Pattern, UnaryOp};
+use crate::error::Span;
+use crate::value::Value;
+
+/// One VM instruction. Operands are indices into the tables of the owning `Chunk`
+/// (constants, names, spans and the call/unpack/assert site tables) or, for jumps,
+/// absolute instruction offsets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    /// Push a constant
+    Constant(u32),
+    /// Discard the top of the stack
+    Pop,
+    /// Exchange the top two values
+    Swap,
+    /// Push a variable's value; `span` is where an undefined-variable error points
+    GetVariable { name: u32, span: u32 },
+    /// Assign the top of the stack to a variable, leaving the value in place
+    SetVariable { name: u32 },
+    /// Destructure the top of the stack into the targets of an unpack site, leaving the value in place
+    Unpack(u32),
+    /// Pop two operands and push the result; `left` and `right` are the operand spans for type labels
+    Binary { operator: BinaryOp, span: u32, left: u32, right: u32 },
+    /// Like `Binary`, but leave the right operand below the result for the next
+    /// comparison of a chain such as `a < b < c`
+    ChainCompare { operator: BinaryOp, span: u32, left: u32, right: u32 },
+    /// Pop one operand and push the result
+    Unary { operator: UnaryOp, span: u32, operand: u32 },
+    Jump(u32),
+    /// Pop the condition and jump if it is falsy
+    JumpIfFalse(u32),
+    /// Pop the condition and jump if it is truthy
+    JumpIfTrue(u32),
+    /// Short-circuit `or`: keep a truthy value and jump, otherwise pop it
+    JumpIfTrueOrPop(u32),
+    /// Short-circuit `and`: keep a falsy value and jump, otherwise pop it
+    JumpIfFalseOrPop(u32),
+    /// Short-circuit `??`: keep a value that is not nil and jump, otherwise pop it
+    JumpIfNotNilOrPop(u32),
+    /// Optional chaining `?.`: jump if the top of the stack is nil, leaving it in place
+    JumpIfNil(u32),
+    /// Pop that many values into a list
+    BuildList(u32),
+    /// Pop that many values into a tuple
+    BuildTuple(u32),
+    /// Push an empty dictionary
+    NewDict,
+    /// Pop a value and a key and insert them into the dictionary below
+    DictInsert { span: u32, key: u32 },
+    /// Push an empty set
+    NewSet,
+    /// Pop an element and insert it into the set below
+    SetInsert { span: u32, element: u32 },
+    /// Replace an object with one of its properties
+    GetProperty { name: u32, span: u32, object: u32 },
+    /// Pop a callee and start a call described by a call site, checking its arguments
+    Call(u32),
+    /// Pop an object and start a call of its method `name`, or of its property of that
+    /// name when the object has no methods; `span` covers `object.name`
+    CallMethod { name: u32, span: u32, object: u32, site: u32 },
+    /// Jump to `skip` if the active call does not evaluate positional argument `index`
+    Argument { index: u32, skip: u32 },
+    /// Hand the evaluated positional argument `index` to the active call
+    ArgumentDone(u32),
+    /// Check that keyword argument `index` of the active call is accepted
+    Keyword(u32),
+    /// Hand the evaluated keyword argument `index` to the active call
+    KeywordDone(u32),
+    /// Finish the active call and push its result
+    CallEnd,
+    /// Compare the two values on the stack; on success pop both and jump to `skip`
+    AssertCompare { operator: BinaryOp, span: u32, skip: u32 },
+    /// Raise the failure described by an assert site
+    AssertFail(u32),
+    /// Test the value on top of the stack against a match site's pattern, leaving it in
+    /// place; on success assign the pattern's names and push true, otherwise push false
+    MatchPattern(u32),
+    /// Raise the error for a match subject, on top of the stack, that no arm accepted
+    NoMatch { span: u32 },
+}
+
+/// Static description of a call expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSite {
+    pub span: u32,
+    /// Span of each positional argument
+    pub arguments: Vec<u32>,
+    pub keywords: Vec<KeywordSite>,
+    /// Whether the call was written with keyword arguments syntax
+    pub with_keywords: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordSite {
+    pub name: u32,
+    /// Span of the whole `name=value` argument
+    pub span: u32,
+    /// Span of the value alone
+    pub value: u32,
+}
+
+/// Static description of a multi-assignment
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnpackSite {
+    pub targets: Vec<AssignTarget>,
+    /// Name index of each name the targets bind, in the order of `AssignTarget::bindings`
+    pub names: Vec<u32>,
+    pub span: u32,
+    pub value: u32,
+}
+
+/// Static description of a failing assert
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssertSite {
+    /// Span of the condition
+    pub span: u32,
+    /// Whether a message value sits on top of the stack
+    pub message: bool,
+    /// Operand spans of a comparison, whose values sit below the message
+    pub operands: Option<(u32, u32)>,
+}
+
+/// Static description of a match arm's pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchSite {
+    pub pattern: Pattern,
+    /// Name index of each name the pattern binds, in the order of `Pattern::bindings`
+    pub names: Vec<u32>,
+}
+
+/// A compiled program
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    /// Span index of the statement or expression each instruction was compiled from
+    pub locations: Vec<u32>,
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub spans: Vec<Span>,
+    pub calls: Vec<CallSite>,
+    pub unpacks: Vec<UnpackSite>,
+    pub asserts: Vec<AssertSite>,
+    pub matches: Vec<MatchSite>,
+}
+
+impl Chunk {
+    pub fn span(&self, index: u32) -> &Span {
+        &self.spans[index as usize]
+    }
+
+    pub fn name(&self, index: u32) -> &str {
+        &self.names[index as usize]
+    }
+}
@@ -0,0 +1,766 @@
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::bigint::BigInt;
+use crate::ast::{AssignTarget, Pattern};
+use crate::bytecode::{AssertSite, CallSite, Chunk, Instruction, KeywordSite, MatchSite, UnpackSite};
+use crate::error::Span;
+use crate::ordered_map::OrderedMap;
+use crate::value::{CaseResult, Key, Value};
+use std::fmt;
+
+/// First bytes of every compiled script
+pub const MAGIC: &[u8; 4] = b"BCCO";
+
+/// Bumped whenever the layout or the instruction set changes; older files must be recompiled
+pub const FORMAT_VERSION: u16 = 10;
+
+/// A script compiled by `bcc compile`, stored as a `.bcco` file.
+///
+/// The source text travels with the bytecode so runtime diagnostics can still show
+/// the offending line, but running a compiled script never lexes or parses it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledScript {
+    /// Name of the source file, used in diagnostics
+    pub filename: String,
+    pub source: String,
+    pub chunk: Chunk,
+}
+
+/// Why a `.bcco` file could not be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// The data does not start with `MAGIC`
+    NotCompiled,
+    /// Written by a different version of the format
+    UnsupportedVersion(u16),
+    /// The data ends in the middle of a value
+    Truncated,
+    /// The data is structurally invalid
+    Corrupt(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::NotCompiled => write!(f, "not a compiled bcc script"),
+            LoadError::UnsupportedVersion(version) => write!(
+                f,
+                "compiled with format version {}, but this bcc reads version {}; recompile the script",
+                version, FORMAT_VERSION
+            ),
+            LoadError::Truncated => write!(f, "compiled script is truncated"),
+            LoadError::Corrupt(reason) => write!(f, "compiled script is corrupt: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl CompiledScript {
+    /// Whether `bytes` look like a compiled script rather than source text
+    pub fn is_compiled(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u16(FORMAT_VERSION);
+        writer.string(&self.filename);
+        writer.string(&self.source);
+
+        let chunk = &self.chunk;
+        writer.u32(chunk.code.len() as u32);
+        for (instruction, location) in chunk.code.iter().zip(&chunk.locations) {
+            writer.instruction(instruction);
+            writer.u32(*location);
+        }
+        writer.u32(chunk.constants.len() as u32);
+        for constant in &chunk.constants {
+            writer.value(constant);
+        }
+        writer.u32(chunk.names.len() as u32);
+        for name in &chunk.names {
+            writer.string(name);
+        }
+        writer.u32(chunk.spans.len() as u32);
+        for span in &chunk.spans {
+            writer.span(span);
+        }
+        writer.u32(chunk.calls.len() as u32);
+        for call in &chunk.calls {
+            writer.u32(call.span);
+            writer.u32s(&call.arguments);
+            writer.u32(call.keywords.len() as u32);
+            for keyword in &call.keywords {
+                writer.u32(keyword.name);
+                writer.u32(keyword.span);
+                writer.u32(keyword.value);
+            }
+            writer.u8(call.with_keywords as u8);
+        }
+        writer.u32(chunk.unpacks.len() as u32);
+        for unpack in &chunk.unpacks {
+            writer.u32(unpack.targets.len() as u32);
+            for target in &unpack.targets {
+                writer.assign_target(target);
+            }
+            writer.u32s(&unpack.names);
+            writer.u32(unpack.span);
+            writer.u32(unpack.value);
+        }
+        writer.u32(chunk.asserts.len() as u32);
+        for site in &chunk.asserts {
+            writer.u32(site.span);
+            writer.u8(site.message as u8);
+            match site.operands {
+                Some((left, right)) => {
+                    writer.u8(1);
+                    writer.u32(left);
+                    writer.u32(right);
+                }
+                None => writer.u8(0),
+            }
+        }
+        writer.u32(chunk.matches.len() as u32);
+        for site in &chunk.matches {
+            writer.pattern(&site.pattern);
+            writer.u32s(&site.names);
+        }
+        writer.bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LoadError> {
+        if !Self::is_compiled(bytes) {
+            return Err(LoadError::NotCompiled);
+        }
+        let mut reader = Reader { bytes, position: MAGIC.len() };
+        let version = reader.u16()?;
+        if version != FORMAT_VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        let filename = reader.string()?;
+        let source = reader.string()?;
+
+        let mut chunk = Chunk::default();
+        for _ in 0..reader.u32()? {
+            chunk.code.push(reader.instruction()?);
+            chunk.locations.push(reader.u32()?);
+        }
+        for _ in 0..reader.u32()? {
+            chunk.constants.push(reader.value()?);
+        }
+        for _ in 0..reader.u32()? {
+            chunk.names.push(reader.string()?);
+        }
+        for _ in 0..reader.u32()? {
+            chunk.spans.push(reader.span()?);
+        }
+        for _ in 0..reader.u32()? {
+            let span = reader.u32()?;
+            let arguments = reader.u32s()?;
+            let mut keywords = Vec::new();
+            for _ in 0..reader.u32()? {
+                keywords.push(KeywordSite {
+                    name: reader.u32()?,
+                    span: reader.u32()?,
+                    value: reader.u32()?,
+                });
+            }
+            let with_keywords = reader.bool()?;
+            chunk.calls.push(CallSite { span, arguments, keywords, with_keywords });
+        }
+        for _ in 0..reader.u32()? {
+            let targets = (0..reader.u32()?).map(|_| reader.assign_target()).collect::<Result<_, _>>()?;
+            let names = reader.u32s()?;
+            let span = reader.u32()?;
+            let value = reader.u32()?;
+            chunk.unpacks.push(UnpackSite { targets, names, span, value });
+        }
+        for _ in 0..reader.u32()? {
+            let span = reader.u32()?;
+            let message = reader.bool()?;
+            let operands = match reader.bool()? {
+                true => Some((reader.u32()?, reader.u32()?)),
+                false => None,
+            };
+            chunk.asserts.push(AssertSite { span, message, operands });
+        }
+        for _ in 0..reader.u32()? {
+            let pattern = reader.pattern()?;
+            let names = reader.u32s()?;
+            chunk.matches.push(MatchSite { pattern, names });
+        }
+        if reader.position != bytes.len() {
+            return Err(LoadError::Corrupt("unexpected data after the last table".to_string()));
+        }
+
+        validate(&chunk, source.len())?;
+        Ok(Self { filename, source, chunk })
+    }
+}
+
+/// Check every table index and jump target a chunk refers to, so a damaged file fails
+/// to load instead of crashing the VM. The stack discipline of the code itself is
+/// trusted to be what the compiler produced.
+fn validate(chunk: &Chunk, source_len: usize) -> Result<(), LoadError> {
+    let check = |index: u32, len: usize, table: &str| {
+        if (index as usize) < len {
+            Ok(())
+        } else {
+            Err(LoadError::Corrupt(format!("{} index {} out of range", table, index)))
+        }
+    };
+    let span = |index: u32| check(index, chunk.spans.len(), "span");
+    let name = |index: u32| check(index, chunk.names.len(), "name");
+    // Jumping to the very end of the code finishes the program
+    let target = |offset: u32| check(offset, chunk.code.len() + 1, "jump target");
+
+    for (instruction, location) in chunk.code.iter().zip(&chunk.locations) {
+        span(*location)?;
+        match *instruction {
+            Instruction::Constant(index) => check(index, chunk.constants.len(), "constant")?,
+            Instruction::GetVariable { name: index, span: at } => {
+                name(index)?;
+                span(at)?;
+            }
+            Instruction::SetVariable { name: index } => name(index)?,
+            Instruction::Unpack(site) => check(site, chunk.unpacks.len(), "unpack site")?,
+            Instruction::Binary { span: at, left, right, .. } | Instruction::ChainCompare { span: at, left, right, .. } => {
+                span(at)?;
+                span(left)?;
+                span(right)?;
+            }
+            Instruction::Unary { span: at, operand, .. } => {
+                span(at)?;
+                span(operand)?;
+            }
+            Instruction::Jump(offset)
+            | Instruction::JumpIfFalse(offset)
+            | Instruction::JumpIfTrue(offset)
+            | Instruction::JumpIfTrueOrPop(offset)
+            | Instruction::JumpIfFalseOrPop(offset)
+            | Instruction::JumpIfNotNilOrPop(offset)
+            | Instruction::JumpIfNil(offset)
+            | Instruction::Argument { skip: offset, .. } => target(offset)?,
+            Instruction::DictInsert { span: at, key } | Instruction::SetInsert { span: at, element: key } => {
+                span(at)?;
+                span(key)?;
+            }
+            Instruction::GetProperty { name: index, span: at, object } => {
+                name(index)?;
+                span(at)?;
+                span(object)?;
+            }
+            Instruction::Call(site) => check(site, chunk.calls.len(), "call site")?,
+            Instruction::CallMethod { name: index, span: at, object, site } => {
+                name(index)?;
+                span(at)?;
+                span(object)?;
+                check(site, chunk.calls.len(), "call site")?;
+            }
+            Instruction::AssertCompare { span: at, skip, .. } => {
+                span(at)?;
+                target(skip)?;
+            }
+            Instruction::AssertFail(site) => check(site, chunk.asserts.len(), "assert site")?,
+            Instruction::MatchPattern(site) => check(site, chunk.matches.len(), "match site")?,
+            Instruction::NoMatch { span: at } => span(at)?,
+            Instruction::Pop
+            | Instruction::BuildList(_)
+            | Instruction::BuildTuple(_)
+            | Instruction::NewDict
+            | Instruction::NewSet
+            | Instruction::Swap
+            | Instruction::ArgumentDone(_)
+            | Instruction::Keyword(_)
+            | Instruction::KeywordDone(_)
+            | Instruction::CallEnd => {}
+        }
+    }
+    for call in &chunk.calls {
+        span(call.span)?;
+        for argument in &call.arguments {
+            span(*argument)?;
+        }
+        for keyword in &call.keywords {
+            name(keyword.name)?;
+            span(keyword.span)?;
+            span(keyword.value)?;
+        }
+    }
+    for unpack in &chunk.unpacks {
+        span(unpack.span)?;
+        span(unpack.value)?;
+        if unpack.names.len() != unpack.targets.iter().flat_map(AssignTarget::bindings).count() {
+            return Err(LoadError::Corrupt("unpack site names do not fit its targets".to_string()));
+        }
+        for target in &unpack.names {
+            name(*target)?;
+        }
+    }
+    for site in &chunk.asserts {
+        span(site.span)?;
+        if let Some((left, right)) = site.operands {
+            span(left)?;
+            span(right)?;
+        }
+    }
+    for site in &chunk.matches {
+        if site.names.len() != site.pattern.bindings().len() {
+            return Err(LoadError::Corrupt("match site names do not fit its pattern".to_string()));
+        }
+        for name in &site.names {
+            check(*name, chunk.names.len(), "name")?;
+        }
+    }
+    if chunk.spans.iter().any(|span| span.start > span.end || span.end > source_len) {
+        return Err(LoadError::Corrupt("span outside the source".to_string()));
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32s(&mut self, values: &[u32]) {
+        self.u32(values.len() as u32);
+        for value in values {
+            self.u32(*value);
+        }
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Nil => self.u8(0),
+            Value::Bool(b) => {
+                self.u8(1);
+                self.u8(*b as u8);
+            }
+            Value::Int(n) => {
+                self.u8(2);
+                self.u64(*n as u64);
+            }
+            Value::Double(n) => {
+                self.u8(3);
+                self.u64(n.to_bits());
+            }
+            Value::String(s) => {
+                self.u8(4);
+                self.string(s);
+            }
+            Value::List(items) => {
+                self.u8(5);
+                self.values(&items.borrow());
+            }
+            Value::Dict(dict) => {
+                self.u8(6);
+                let dict = dict.borrow();
+                self.u32(dict.len() as u32);
+                for (key, value) in dict.iter() {
+                    self.value(&key.to_value());
+                    self.value(value);
+                }
+            }
+            Value::CaseResult(case_result) => {
+                self.u8(7);
+                self.value(&case_result.result);
+            }
+            Value::Tuple(items) => {
+                self.u8(8);
+                self.values(items);
+            }
+            Value::BigInt(n) => {
+                self.u8(10);
+                self.string(&n.to_string());
+            }
+            Value::Set(set) => {
+                self.u8(9);
+                let set = set.borrow();
+                self.u32(set.len() as u32);
+                for element in set.keys() {
+                    self.value(&element.to_value());
+                }
+            }
+        }
+    }
+
+    fn values(&mut self, values: &[Value]) {
+        self.u32(values.len() as u32);
+        for value in values {
+            self.value(value);
+        }
+    }
+
+    fn span(&mut self, span: &Span) {
+        self.u64(span.start as u64);
+        self.u64(span.end as u64);
+    }
+
+    /// Patterns keep their own names, literals and spans rather than table indices
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Target(target) => self.assign_target(target),
+            Pattern::Literal { value, span } => {
+                self.u8(2);
+                self.value(value);
+                self.span(span);
+            }
+            Pattern::Sequence { elements, span } => {
+                self.u8(3);
+                self.u32(elements.len() as u32);
+                for element in elements {
+                    self.pattern(element);
+                }
+                self.span(span);
+            }
+            Pattern::Dict { entries, span } => {
+                self.u8(4);
+                self.u32(entries.len() as u32);
+                for (key, value) in entries {
+                    self.value(key);
+                    self.pattern(value);
+                }
+                self.span(span);
+            }
+        }
+    }
+
+    /// Targets share their tags with patterns, so a pattern's targets need no tag of their own
+    fn assign_target(&mut self, target: &AssignTarget) {
+        match target {
+            AssignTarget::Ignore { span } => {
+                self.u8(0);
+                self.span(span);
+            }
+            AssignTarget::Variable { name, span } => {
+                self.u8(1);
+                self.string(name);
+                self.span(span);
+            }
+            AssignTarget::Starred { target, span } => {
+                self.u8(5);
+                self.assign_target(target);
+                self.span(span);
+            }
+            AssignTarget::Nested { targets, span } => {
+                self.u8(6);
+                self.u32(targets.len() as u32);
+                for target in targets {
+                    self.assign_target(target);
+                }
+                self.span(span);
+            }
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match *instruction {
+            Instruction::Constant(index) => self.op(0, &[index]),
+            Instruction::Pop => self.op(1, &[]),
+            Instruction::GetVariable { name, span } => self.op(2, &[name, span]),
+            Instruction::SetVariable { name } => self.op(3, &[name]),
+            Instruction::Unpack(site) => self.op(4, &[site]),
+            Instruction::Binary { operator, span, left, right } => {
+                self.op(5, &[span, left, right]);
+                self.u8(binary_code(operator));
+            }
+            Instruction::Unary { operator, span, operand } => {
+                self.op(6, &[span, operand]);
+                self.u8(unary_code(operator));
+            }
+            Instruction::Jump(target) => self.op(7, &[target]),
+            Instruction::JumpIfFalse(target) => self.op(8, &[target]),
+            Instruction::JumpIfTrue(target) => self.op(9, &[target]),
+            Instruction::JumpIfTrueOrPop(target) => self.op(10, &[target]),
+            Instruction::JumpIfFalseOrPop(target) => self.op(11, &[target]),
+            Instruction::BuildList(count) => self.op(14, &[count]),
+            Instruction::BuildTuple(count) => self.op(15, &[count]),
+            Instruction::NewDict => self.op(16, &[]),
+            Instruction::DictInsert { span, key } => self.op(17, &[span, key]),
+            Instruction::GetProperty { name, span, object } => self.op(18, &[name, span, object]),
+            Instruction::Call(site) => self.op(19, &[site]),
+            Instruction::Argument { index, skip } => self.op(20, &[index, skip]),
+            Instruction::ArgumentDone(index) => self.op(21, &[index]),
+            Instruction::Keyword(index) => self.op(22, &[index]),
+            Instruction::KeywordDone(index) => self.op(23, &[index]),
+            Instruction::CallEnd => self.op(24, &[]),
+            Instruction::AssertCompare { operator, span, skip } => {
+                self.op(25, &[span, skip]);
+                self.u8(binary_code(operator));
+            }
+            Instruction::AssertFail(site) => self.op(26, &[site]),
+            Instruction::CallMethod { name, span, object, site } => self.op(27, &[name, span, object, site]),
+            Instruction::NewSet => self.op(28, &[]),
+            Instruction::SetInsert { span, element } => self.op(29, &[span, element]),
+            Instruction::Swap => self.op(30, &[]),
+            Instruction::ChainCompare { operator, span, left, right } => {
+                self.op(31, &[span, left, right]);
+                self.u8(binary_code(operator));
+            }
+            Instruction::JumpIfNotNilOrPop(target) => self.op(32, &[target]),
+            Instruction::JumpIfNil(target) => self.op(33, &[target]),
+            Instruction::MatchPattern(site) => self.op(34, &[site]),
+            Instruction::NoMatch { span } => self.op(35, &[span]),
+        }
+    }
+
+    fn op(&mut self, opcode: u8, operands: &[u32]) {
+        self.u8(opcode);
+        for operand in operands {
+            self.u32(*operand);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], LoadError> {
+        let end = self.position.checked_add(N).ok_or(LoadError::Truncated)?;
+        let bytes = self.bytes.get(self.position..end).ok_or(LoadError::Truncated)?;
+        self.position = end;
+        Ok(bytes.try_into().expect("slice has the requested length"))
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, LoadError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(LoadError::Corrupt(format!("invalid flag {}", other))),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, LoadError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn u32s(&mut self) -> Result<Vec<u32>, LoadError> {
+        (0..self.u32()?).map(|_| self.u32()).collect()
+    }
+
+    fn string(&mut self) -> Result<String, LoadError> {
+        let len = self.u32()? as usize;
+        let end = self.position.checked_add(len).ok_or(LoadError::Truncated)?;
+        let bytes = self.bytes.get(self.position..end).ok_or(LoadError::Truncated)?;
+        self.position = end;
+        String::from_utf8(bytes.to_vec()).map_err(|_| LoadError::Corrupt("string is not valid UTF-8".to_string()))
+    }
+
+    fn value(&mut self) -> Result<Value, LoadError> {
+        Ok(match self.u8()? {
+            0 => Value::Nil,
+            1 => Value::Bool(self.bool()?),
+            2 => Value::Int(self.u64()? as i64),
+            3 => Value::Double(f64::from_bits(self.u64()?)),
+            4 => Value::String(self.string()?),
+            5 => Value::list(self.values()?),
+            6 => {
+                let mut dict = OrderedMap::new();
+                for _ in 0..self.u32()? {
+                    let key = Key::from_value(&self.value()?)
+                        .map_err(|unhashable| LoadError::Corrupt(format!("{} used as a dictionary key", unhashable)))?;
+                    dict.insert(key, self.value()?);
+                }
+                Value::dict(dict)
+            }
+            7 => Value::CaseResult(CaseResult { result: Box::new(self.value()?) }),
+            8 => Value::Tuple(self.values()?),
+            9 => {
+                let mut set = OrderedMap::new();
+                for _ in 0..self.u32()? {
+                    let element = Key::from_value(&self.value()?)
+                        .map_err(|unhashable| LoadError::Corrupt(format!("{} used as a set element", unhashable)))?;
+                    set.insert(element, ());
+                }
+                Value::set(set)
+            }
+            10 => {
+                let digits = self.string()?;
+                let n = digits.parse::<BigInt>().map_err(|_| LoadError::Corrupt(format!("invalid integer {}", digits)))?;
+                Value::integer(n)
+            }
+            tag => return Err(LoadError::Corrupt(format!("unknown value tag {}", tag))),
+        })
+    }
+
+    fn values(&mut self) -> Result<Vec<Value>, LoadError> {
+        (0..self.u32()?).map(|_| self.value()).collect()
+    }
+
+    fn span(&mut self) -> Result<Span, LoadError> {
+        let start = self.u64()? as usize;
+        let end = self.u64()? as usize;
+        Ok(Span::new(start, end))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, LoadError> {
+        Ok(match self.u8()? {
+            2 => Pattern::Literal { value: self.value()?, span: self.span()? },
+            3 => {
+                let elements = (0..self.u32()?).map(|_| self.pattern()).collect::<Result<_, _>>()?;
+                Pattern::Sequence { elements, span: self.span()? }
+            }
+            4 => {
+                let mut entries = Vec::new();
+                for _ in 0..self.u32()? {
+                    entries.push((self.value()?, self.pattern()?));
+                }
+                Pattern::Dict { entries, span: self.span()? }
+            }
+            tag => Pattern::Target(self.tagged_assign_target(tag)?),
+        })
+    }
+
+    fn assign_target(&mut self) -> Result<AssignTarget, LoadError> {
+        let tag = self.u8()?;
+        self.tagged_assign_target(tag)
+    }
+
+    fn tagged_assign_target(&mut self, tag: u8) -> Result<AssignTarget, LoadError> {
+        Ok(match tag {
+            0 => AssignTarget::Ignore { span: self.span()? },
+            1 => AssignTarget::Variable { name: self.string()?, span: self.span()? },
+            5 => AssignTarget::Starred { target: Box::new(self.assign_target()?), span: self.span()? },
+            6 => {
+                let targets = (0..self.u32()?).map(|_| self.assign_target()).collect::<Result<_, _>>()?;
+                AssignTarget::Nested { targets, span: self.span()? }
+            }
+            tag => return Err(LoadError::Corrupt(format!("unknown pattern tag {}", tag))),
+        })
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, LoadError> {
+        Ok(match self.u8()? {
+            0 => Instruction::Constant(self.u32()?),
+            1 => Instruction::Pop,
+            2 => Instruction::GetVariable { name: self.u32()?, span: self.u32()? },
+            3 => Instruction::SetVariable { name: self.u32()? },
+            4 => Instruction::Unpack(self.u32()?),
+            5 => {
+                let (span, left, right) = (self.u32()?, self.u32()?, self.u32()?);
+                Instruction::Binary { operator: binary_operator(self.u8()?)?, span, left, right }
+            }
+            6 => {
+                let (span, operand) = (self.u32()?, self.u32()?);
+                Instruction::Unary { operator: unary_operator(self.u8()?)?, span, operand }
+            }
+            7 => Instruction::Jump(self.u32()?),
+            8 => Instruction::JumpIfFalse(self.u32()?),
+            9 => Instruction::JumpIfTrue(self.u32()?),
+            10 => Instruction::JumpIfTrueOrPop(self.u32()?),
+            11 => Instruction::JumpIfFalseOrPop(self.u32()?),
+            14 => Instruction::BuildList(self.u32()?),
+            15 => Instruction::BuildTuple(self.u32()?),
+            16 => Instruction::NewDict,
+            17 => Instruction::DictInsert { span: self.u32()?, key: self.u32()? },
+            18 => Instruction::GetProperty { name: self.u32()?, span: self.u32()?, object: self.u32()? },
+            19 => Instruction::Call(self.u32()?),
+            20 => Instruction::Argument { index: self.u32()?, skip: self.u32()? },
+            21 => Instruction::ArgumentDone(self.u32()?),
+            22 => Instruction::Keyword(self.u32()?),
+            23 => Instruction::KeywordDone(self.u32()?),
+            24 => Instruction::CallEnd,
+            25 => {
+                let (span, skip) = (self.u32()?, self.u32()?);
+                Instruction::AssertCompare { operator: binary_operator(self.u8()?)?, span, skip }
+            }
+            26 => Instruction::AssertFail(self.u32()?),
+            27 => Instruction::CallMethod { name: self.u32()?, span: self.u32()?, object: self.u32()?, site: self.u32()? },
+            28 => Instruction::NewSet,
+            29 => Instruction::SetInsert { span: self.u32()?, element: self.u32()? },
+            30 => Instruction::Swap,
+            31 => {
+                let (span, left, right) = (self.u32()?, self.u32()?, self.u32()?);
+                Instruction::ChainCompare { operator: binary_operator(self.u8()?)?, span, left, right }
+            }
+            32 => Instruction::JumpIfNotNilOrPop(self.u32()?),
+            33 => Instruction::JumpIfNil(self.u32()?),
+            34 => Instruction::MatchPattern(self.u32()?),
+            35 => Instruction::NoMatch { span: self.u32()? },
+            opcode => return Err(LoadError::Corrupt(format!("unknown opcode {}", opcode))),
+        })
+    }
+}
+
+const BINARY_OPERATORS: [BinaryOp; 17] = [
+    BinaryOp::Add,
+    BinaryOp::Subtract,
+    BinaryOp::Multiply,
+    BinaryOp::Divide,
+    BinaryOp::Equal,
+    BinaryOp::NotEqual,
+    BinaryOp::Less,
+    BinaryOp::LessEqual,
+    BinaryOp::Greater,
+    BinaryOp::GreaterEqual,
+    BinaryOp::In,
+    BinaryOp::Is,
+    BinaryOp::Union,
+    BinaryOp::Intersection,
+    BinaryOp::SymmetricDifference,
+    BinaryOp::IsNot,
+    BinaryOp::NotIn,
+];
+
+const UNARY_OPERATORS: [UnaryOp; 2] = [UnaryOp::Negate, UnaryOp::Not];
+
+fn binary_code(operator: BinaryOp) -> u8 {
+    BINARY_OPERATORS.iter().position(|&op| op == operator).expect("every operator has a code") as u8
+}
+
+fn binary_operator(code: u8) -> Result<BinaryOp, LoadError> {
+    BINARY_OPERATORS
+        .get(code as usize)
+        .copied()
+        .ok_or_else(|| LoadError::Corrupt(format!("unknown binary operator {}", code)))
+}
+
+fn unary_code(operator: UnaryOp) -> u8 {
+    UNARY_OPERATORS.iter().position(|&op| op == operator).expect("every operator has a code") as u8
+}
+
+fn unary_operator(code: u8) -> Result<UnaryOp, LoadError> {
+    UNARY_OPERATORS
+        .get(code as usize)
+        .copied()
+        .ok_or_else(|| LoadError::Corrupt(format!("unknown unary operator {}", code)))
+}
@@ -0,0 +1,438 @@
+use crate::ast::{AssignTarget, Expr, LogicalOp, Program, Stmt};
+use crate::bytecode::{AssertSite, CallSite, Chunk, Instruction, KeywordSite, MatchSite, UnpackSite};
+use crate::error::Span;
+use crate::value::Value;
+use std::collections::HashMap;
+
+/// Compiles a program into bytecode for the `Vm`.
+///
+/// Every span the evaluator would put on a diagnostic is recorded in the chunk, so
+/// both backends report the same errors at the same places.
+pub struct Compiler {
+    chunk: Chunk,
+    names: HashMap<String, u32>,
+    spans: HashMap<(usize, usize), u32>,
+    /// Span of the innermost statement or expression being compiled
+    location: u32,
+}
+
+impl Compiler {
+    pub fn compile(program: &Program) -> Chunk {
+        let mut compiler = Self {
+            chunk: Chunk::default(),
+            names: HashMap::new(),
+            spans: HashMap::new(),
+            location: 0,
+        };
+        for statement in &program.statements {
+            compiler.compile_statement(statement);
+        }
+        compiler.chunk
+    }
+
+    fn compile_statement(&mut self, stmt: &Stmt) {
+        let enclosing = self.enter(stmt.span());
+        match stmt {
+            Stmt::Expression { expr, .. } => {
+                self.compile_expression(expr);
+                self.emit(Instruction::Pop);
+            }
+            Stmt::Block { statements, .. } => {
+                // New names always go to the global scope, so a block needs no instructions of its own
+                for statement in statements {
+                    self.compile_statement(statement);
+                }
+            }
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                self.compile_expression(condition);
+                let to_else = self.emit_jump(Instruction::JumpIfFalse);
+                self.compile_statement(then_branch);
+                match else_branch {
+                    Some(else_branch) => {
+                        let to_end = self.emit_jump(Instruction::Jump);
+                        self.patch(to_else);
+                        self.compile_statement(else_branch);
+                        self.patch(to_end);
+                    }
+                    None => self.patch(to_else),
+                }
+            }
+            Stmt::While { condition, body, .. } => {
+                let start = self.offset();
+                self.compile_expression(condition);
+                let to_end = self.emit_jump(Instruction::JumpIfFalse);
+                self.compile_statement(body);
+                self.emit(Instruction::Jump(start));
+                self.patch(to_end);
+            }
+            Stmt::For { initializer, condition, increment, body, .. } => {
+                // The initializer runs in the enclosing scope, as in the evaluator
+                if let Some(initializer) = initializer {
+                    self.compile_statement(initializer);
+                }
+                let start = self.offset();
+                let to_end = condition.as_ref().map(|condition| {
+                    self.compile_expression(condition);
+                    self.emit_jump(Instruction::JumpIfFalse)
+                });
+                self.compile_statement(body);
+                if let Some(increment) = increment {
+                    self.compile_expression(increment);
+                    self.emit(Instruction::Pop);
+                }
+                self.emit(Instruction::Jump(start));
+                if let Some(to_end) = to_end {
+                    self.patch(to_end);
+                }
+            }
+            Stmt::Assert { condition, message, .. } => self.compile_assert(condition, message.as_ref()),
+        }
+        self.location = enclosing;
+    }
+
+    fn compile_assert(&mut self, condition: &Expr, message: Option<&Expr>) {
+        // Comparisons keep both operands on the stack so a failure can label them
+        if let Expr::Binary { left, operator, right, span } = condition {
+            if operator.is_comparison() {
+                self.compile_expression(left);
+                self.compile_expression(right);
+                let span = self.span(span);
+                let to_end = self.emit(Instruction::AssertCompare { operator: *operator, span, skip: 0 });
+                let operands = Some((self.span(left.span()), self.span(right.span())));
+                self.compile_assert_failure(span, message, operands);
+                self.patch(to_end);
+                return;
+            }
+        }
+
+        self.compile_expression(condition);
+        let to_end = self.emit_jump(Instruction::JumpIfTrue);
+        let span = self.span(condition.span());
+        self.compile_assert_failure(span, message, None);
+        self.patch(to_end);
+    }
+
+    fn compile_assert_failure(&mut self, span: u32, message: Option<&Expr>, operands: Option<(u32, u32)>) {
+        if let Some(message) = message {
+            self.compile_expression(message);
+        }
+        let site = self.chunk.asserts.len() as u32;
+        self.chunk.asserts.push(AssertSite {
+            span,
+            message: message.is_some(),
+            operands,
+        });
+        self.emit(Instruction::AssertFail(site));
+    }
+
+    fn compile_expression(&mut self, expr: &Expr) {
+        let enclosing = self.enter(expr.span());
+        match expr {
+            Expr::Literal { value, .. } => {
+                let constant = self.constant(value.clone());
+                self.emit(Instruction::Constant(constant));
+            }
+            Expr::Variable { name, span } => {
+                let name = self.name(name);
+                let span = self.span(span);
+                self.emit(Instruction::GetVariable { name, span });
+            }
+            Expr::Assign { name, value, .. } => {
+                self.compile_expression(value);
+                let name = self.name(name);
+                self.emit(Instruction::SetVariable { name });
+            }
+            Expr::MultiAssign { targets, value, span } => {
+                self.compile_expression(value);
+                let names = targets.iter().flat_map(AssignTarget::bindings).map(|(name, _)| self.name(name)).collect();
+                let site = UnpackSite {
+                    targets: targets.clone(),
+                    names,
+                    span: self.span(span),
+                    value: self.span(value.span()),
+                };
+                self.chunk.unpacks.push(site);
+                self.emit(Instruction::Unpack(self.chunk.unpacks.len() as u32 - 1));
+            }
+            Expr::Binary { left, operator, right, span } => {
+                self.compile_expression(left);
+                self.compile_expression(right);
+                let instruction = Instruction::Binary {
+                    operator: *operator,
+                    span: self.span(span),
+                    left: self.span(left.span()),
+                    right: self.span(right.span()),
+                };
+                self.emit(instruction);
+            }
+            Expr::Comparison { operands, operators, .. } => {
+                self.compile_expression(&operands[0]);
+                let mut to_false = Vec::new();
+                for (index, (operator, pair)) in operators.iter().zip(operands.windows(2)).enumerate() {
+                    self.compile_expression(&pair[1]);
+                    let span = self.span(&Span::new(pair[0].span().start, pair[1].span().end));
+                    let (left, right) = (self.span(pair[0].span()), self.span(pair[1].span()));
+                    if index + 1 == operators.len() {
+                        self.emit(Instruction::Binary { operator: *operator, span, left, right });
+                    } else {
+                        self.emit(Instruction::ChainCompare { operator: *operator, span, left, right });
+                        to_false.push(self.emit_jump(Instruction::JumpIfFalseOrPop));
+                    }
+                }
+                let to_end = self.emit_jump(Instruction::Jump);
+                for jump in to_false {
+                    self.patch(jump);
+                }
+                // A false comparison left its right operand below the result
+                self.emit(Instruction::Swap);
+                self.emit(Instruction::Pop);
+                self.patch(to_end);
+            }
+            Expr::Unary { operator, operand, span } => {
+                self.compile_expression(operand);
+                let instruction = Instruction::Unary {
+                    operator: *operator,
+                    span: self.span(span),
+                    operand: self.span(operand.span()),
+                };
+                self.emit(instruction);
+            }
+            Expr::Logical { left, operator, right, .. } => {
+                self.compile_expression(left);
+                let to_end = match operator {
+                    LogicalOp::Or => self.emit_jump(Instruction::JumpIfTrueOrPop),
+                    LogicalOp::And => self.emit_jump(Instruction::JumpIfFalseOrPop),
+                    LogicalOp::Coalesce => self.emit_jump(Instruction::JumpIfNotNilOrPop),
+                };
+                self.compile_expression(right);
+                self.patch(to_end);
+            }
+            Expr::Conditional { condition, then_branch, else_branch, .. } => {
+                self.compile_expression(condition);
+                let to_else = self.emit_jump(Instruction::JumpIfFalse);
+                self.compile_expression(then_branch);
+                let to_end = self.emit_jump(Instruction::Jump);
+                self.patch(to_else);
+                self.compile_expression(else_branch);
+                self.patch(to_end);
+            }
+            Expr::Call { callee, args, span } => self.compile_call(callee, args, &[], span, false),
+            Expr::CallWithKwargs { callee, positional_args, keyword_args, span } => {
+                let keywords: Vec<(&str, &Span, &Expr)> = keyword_args
+                    .iter()
+                    .map(|kwarg| (kwarg.name.as_str(), &kwarg.span, &kwarg.value))
+                    .collect();
+                self.compile_call(callee, positional_args, &keywords, span, true)
+            }
+            Expr::Grouping { expr, .. } => self.compile_expression(expr),
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.compile_expression(element);
+                }
+                self.emit(Instruction::BuildList(elements.len() as u32));
+            }
+            Expr::MultiReturn { values: elements, .. } | Expr::Tuple { elements, .. } => {
+                for element in elements {
+                    self.compile_expression(element);
+                }
+                self.emit(Instruction::BuildTuple(elements.len() as u32));
+            }
+            Expr::Dict { pairs, span } => {
+                self.emit(Instruction::NewDict);
+                for (key, value) in pairs {
+                    self.compile_expression(key);
+                    self.compile_expression(value);
+                    let instruction = Instruction::DictInsert {
+                        span: self.span(span),
+                        key: self.span(key.span()),
+                    };
+                    self.emit(instruction);
+                }
+            }
+            Expr::Set { elements, span } => {
+                self.emit(Instruction::NewSet);
+                for element in elements {
+                    self.compile_expression(element);
+                    let instruction = Instruction::SetInsert {
+                        span: self.span(span),
+                        element: self.span(element.span()),
+                    };
+                    self.emit(instruction);
+                }
+            }
+            Expr::Match { subject, arms, .. } => {
+                // The subject stays on the stack while the arms test it
+                self.compile_expression(subject);
+                let mut to_end = Vec::new();
+                for arm in arms {
+                    let site = MatchSite {
+                        pattern: arm.pattern.clone(),
+                        names: arm.pattern.bindings().into_iter().map(|(name, _)| self.name(name)).collect(),
+                    };
+                    self.chunk.matches.push(site);
+                    self.emit(Instruction::MatchPattern(self.chunk.matches.len() as u32 - 1));
+                    let mut to_next = vec![self.emit_jump(Instruction::JumpIfFalse)];
+                    if let Some(guard) = &arm.guard {
+                        self.compile_expression(guard);
+                        to_next.push(self.emit_jump(Instruction::JumpIfFalse));
+                    }
+                    self.emit(Instruction::Pop);
+                    self.compile_expression(&arm.body);
+                    to_end.push(self.emit_jump(Instruction::Jump));
+                    for jump in to_next {
+                        self.patch(jump);
+                    }
+                }
+                let instruction = Instruction::NoMatch { span: self.span(subject.span()) };
+                self.emit(instruction);
+                for jump in to_end {
+                    self.patch(jump);
+                }
+            }
+            Expr::PropertyAccess { object, property, optional, span } => {
+                self.compile_expression(object);
+                let to_end = optional.then(|| self.emit_jump(Instruction::JumpIfNil));
+                let instruction = Instruction::GetProperty {
+                    name: self.name(property),
+                    span: self.span(span),
+                    object: self.span(object.span()),
+                };
+                self.emit(instruction);
+                if let Some(to_end) = to_end {
+                    self.patch(to_end);
+                }
+            }
+        }
+        self.location = enclosing;
+    }
+
+    /// The callee is only known at runtime, so every argument is guarded by an
+    /// `Argument` check that lets the active call decide whether it is evaluated
+    fn compile_call(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        keywords: &[(&str, &Span, &Expr)],
+        span: &Span,
+        with_keywords: bool,
+    ) {
+        let site = CallSite {
+            span: self.span(span),
+            arguments: args.iter().map(|arg| self.span(arg.span())).collect(),
+            keywords: keywords
+                .iter()
+                .map(|(name, span, value)| KeywordSite {
+                    name: self.name(name),
+                    span: self.span(span),
+                    value: self.span(value.span()),
+                })
+                .collect(),
+            with_keywords,
+        };
+        self.chunk.calls.push(site);
+        let site = self.chunk.calls.len() as u32 - 1;
+        // `object.name(...)` may be a method call, which only the object's type decides
+        let mut to_end = None;
+        match callee {
+            Expr::PropertyAccess { object, property, optional, span } => {
+                self.compile_expression(object);
+                // `object?.name(...)` leaves the nil object as its result and skips the call
+                if *optional {
+                    to_end = Some(self.emit_jump(Instruction::JumpIfNil));
+                }
+                let instruction = Instruction::CallMethod {
+                    name: self.name(property),
+                    span: self.span(span),
+                    object: self.span(object.span()),
+                    site,
+                };
+                self.emit(instruction);
+            }
+            callee => {
+                self.compile_expression(callee);
+                self.emit(Instruction::Call(site));
+            }
+        }
+
+        for (index, arg) in args.iter().enumerate() {
+            let index = index as u32;
+            let to_skip = self.emit(Instruction::Argument { index, skip: 0 });
+            self.compile_expression(arg);
+            self.emit(Instruction::ArgumentDone(index));
+            self.patch(to_skip);
+        }
+        for (index, (_, _, value)) in keywords.iter().enumerate() {
+            let index = index as u32;
+            self.emit(Instruction::Keyword(index));
+            self.compile_expression(value);
+            self.emit(Instruction::KeywordDone(index));
+        }
+        self.emit(Instruction::CallEnd);
+        if let Some(to_end) = to_end {
+            self.patch(to_end);
+        }
+    }
+
+    /// Attribute the instructions emitted next to `span`, returning the previous location
+    fn enter(&mut self, span: &Span) -> u32 {
+        let location = self.span(span);
+        std::mem::replace(&mut self.location, location)
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.chunk.code.push(instruction);
+        self.chunk.locations.push(self.location);
+        self.chunk.code.len() - 1
+    }
+
+    fn emit_jump(&mut self, jump: fn(u32) -> Instruction) -> usize {
+        self.emit(jump(0))
+    }
+
+    fn offset(&self) -> u32 {
+        self.chunk.code.len() as u32
+    }
+
+    /// Point a forward jump at the next instruction to be emitted
+    fn patch(&mut self, at: usize) {
+        let target = self.offset();
+        match &mut self.chunk.code[at] {
+            Instruction::Jump(to)
+            | Instruction::JumpIfFalse(to)
+            | Instruction::JumpIfTrue(to)
+            | Instruction::JumpIfTrueOrPop(to)
+            | Instruction::JumpIfFalseOrPop(to)
+            | Instruction::JumpIfNotNilOrPop(to)
+            | Instruction::JumpIfNil(to)
+            | Instruction::Argument { skip: to, .. }
+            | Instruction::AssertCompare { skip: to, .. } => *to = target,
+            instruction => unreachable!("cannot patch {:?}", instruction),
+        }
+    }
+
+    fn constant(&mut self, value: Value) -> u32 {
+        self.chunk.constants.push(value);
+        self.chunk.constants.len() as u32 - 1
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        if let Some(&index) = self.names.get(name) {
+            return index;
+        }
+        let index = self.chunk.names.len() as u32;
+        self.chunk.names.push(name.to_string());
+        self.names.insert(name.to_string(), index);
+        index
+    }
+
+    fn span(&mut self, span: &Span) -> u32 {
+        if let Some(&index) = self.spans.get(&(span.start, span.end)) {
+            return index;
+        }
+        let index = self.chunk.spans.len() as u32;
+        self.chunk.spans.push(span.clone());
+        self.spans.insert((span.start, span.end), index);
+        index
+    }
+}
+
@@ -0,0 +1,145 @@
+use crate::ast::{AssignTarget, Pattern};
+use crate::bytecode::{Chunk, Instruction};
+use crate::error::LineIndex;
+use crate::value::Value;
+use std::fmt::Write;
+
+/// Human-readable listing of a chunk for `bcc --disassemble`.
+///
+/// Each instruction is shown with its offset and the source line and column it was
+/// compiled from; the location is only repeated when it changes, as in:
+///
+/// ```text
+/// 0000    1:5  Constant         #0 1
+/// 0001      |  SetVariable      x
+/// ```
+pub fn disassemble(chunk: &Chunk, source: &str, name: &str) -> String {
+    let line_index = LineIndex::new(source);
+    let mut listing = format!("== {} ==\n", name);
+    let mut previous_location = None;
+    // Calls are compiled as properly nested Call ... CallEnd ranges
+    let mut open_calls = Vec::new();
+    for (offset, instruction) in chunk.code.iter().enumerate() {
+        let span = chunk.span(chunk.locations[offset]);
+        let location = line_index.line_col(source, span.start);
+        let position = if previous_location == Some(location.line) {
+            "|".to_string()
+        } else {
+            format!("{}:{}", location.line, location.column)
+        };
+        previous_location = Some(location.line);
+        match instruction {
+            Instruction::Call(site) | Instruction::CallMethod { site, .. } => open_calls.push(*site),
+            Instruction::CallEnd => {
+                open_calls.pop();
+            }
+            _ => {}
+        }
+        let _ = writeln!(listing, "{:04}  {:>6}  {}", offset, position, describe(chunk, instruction, open_calls.last().copied()));
+    }
+    listing
+}
+
+fn describe(chunk: &Chunk, instruction: &Instruction, call: Option<u32>) -> String {
+    let (opcode, operands) = match *instruction {
+        Instruction::Constant(index) => ("Constant", format!("#{} {}", index, constant(chunk, index))),
+        Instruction::Pop => ("Pop", String::new()),
+        Instruction::Swap => ("Swap", String::new()),
+        Instruction::GetVariable { name, .. } => ("GetVariable", chunk.name(name).to_string()),
+        Instruction::SetVariable { name } => ("SetVariable", chunk.name(name).to_string()),
+        Instruction::Unpack(site) => {
+            let targets: Vec<String> = chunk.unpacks[site as usize].targets.iter().map(describe_target).collect();
+            ("Unpack", targets.join(", "))
+        }
+        Instruction::Binary { operator, .. } => ("Binary", format!("{:?}", operator)),
+        Instruction::ChainCompare { operator, .. } => ("ChainCompare", format!("{:?}", operator)),
+        Instruction::Unary { operator, .. } => ("Unary", format!("{:?}", operator)),
+        Instruction::Jump(target) => ("Jump", format!("-> {:04}", target)),
+        Instruction::JumpIfFalse(target) => ("JumpIfFalse", format!("-> {:04}", target)),
+        Instruction::JumpIfTrue(target) => ("JumpIfTrue", format!("-> {:04}", target)),
+        Instruction::JumpIfTrueOrPop(target) => ("JumpIfTrueOrPop", format!("-> {:04}", target)),
+        Instruction::JumpIfFalseOrPop(target) => ("JumpIfFalseOrPop", format!("-> {:04}", target)),
+        Instruction::JumpIfNotNilOrPop(target) => ("JumpIfNotNilOrPop", format!("-> {:04}", target)),
+        Instruction::JumpIfNil(target) => ("JumpIfNil", format!("-> {:04}", target)),
+        Instruction::BuildList(count) => ("BuildList", count.to_string()),
+        Instruction::BuildTuple(count) => ("BuildTuple", count.to_string()),
+        Instruction::NewDict => ("NewDict", String::new()),
+        Instruction::DictInsert { .. } => ("DictInsert", String::new()),
+        Instruction::NewSet => ("NewSet", String::new()),
+        Instruction::SetInsert { .. } => ("SetInsert", String::new()),
+        Instruction::GetProperty { name, .. } => ("GetProperty", chunk.name(name).to_string()),
+        Instruction::Call(site) => ("Call", call_operands(chunk, site)),
+        Instruction::CallMethod { name, site, .. } => ("CallMethod", format!("{}, {}", chunk.name(name), call_operands(chunk, site))),
+        Instruction::Argument { index, skip } => ("Argument", format!("{} else -> {:04}", index, skip)),
+        Instruction::ArgumentDone(index) => ("ArgumentDone", index.to_string()),
+        Instruction::Keyword(index) => ("Keyword", keyword(chunk, call, index)),
+        Instruction::KeywordDone(index) => ("KeywordDone", keyword(chunk, call, index)),
+        Instruction::CallEnd => ("CallEnd", String::new()),
+        Instruction::AssertCompare { operator, skip, .. } => ("AssertCompare", format!("{:?} else -> {:04}", operator, skip)),
+        Instruction::AssertFail(site) => {
+            let message = if chunk.asserts[site as usize].message { "with message" } else { "" };
+            ("AssertFail", message.to_string())
+        }
+        Instruction::MatchPattern(site) => ("MatchPattern", describe_pattern(&chunk.matches[site as usize].pattern)),
+        Instruction::NoMatch { .. } => ("NoMatch", String::new()),
+    };
+    format!("{:<16} {}", opcode, operands).trim_end().to_string()
+}
+
+fn call_operands(chunk: &Chunk, site: u32) -> String {
+    let site = &chunk.calls[site as usize];
+    let keywords: Vec<&str> = site.keywords.iter().map(|keyword| chunk.name(keyword.name)).collect();
+    if keywords.is_empty() {
+        format!("{} args", site.arguments.len())
+    } else {
+        format!("{} args, keywords: {}", site.arguments.len(), keywords.join(", "))
+    }
+}
+
+fn constant(chunk: &Chunk, index: u32) -> String {
+    match &chunk.constants[index as usize] {
+        Value::String(s) => format!("{:?}", s),
+        value => value.to_string(),
+    }
+}
+
+/// A match pattern as it would be written in the source
+fn describe_pattern(pattern: &Pattern) -> String {
+    let literal = |value: &Value| match value {
+        Value::String(s) => format!("{:?}", s),
+        value => value.to_string(),
+    };
+    match pattern {
+        Pattern::Target(target) => describe_target(target),
+        Pattern::Literal { value, .. } => literal(value),
+        Pattern::Sequence { elements, .. } => {
+            let elements: Vec<String> = elements.iter().map(describe_pattern).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        Pattern::Dict { entries, .. } => {
+            let entries: Vec<String> = entries.iter().map(|(key, value)| format!("{}: {}", literal(key), describe_pattern(value))).collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+fn describe_target(target: &AssignTarget) -> String {
+    match target {
+        AssignTarget::Variable { name, .. } => name.clone(),
+        AssignTarget::Ignore { .. } => "_".to_string(),
+        AssignTarget::Starred { target, .. } => format!("*{}", describe_target(target)),
+        AssignTarget::Nested { targets, .. } => {
+            let targets: Vec<String> = targets.iter().map(describe_target).collect();
+            match &targets[..] {
+                [single] => format!("({},)", single),
+                targets => format!("({})", targets.join(", ")),
+            }
+        }
+    }
+}
+
+/// Keyword names live on the enclosing call's site
+fn keyword(chunk: &Chunk, call: Option<u32>, index: u32) -> String {
+    call.and_then(|site| chunk.calls[site as usize].keywords.get(index as usize))
+        .map_or_else(|| format!("#{}", index), |keyword| chunk.name(keyword.name).to_string())
+}
@@ -1,7 +1,7 @@
-use ariadne::{Color, Fmt, Label, Report, ReportKind, Source};
+use ariadne::{Color, Config, Fmt, IndexType, Label, Report, ReportKind, Source};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -20,11 +20,92 @@ impl Span {
     }
 }
 
+/// A 1-based line and column; columns count characters, not bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Maps byte offsets to lines and columns.
+/// Line starts are computed once per source, so each lookup is a binary search.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        Self { line_starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte offset where the given 1-based line starts
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line.checked_sub(1)?).copied()
+    }
+
+    /// Line and column of a byte offset in `source`, the text this index was built from
+    pub fn line_col(&self, source: &str, offset: usize) -> LineCol {
+        let offset = char_boundary(source, offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = source[line_start..offset].chars().count() + 1;
+        LineCol { line, column }
+    }
+}
+
+/// A secondary label pointing at a related piece of source code
+#[derive(Debug, Clone)]
+pub struct SpanLabel {
+    pub span: Span,
+    pub message: String,
+}
+
+/// One active call when a runtime error occurred
+#[derive(Debug, Clone)]
+pub struct StackFrame {
+    pub function: String,
+    pub call_site: Span,
+}
+
 #[derive(Debug, Clone)]
 pub enum ErrorKind {
     LexError,
     ParseError,
     RuntimeError,
+    /// An error the resolver finds before the program runs
+    ResolveError,
+    /// A likely mistake that does not stop the program from running
+    Warning,
+}
+
+impl ErrorKind {
+    /// Machine-readable name used in JSON diagnostics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::LexError => "lex_error",
+            ErrorKind::ParseError => "parse_error",
+            ErrorKind::RuntimeError => "runtime_error",
+            ErrorKind::ResolveError => "resolve_error",
+            ErrorKind::Warning => "warning",
+        }
+    }
+}
+
+/// How diagnostics are written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ErrorFormat {
+    /// Colored ariadne reports for people
+    #[default]
+    Human,
+    /// One JSON object per line for editors and CI annotators
+    Json,
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +114,11 @@ pub struct BccError {
     pub span: Span,
     pub message: String,
     pub help: Option<String>,
+    pub labels: Vec<SpanLabel>,
+    pub notes: Vec<String>,
+    pub code: Option<&'static str>,
+    /// Calls the error propagated through, innermost first
+    pub trace: Vec<StackFrame>,
 }
 
 impl BccError {
@@ -42,6 +128,10 @@ impl BccError {
             span,
             message,
             help: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            code: None,
+            trace: Vec::new(),
         }
     }
 
@@ -51,13 +141,44 @@ impl BccError {
             span,
             message,
             help: Some(help),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            code: None,
+            trace: Vec::new(),
         }
     }
 
+    /// Attach a secondary label, drawn alongside the primary one
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(SpanLabel { span, message });
+        self
+    }
+
+    /// Attach an extra note, rendered below the source snippet
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Record a call the error propagated out of; frames are pushed innermost first
+    pub fn with_frame(mut self, frame: StackFrame) -> Self {
+        self.trace.push(frame);
+        self
+    }
+
     pub fn lex_error(span: Span, message: String) -> Self {
         Self::new(ErrorKind::LexError, span, message)
     }
 
+    pub fn lex_error_with_help(span: Span, message: String, help: String) -> Self {
+        Self::new_with_help(ErrorKind::LexError, span, message, help)
+    }
+
     pub fn parse_error(span: Span, message: String) -> Self {
         Self::new(ErrorKind::ParseError, span, message)
     }
@@ -74,6 +195,22 @@ impl BccError {
         Self::new_with_help(ErrorKind::RuntimeError, span, message, help)
     }
 
+    pub fn resolve_error(span: Span, message: String) -> Self {
+        Self::new(ErrorKind::ResolveError, span, message)
+    }
+
+    pub fn resolve_error_with_help(span: Span, message: String, help: String) -> Self {
+        Self::new_with_help(ErrorKind::ResolveError, span, message, help)
+    }
+
+    /// Write this diagnostic to stderr in the requested format
+    pub fn emit(&self, source: &str, filename: Option<&str>, format: ErrorFormat, line_index: &LineIndex) {
+        match format {
+            ErrorFormat::Human => self.report(source, filename),
+            ErrorFormat::Json => eprintln!("{}", self.to_json(source, filename, line_index)),
+        }
+    }
+
     pub fn report(&self, source: &str, filename: Option<&str>) {
         let filename = filename.unwrap_or("<repl>");
         
@@ -81,25 +218,73 @@ impl BccError {
             ErrorKind::LexError => Color::Red,
             ErrorKind::ParseError => Color::Yellow,
             ErrorKind::RuntimeError => Color::Magenta,
+            ErrorKind::ResolveError => Color::Red,
+            ErrorKind::Warning => Color::Cyan,
         };
 
+        // The report kind already says "Warning", so only errors name their kind
         let kind_str = match self.kind {
-            ErrorKind::LexError => "Lexical Error",
-            ErrorKind::ParseError => "Parse Error", 
-            ErrorKind::RuntimeError => "Runtime Error",
+            ErrorKind::LexError => Some("Lexical Error"),
+            ErrorKind::ParseError => Some("Parse Error"),
+            ErrorKind::RuntimeError => Some("Runtime Error"),
+            ErrorKind::ResolveError => Some("Resolve Error"),
+            ErrorKind::Warning => None,
+        };
+        let message = match kind_str {
+            Some(kind_str) => format!("{}: {}", kind_str.fg(color), self.message),
+            None => self.message.clone(),
         };
 
-        let mut report_builder = Report::build(ReportKind::Error, filename, self.span.start)
-            .with_message(format!("{}: {}", kind_str.fg(color), self.message))
+        let report_kind = match self.kind {
+            ErrorKind::Warning => ReportKind::Warning,
+            _ => ReportKind::Error,
+        };
+
+        // Spans are byte offsets, so ariadne must not treat them as character indices
+        let mut report_builder = Report::build(report_kind, (filename, clamp(&self.span, source)))
+            .with_config(Config::default().with_index_type(IndexType::Byte))
+            .with_message(message)
             .with_label(
-                Label::new((filename, self.span.start..self.span.end))
+                Label::new((filename, clamp(&self.span, source)))
                     .with_message(&self.message)
                     .with_color(color),
             );
 
-        // Add help note if available
+        for label in &self.labels {
+            report_builder = report_builder.with_label(
+                Label::new((filename, clamp(&label.span, source)))
+                    .with_message(&label.message)
+                    .with_color(Color::Blue),
+            );
+        }
+
+        for (depth, frame) in self.trace.iter().enumerate() {
+            // Built-ins fail at their own call site, which the primary label already covers
+            if frame.call_site.start == self.span.start && frame.call_site.end == self.span.end {
+                continue;
+            }
+            report_builder = report_builder.with_label(
+                Label::new((filename, clamp(&frame.call_site, source)))
+                    .with_message(format!("#{} {}() called here", depth, frame.function))
+                    .with_color(Color::Blue),
+            );
+        }
+
+        if let Some(code) = self.code {
+            report_builder = report_builder.with_code(code);
+        }
+
+        for note in &self.notes {
+            report_builder = report_builder.with_note(note);
+        }
+
+        if !self.trace.is_empty() {
+            report_builder = report_builder.with_note(self.traceback(source, &LineIndex::new(source)));
+        }
+
+        // Add help if available
         if let Some(ref help_text) = self.help {
-            report_builder = report_builder.with_note(format!("{}: {}", "help".fg(Color::Cyan), help_text));
+            report_builder = report_builder.with_help(help_text);
         }
 
         report_builder
@@ -107,6 +292,106 @@ impl BccError {
             .print((filename, Source::from(source)))
             .unwrap();
     }
+
+    /// Python-style traceback, innermost call first
+    pub fn traceback(&self, source: &str, line_index: &LineIndex) -> String {
+        let frames: Vec<String> = self
+            .trace
+            .iter()
+            .enumerate()
+            .map(|(depth, frame)| {
+                let location = line_index.line_col(source, frame.call_site.start);
+                format!("  #{} {}() called at {}:{}", depth, frame.function, location.line, location.column)
+            })
+            .collect();
+        format!("traceback (innermost first):\n{}", frames.join("\n"))
+    }
+
+    /// Serialize as a single-line JSON object.
+    /// Spans are byte offsets; lines and columns are 1-based, columns counted in characters.
+    pub fn to_json(&self, source: &str, filename: Option<&str>, line_index: &LineIndex) -> String {
+        let filename = filename.unwrap_or("<repl>");
+
+        let labels: Vec<String> = self
+            .labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"message\":{},{}}}",
+                    json_string(&label.message),
+                    json_location(source, line_index, &label.span)
+                )
+            })
+            .collect();
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+        let trace: Vec<String> = self
+            .trace
+            .iter()
+            .map(|frame| {
+                format!(
+                    "{{\"function\":{},{}}}",
+                    json_string(&frame.function),
+                    json_location(source, line_index, &frame.call_site)
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"kind\":\"{}\",\"code\":{},\"message\":{},\"help\":{},\"file\":{},{},\"labels\":[{}],\"notes\":[{}],\"trace\":[{}]}}",
+            self.kind.as_str(),
+            self.code.map_or("null".to_string(), json_string),
+            json_string(&self.message),
+            self.help.as_deref().map_or("null".to_string(), json_string),
+            json_string(filename),
+            json_location(source, line_index, &self.span),
+            labels.join(","),
+            notes.join(","),
+            trace.join(","),
+        )
+    }
+}
+
+/// Byte range of a span that stays inside `source`. End-of-input errors point
+/// one past the last byte, which ariadne cannot slice.
+fn clamp(span: &Span, source: &str) -> std::ops::Range<usize> {
+    let start = char_boundary(source, span.start);
+    start..char_boundary(source, span.end).max(start)
+}
+
+/// `offset` within `source`, moved back to the start of the character it falls inside
+fn char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+fn json_location(source: &str, line_index: &LineIndex, span: &Span) -> String {
+    let start = line_index.line_col(source, span.start);
+    let end = line_index.line_col(source, span.end);
+    format!(
+        "\"span\":{{\"start\":{},\"end\":{}}},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}",
+        span.start, span.end, start.line, start.column, end.line, end.column
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
 }
 
 impl fmt::Display for BccError {
@@ -0,0 +1,529 @@
+// Stable diagnostic codes.
+//
+// Codes never change meaning once published, so CI logs and runbooks can
+// link to them even when the wording of a message changes. Lexer codes use
+// E01xx, parser codes E02xx and runtime codes E03xx. Warnings from the
+// resolver use W04xx. An undefined name the resolver can prove before the
+// program runs keeps its runtime code, E0301, but has the kind
+// `resolve_error`.
+
+// Lexical errors
+pub const UNEXPECTED_CHARACTER: &str = "E0101";
+pub const UNTERMINATED_STRING: &str = "E0102";
+pub const INVALID_NUMBER: &str = "E0103";
+
+// Parse errors
+pub const EXPECTED_EXPRESSION: &str = "E0201";
+pub const UNCLOSED_DELIMITER: &str = "E0202";
+pub const EXPECTED_TOKEN: &str = "E0203";
+pub const INVALID_ASSIGNMENT_TARGET: &str = "E0204";
+pub const EMPTY_PARENTHESES: &str = "E0205";
+pub const POSITIONAL_AFTER_KEYWORD: &str = "E0206";
+pub const MISSING_OPERAND: &str = "E0207";
+pub const INVALID_ARGUMENT_SYNTAX: &str = "E0208";
+
+// Runtime errors
+pub const UNDEFINED_VARIABLE: &str = "E0301";
+pub const UNSUPPORTED_OPERAND_TYPES: &str = "E0302";
+pub const DIVISION_BY_ZERO: &str = "E0303";
+pub const WRONG_ARGUMENT_COUNT: &str = "E0304";
+pub const UNSUPPORTED_ARGUMENT_TYPE: &str = "E0305";
+pub const INVALID_ARGUMENT_VALUE: &str = "E0306";
+pub const INVALID_KEYWORD_ARGUMENT: &str = "E0307";
+pub const INVALID_DICTIONARY_KEY: &str = "E0308";
+pub const UNKNOWN_PROPERTY: &str = "E0309";
+pub const UNPACK_MISMATCH: &str = "E0310";
+pub const NOT_CALLABLE: &str = "E0311";
+pub const ASSERTION_FAILED: &str = "E0312";
+pub const UNKNOWN_METHOD: &str = "E0313";
+pub const MISSING_ELEMENT: &str = "E0314";
+pub const NO_MATCHING_ARM: &str = "E0315";
+pub const MALFORMED_BYTECODE: &str = "E0316";
+
+// Resolver warnings
+pub const UNUSED_VARIABLE: &str = "W0401";
+pub const UNREAD_ASSIGNMENT: &str = "W0402";
+pub const UNDERSCORE_READ: &str = "W0403";
+pub const NON_EXHAUSTIVE_MATCH: &str = "W0404";
+
+/// Long-form documentation for a diagnostic code, shown by `bcc --explain`
+pub struct ErrorCodeInfo {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+}
+
+pub const ERROR_CODES: &[ErrorCodeInfo] = &[
+    ErrorCodeInfo {
+        code: UNEXPECTED_CHARACTER,
+        title: "unexpected character",
+        explanation: "\
+The lexer found a character that does not start any token.
+
+Erroneous example:
+
+    x = 5 @ 3
+
+BCC has no '@' operator. Remove the character or replace it with a
+supported operator such as '+', '-', '*' or '/'.",
+    },
+    ErrorCodeInfo {
+        code: UNTERMINATED_STRING,
+        title: "unterminated string",
+        explanation: "\
+A string literal was opened with '\"' but the file ended before the
+closing quote.
+
+Erroneous example:
+
+    greeting = \"hello
+
+Add the closing quote:
+
+    greeting = \"hello\"",
+    },
+    ErrorCodeInfo {
+        code: INVALID_NUMBER,
+        title: "invalid number literal",
+        explanation: "\
+A numeric literal is malformed. Ints may be written in decimal, hex
+(0xFF), octal (0o755) or binary (0b1010), with '_' between digits
+(1_000_000), and have any number of digits. Doubles need a digit after
+the '.' and may have an exponent (.5, 2.5e-3), up to about 1.8e308.
+
+Erroneous examples:
+
+    mask = 0x          // no digits
+    flags = 0b102      // '2' is not a binary digit
+    count = 1__000     // '_' must separate two digits
+    tiny = 1e-         // exponent without digits
+
+Corrected:
+
+    mask = 0xFF
+    flags = 0b101
+    count = 1_000
+    tiny = 1e-9",
+    },
+    ErrorCodeInfo {
+        code: EXPECTED_EXPRESSION,
+        title: "expected expression",
+        explanation: "\
+The parser needed an expression (a literal, variable, call or
+parenthesized expression) but found something else.
+
+Erroneous example:
+
+    x = )
+
+Provide a value where the parser points:
+
+    x = 1",
+    },
+    ErrorCodeInfo {
+        code: UNCLOSED_DELIMITER,
+        title: "unclosed delimiter",
+        explanation: "\
+An opening '(', '[' or '{' has no matching closer. The secondary label
+shows where the delimiter was opened.
+
+Erroneous example:
+
+    items = [1, 2, 3
+
+Close the delimiter:
+
+    items = [1, 2, 3]",
+    },
+    ErrorCodeInfo {
+        code: EXPECTED_TOKEN,
+        title: "expected token",
+        explanation: "\
+A specific piece of punctuation is required by the surrounding syntax,
+such as the parentheses around an if condition or the ':' between a
+dictionary key and its value.
+
+Erroneous example:
+
+    if x > 1 { print(x) }
+
+Add the missing token:
+
+    if (x > 1) { print(x) }",
+    },
+    ErrorCodeInfo {
+        code: INVALID_ASSIGNMENT_TARGET,
+        title: "invalid assignment target",
+        explanation: "\
+Only variable names (or comma-separated names for multi-assignment) can
+appear on the left of '='.
+
+Erroneous example:
+
+    1 = x
+
+Assign to a variable instead:
+
+    x = 1",
+    },
+    ErrorCodeInfo {
+        code: EMPTY_PARENTHESES,
+        title: "empty parentheses",
+        explanation: "\
+Parentheses used for grouping must contain an expression.
+
+Erroneous example:
+
+    x = ()
+
+Use nil for an empty value:
+
+    x = (nil)",
+    },
+    ErrorCodeInfo {
+        code: POSITIONAL_AFTER_KEYWORD,
+        title: "positional argument after keyword argument",
+        explanation: "\
+Once a keyword argument has been passed, every following argument must
+also be a keyword argument.
+
+Erroneous example:
+
+    divmod(7, round_mode=\"up\", 2)
+
+Move positional arguments first:
+
+    divmod(7, 2, round_mode=\"up\")",
+    },
+    ErrorCodeInfo {
+        code: MISSING_OPERAND,
+        title: "missing operand",
+        explanation: "\
+A binary operator needs an expression on both sides.
+
+Erroneous example:
+
+    total = price *
+
+Supply the right-hand operand:
+
+    total = price * 2",
+    },
+    ErrorCodeInfo {
+        code: INVALID_ARGUMENT_SYNTAX,
+        title: "invalid argument syntax",
+        explanation: "\
+An argument inside a function call is not a valid expression.
+
+Erroneous example:
+
+    print(1, +)
+
+Each argument must be a complete expression:
+
+    print(1, 2)",
+    },
+    ErrorCodeInfo {
+        code: UNDEFINED_VARIABLE,
+        title: "undefined variable",
+        explanation: "\
+A variable was read before any value was assigned to it.
+
+Erroneous example:
+
+    print(count)
+
+Assign the variable first:
+
+    count = 0
+    print(count)",
+    },
+    ErrorCodeInfo {
+        code: UNSUPPORTED_OPERAND_TYPES,
+        title: "unsupported operand types",
+        explanation: "\
+An operator was applied to values whose types it does not support. The
+secondary labels show the type of each operand.
+
+Erroneous example:
+
+    message = \"total: \" + 5
+
+Strings can only be concatenated with strings, and arithmetic needs
+numbers on both sides.",
+    },
+    ErrorCodeInfo {
+        code: DIVISION_BY_ZERO,
+        title: "division by zero",
+        explanation: "\
+The divisor of '/' or divmod() was zero.
+
+Erroneous example:
+
+    ratio = 10 / 0
+
+Check the divisor before dividing:
+
+    if (d != 0) { ratio = 10 / d }",
+    },
+    ErrorCodeInfo {
+        code: WRONG_ARGUMENT_COUNT,
+        title: "wrong number of arguments",
+        explanation: "\
+A built-in function was called with the wrong number of arguments.
+
+Erroneous example:
+
+    len(\"a\", \"b\")
+
+Pass exactly the arguments the function expects:
+
+    len(\"a\")",
+    },
+    ErrorCodeInfo {
+        code: UNSUPPORTED_ARGUMENT_TYPE,
+        title: "unsupported argument type",
+        explanation: "\
+A built-in function received an argument of a type it cannot handle.
+
+Erroneous example:
+
+    len(42)
+
+len() accepts strings, lists and dictionaries:
+
+    len(\"42\")",
+    },
+    ErrorCodeInfo {
+        code: INVALID_ARGUMENT_VALUE,
+        title: "invalid argument value",
+        explanation: "\
+An argument has the right type but a value the function does not accept.
+
+Erroneous example:
+
+    divmod(7, 2, round_mode=\"sideways\")
+
+Valid rounding modes are \"down\", \"up\" and \"nearest\".",
+    },
+    ErrorCodeInfo {
+        code: INVALID_KEYWORD_ARGUMENT,
+        title: "invalid keyword argument",
+        explanation: "\
+A keyword argument was passed to a function that does not accept it.
+
+Erroneous example:
+
+    divmod(7, 2, mode=\"up\")
+
+Use one of the keyword arguments the function documents:
+
+    divmod(7, 2, round_mode=\"up\")",
+    },
+    ErrorCodeInfo {
+        code: INVALID_DICTIONARY_KEY,
+        title: "invalid dictionary key",
+        explanation: "\
+Dictionary keys and set elements, and values tested with 'in' against a
+dictionary or set, must be hashable: ints, bools, strings, nil, or tuples
+of those. Lists, dictionaries and sets can change after they are used as
+a key, so they cannot be keys or elements.
+
+Erroneous example:
+
+    seen = {[1, 2]: true}
+
+Use a tuple instead:
+
+    seen = {(1, 2): true}",
+    },
+    ErrorCodeInfo {
+        code: UNKNOWN_PROPERTY,
+        title: "unknown property",
+        explanation: "\
+A property was accessed on a value that does not have it.
+
+Erroneous example:
+
+    case(true, 1).value
+
+case() results only have a 'result' property:
+
+    case(true, 1).result",
+    },
+    ErrorCodeInfo {
+        code: UNPACK_MISMATCH,
+        title: "unpacking mismatch",
+        explanation: "\
+A multi-assignment has a different number of targets than the right-hand
+side produces, either at the top level or in a nested target list.
+
+Erroneous example:
+
+    a, b, c = (1, 2)
+    a, (b, c) = (1, (2, 3, 4))
+
+Match the number of targets to the number of values, using '_' to skip a
+value and a starred target to collect any number of them into a list:
+
+    a, b = (1, 2)
+    a, (b, *rest) = (1, (2, 3, 4))",
+    },
+    ErrorCodeInfo {
+        code: NOT_CALLABLE,
+        title: "value is not callable",
+        explanation: "\
+Only built-in functions can be called.
+
+Erroneous example:
+
+    x = 5
+    x()
+
+Call a built-in such as print(), len(), type(), case(), divmod() or set().",
+    },
+    ErrorCodeInfo {
+        code: ASSERTION_FAILED,
+        title: "assertion failed",
+        explanation: "\
+The condition of an assert statement was false. For comparisons, the
+secondary labels show the value each side evaluated to.
+
+Example:
+
+    x = 3
+    assert x == 4, \"x should be 4\"
+
+Fix the program state or the expectation being asserted.",
+    },
+    ErrorCodeInfo {
+        code: UNKNOWN_METHOD,
+        title: "unknown method",
+        explanation: "\
+A method was called on a list, dictionary or set that does not have it.
+
+Erroneous example:
+
+    items = [1, 2]
+    items.push(3)
+
+Lists have an append() method, dictionaries an update() method, and sets
+add() and remove() methods:
+
+    items.append(3)",
+    },
+    ErrorCodeInfo {
+        code: MISSING_ELEMENT,
+        title: "element not in set",
+        explanation: "\
+A set's remove() method was given a value that is not in the set.
+
+Erroneous example:
+
+    seen = {1, 2}
+    seen.remove(3)
+
+Check membership first:
+
+    if (3 in seen) seen.remove(3)",
+    },
+    ErrorCodeInfo {
+        code: NO_MATCHING_ARM,
+        title: "no match arm matched",
+        explanation: "\
+A match expression ran out of arms: no pattern accepted the value, or every
+arm that did had a guard that was false.
+
+Erroneous example:
+
+    sign = match -5 { 0 => \"zero\", n if n > 0 => \"positive\" }
+
+End the match with an arm that accepts everything:
+
+    sign = match -5 { 0 => \"zero\", n if n > 0 => \"positive\", _ => \"negative\" }",
+    },
+    ErrorCodeInfo {
+        code: MALFORMED_BYTECODE,
+        title: "malformed bytecode",
+        explanation: "\
+A compiled .bcco script passed the checks made when it was loaded, but its
+instructions misused the VM, for example by popping from an empty stack. The
+compiler never produces such code, so the file was damaged or edited by hand.
+
+Recompile the script from its source:
+
+    bcc compile script.bcc",
+    },
+    ErrorCodeInfo {
+        code: UNUSED_VARIABLE,
+        title: "unused variable",
+        explanation: "\
+A variable is assigned but never read anywhere in its scope. Variables
+created inside a block disappear when the block ends.
+
+Example:
+
+    {
+        total = 10
+    }
+
+Read the variable, remove it, or start its name with '_' to mark it as
+intentionally unused.",
+    },
+    ErrorCodeInfo {
+        code: UNREAD_ASSIGNMENT,
+        title: "assigned value is never read",
+        explanation: "\
+A value is stored in a variable but is always overwritten, or the scope
+ends, before anything reads it.
+
+Example:
+
+    x = compute()
+    x = 2
+    print(x)
+
+Remove the dead assignment, or read the value before replacing it.",
+    },
+    ErrorCodeInfo {
+        code: UNDERSCORE_READ,
+        title: "'_' is read",
+        explanation: "\
+'_' marks values that are deliberately ignored, such as an unwanted
+position in a multi-assignment. Reading it back usually means the value
+should have been given a real name.
+
+Example:
+
+    _ = divmod(7, 2)
+    print(_)
+
+Use a descriptive name instead:
+
+    result = divmod(7, 2)
+    print(result)",
+    },
+    ErrorCodeInfo {
+        code: NON_EXHAUSTIVE_MATCH,
+        title: "match without a catch-all arm",
+        explanation: "\
+No arm of a match accepts every value, so a value that matches none of them
+stops the program with E0315. Only an unguarded '_' or name accepts every
+value.
+
+Example:
+
+    kind = match x { 0 => \"zero\", 1 => \"one\" }
+
+Add a final arm for everything else:
+
+    kind = match x { 0 => \"zero\", 1 => \"one\", _ => \"many\" }",
+    },
+];
+
+/// Find the documentation for a code, accepting either `E0102` or `e0102`
+pub fn lookup(code: &str) -> Option<&'static ErrorCodeInfo> {
+    ERROR_CODES
+        .iter()
+        .find(|info| info.code.eq_ignore_ascii_case(code))
+}
@@ -1,73 +1,173 @@
-use crate::ast::{BinaryOp, Expr, LogicalOp, Program, Stmt, UnaryOp};
-use crate::error::{BccError, Span};
-use crate::value::{Value, CaseResult};
+use crate::ast::{AssignTarget, BinaryOp, Expr, KeywordArg, LogicalOp, Pattern, Program, Stmt, UnaryOp};
+use crate::bigint::BigInt;
+use crate::error::{BccError, Span, StackFrame};
+use crate::error_codes;
+use crate::ordered_map::OrderedMap;
+use crate::resolver::Resolution;
+use crate::suggest;
+use crate::value::{Value, CaseResult, Key};
 use std::collections::HashMap;
 
+/// Variables of every open scope.
+///
+/// New names are always created in the global scope, so blocks never own bindings
+/// and every variable lives in one flat table. Entering and leaving a block only
+/// moves a depth counter; nothing is copied.
 #[derive(Debug, Clone)]
 pub struct Environment {
     values: HashMap<String, Value>,
-    enclosing: Option<Box<Environment>>,
+    /// Blocks open around the code being run, 0 at the top level
+    depth: usize,
 }
 
 impl Environment {
     pub fn new() -> Self {
         Self {
             values: HashMap::new(),
-            enclosing: None,
+            depth: 0,
         }
     }
 
-    pub fn with_enclosing(enclosing: Environment) -> Self {
-        Self {
-            values: HashMap::new(),
-            enclosing: Some(Box::new(enclosing)),
-        }
+    pub fn with_enclosing(mut enclosing: Environment) -> Self {
+        enclosing.push_scope();
+        enclosing
+    }
+
+    pub fn push_scope(&mut self) {
+        self.depth += 1;
+    }
+
+    /// Leave the innermost block. The global scope is never popped.
+    pub fn pop_scope(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
     }
 
     pub fn get(&self, name: &str) -> Option<Value> {
-        if let Some(value) = self.values.get(name) {
-            Some(value.clone())
-        } else if let Some(ref enclosing) = self.enclosing {
-            enclosing.get(name)
-        } else {
-            None
-        }
+        self.values.get(name).cloned()
+    }
+
+    /// Read a variable the resolver found `depth` blocks out from the running code.
+    /// Only the global scope holds bindings, so that must be the outermost one.
+    pub fn get_at(&self, name: &str, depth: usize) -> Option<Value> {
+        debug_assert_eq!(depth, self.depth, "'{}' resolved outside the global scope", name);
+        self.values.get(name).cloned()
     }
 
     pub fn assign(&mut self, name: &str, value: Value) -> Result<(), BccError> {
-        if self.values.contains_key(name) {
-            self.values.insert(name.to_string(), value);
-            Ok(())
-        } else if let Some(ref mut enclosing) = self.enclosing {
-            enclosing.assign(name, value)
-        } else {
-            // For Python-like behavior, create the variable if it doesn't exist
-            self.values.insert(name.to_string(), value);
-            Ok(())
+        match self.values.get_mut(name) {
+            Some(slot) => *slot = value,
+            None => {
+                // For Python-like behavior, create the variable if it doesn't exist.
+                // It lives in the global scope, so it outlives the block.
+                self.values.insert(name.to_string(), value);
+            }
         }
+        Ok(())
+    }
+
+    /// Every name visible from this scope, sorted so suggestions are deterministic
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Names of the built-in functions, registered in the global scope
+pub const BUILTIN_NAMES: &[&str] = &["print", "len", "type", "case", "divmod", "set"];
+
+/// Properties that case() results expose
+const CASE_RESULT_PROPERTIES: &[&str] = &["result"];
+
+/// Keyword arguments accepted by divmod()
+pub(crate) const DIVMOD_KEYWORDS: &[&str] = &["round_mode"];
+
+const HASHABLE_KEYS_HELP: &str = "Dictionary keys and set elements must be ints, bools, strings, nil, or tuples of those.";
+
+/// Methods that lists, dictionaries and sets expose
+const LIST_METHODS: &[&str] = &["append"];
+const DICT_METHODS: &[&str] = &["update"];
+const SET_METHODS: &[&str] = &["add", "remove"];
+
+/// A method of a built-in container type, called as `value.method(args)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Method {
+    /// `list.append(value)`
+    Append,
+    /// `dict.update(other)`
+    Update,
+    /// `set.add(value)`
+    Add,
+    /// `set.remove(value)`
+    Remove,
+}
+
+impl Method {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Method::Append => "append",
+            Method::Update => "update",
+            Method::Add => "add",
+            Method::Remove => "remove",
+        }
+    }
+
+    /// Reject a wrong argument count before any argument is evaluated
+    pub(crate) fn check_arity(self, count: usize, span: &Span) -> Result<(), BccError> {
+        if count == 1 {
+            return Ok(());
+        }
+        let help = match self {
+            Method::Append => "Usage: list.append(value) adds value to the end of the list.",
+            Method::Update => "Usage: dict.update(other) copies every entry of other into dict.",
+            Method::Add => "Usage: set.add(value) adds value to the set.",
+            Method::Remove => "Usage: set.remove(value) removes value, which must be in the set.",
+        };
+        Err(BccError::runtime_error_with_help(
+            span.clone(),
+            format!("{}() takes exactly 1 argument, got {}", self.name(), count),
+            help.to_string(),
+        ).with_code(error_codes::WRONG_ARGUMENT_COUNT))
     }
 }
 
 pub struct Evaluator {
     environment: Environment,
+    call_stack: Vec<StackFrame>,
+    // Set while an error raised by an argument unwinds to its call, which was never entered
+    argument_failed: bool,
+    /// Scope depths of the running program's variable reads, from its `Resolution`
+    depths: HashMap<usize, usize>,
 }
 
 impl Evaluator {
     pub fn new() -> Self {
         let mut evaluator = Self {
             environment: Environment::new(),
+            call_stack: Vec::new(),
+            argument_failed: false,
+            depths: HashMap::new(),
         };
         
         // Add built-in functions
-        evaluator.environment.assign("print", Value::String("__builtin_print__".to_string())).unwrap();
-        evaluator.environment.assign("len", Value::String("__builtin_len__".to_string())).unwrap();
-        evaluator.environment.assign("type", Value::String("__builtin_type__".to_string())).unwrap();
-        evaluator.environment.assign("case", Value::String("__builtin_case__".to_string())).unwrap();
-        evaluator.environment.assign("divmod", Value::String("__builtin_divmod__".to_string())).unwrap();
+        for name in BUILTIN_NAMES {
+            evaluator.environment.assign(name, Value::String(format!("__builtin_{}__", name))).unwrap();
+        }
         
         evaluator
     }
 
+    /// Names defined in the global scope, including built-ins
+    pub fn global_names(&self) -> Vec<String> {
+        self.environment.names()
+    }
+
     pub fn evaluate_program(&mut self, program: &Program) -> Result<(), BccError> {
         for statement in &program.statements {
             self.execute_statement(statement)?;
@@ -75,6 +175,15 @@ impl Evaluator {
         Ok(())
     }
 
+    /// Evaluate a program the resolver has checked, reading variables at the scope
+    /// depths it computed
+    pub fn evaluate_resolved(&mut self, program: &Program, resolution: &Resolution) -> Result<(), BccError> {
+        self.depths = resolution.depths.clone();
+        let result = self.evaluate_program(program);
+        self.depths.clear();
+        result
+    }
+
     fn execute_statement(&mut self, stmt: &Stmt) -> Result<(), BccError> {
         match stmt {
             Stmt::Expression { expr, .. } => {
@@ -135,12 +244,62 @@ impl Evaluator {
                 }
                 Ok(())
             }
+            Stmt::Assert { condition, message, .. } => {
+                self.execute_assert(condition, message.as_ref())
+            }
+        }
+    }
+
+    fn execute_assert(&mut self, condition: &Expr, message: Option<&Expr>) -> Result<(), BccError> {
+        // Comparisons are evaluated one side at a time so a failure can show both operands
+        if let Expr::Binary { left, operator, right, span: condition_span } = condition {
+            if operator.is_comparison() {
+                let left_val = self.evaluate_expression(left)?;
+                let right_val = self.evaluate_expression(right)?;
+                let result = self.evaluate_binary_op(operator, left_val.clone(), right_val.clone(), condition_span)?;
+                if result.is_truthy() {
+                    return Ok(());
+                }
+
+                let error = self.assertion_error(message, condition_span)?;
+                return Err(error
+                    .with_label(left.span().clone(), format!("left side is {}", Self::describe_value(&left_val)))
+                    .with_label(right.span().clone(), format!("right side is {}", Self::describe_value(&right_val))));
+            }
+        }
+
+        if self.evaluate_expression(condition)?.is_truthy() {
+            return Ok(());
+        }
+        Err(self.assertion_error(message, condition.span())?)
+    }
+
+    fn assertion_error(&mut self, message: Option<&Expr>, condition_span: &Span) -> Result<BccError, BccError> {
+        let message = match message {
+            Some(message_expr) => Some(self.evaluate_expression(message_expr)?),
+            None => None,
+        };
+        Ok(Self::assertion_failed(message, condition_span))
+    }
+
+    pub(crate) fn assertion_failed(message: Option<Value>, condition_span: &Span) -> BccError {
+        let text = match message {
+            Some(message) => format!("Assertion failed: {}", message),
+            None => "Assertion failed".to_string(),
+        };
+        BccError::runtime_error(condition_span.clone(), text).with_code(error_codes::ASSERTION_FAILED)
+    }
+
+    /// Render a value for assertion labels, quoting strings so "1" and 1 are distinguishable
+    pub(crate) fn describe_value(value: &Value) -> String {
+        match value {
+            Value::String(s) => format!("\"{}\" ({})", s, value.type_name()),
+            _ => format!("{} ({})", value, value.type_name()),
         }
     }
 
     fn execute_block(&mut self, statements: &[Stmt]) -> Result<(), BccError> {
-        let previous_env = self.environment.clone();
-        self.environment = Environment::with_enclosing(previous_env);
+        self.environment.push_scope();
 
         let result = (|| {
             for statement in statements {
@@ -149,10 +308,8 @@ impl Evaluator {
             Ok(())
         })();
 
-        // Extract the enclosing environment which may have been modified
-        if let Some(enclosing) = self.environment.enclosing.take() {
-            self.environment = *enclosing;
-        }
+        // Leave the scope on errors too, so the REPL keeps working afterwards
+        self.environment.pop_scope();
         
         result
     }
@@ -161,12 +318,11 @@ impl Evaluator {
         match expr {
             Expr::Literal { value, .. } => Ok(value.clone()),
             Expr::Variable { name, span } => {
-                self.environment.get(name).ok_or_else(|| {
-                    BccError::runtime_error(
-                        span.clone(),
-                        format!("Undefined variable '{}'", name),
-                    )
-                })
+                let value = match self.depths.get(&span.start) {
+                    Some(&depth) => self.environment.get_at(name, depth),
+                    None => self.environment.get(name),
+                };
+                value.ok_or_else(|| Self::undefined_variable_error(name, span, &self.environment.names()))
             }
             Expr::Assign { name, value, span } => {
                 let val = self.evaluate_expression(value)?;
@@ -174,7 +330,7 @@ impl Evaluator {
                     BccError::runtime_error(
                         span.clone(),
                         format!("Undefined variable '{}'", name),
-                    )
+                    ).with_code(error_codes::UNDEFINED_VARIABLE)
                 })?;
                 Ok(val)
             }
@@ -186,13 +342,30 @@ impl Evaluator {
             } => {
                 let left_val = self.evaluate_expression(left)?;
                 let right_val = self.evaluate_expression(right)?;
-                self.evaluate_binary_op(operator, left_val, right_val, span)
+                self.labeled_binary_op(operator, left_val, right_val, span, left.span(), right.span())
+            }
+            Expr::Comparison { operands, operators, .. } => {
+                let mut left_val = self.evaluate_expression(&operands[0])?;
+                let mut result = Value::Bool(true);
+                for (operator, pair) in operators.iter().zip(operands.windows(2)) {
+                    let right_val = self.evaluate_expression(&pair[1])?;
+                    let span = Span::new(pair[0].span().start, pair[1].span().end);
+                    result = self.labeled_binary_op(operator, left_val, right_val.clone(), &span, pair[0].span(), pair[1].span())?;
+                    if !result.is_truthy() {
+                        break;
+                    }
+                    left_val = right_val;
+                }
+                Ok(result)
             }
             Expr::Unary {
                 operator, operand, span
             } => {
                 let operand_val = self.evaluate_expression(operand)?;
-                self.evaluate_unary_op(operator, operand_val, span)
+                let operand_type = operand_val.type_name();
+                self.evaluate_unary_op(operator, operand_val, span).map_err(|error| {
+                    error.with_label(operand.span().clone(), format!("this has type {}", operand_type))
+                })
             }
             Expr::Logical {
                 left,
@@ -217,108 +390,37 @@ impl Evaluator {
                             self.evaluate_expression(right)
                         }
                     }
+                    LogicalOp::Coalesce => {
+                        if matches!(left_val, Value::Nil) {
+                            self.evaluate_expression(right)
+                        } else {
+                            Ok(left_val)
+                        }
+                    }
+                }
+            }
+            Expr::Conditional { condition, then_branch, else_branch, .. } => {
+                if self.evaluate_expression(condition)?.is_truthy() {
+                    self.evaluate_expression(then_branch)
+                } else {
+                    self.evaluate_expression(else_branch)
                 }
             }
             Expr::Call { callee, args, span } => {
-                let function_value = self.evaluate_expression(callee)?;
-                
-                // Check if it's a built-in function
-                if let Value::String(name) = function_value {
-                    match name.as_str() {
-                        "__builtin_print__" => {
-                            // Handle print built-in
-                            for arg in args {
-                                let value = self.evaluate_expression(arg)?;
-                                println!("{}", value);
-                            }
-                            return Ok(Value::Nil);
-                        },
-                        "__builtin_len__" => {
-                            // Handle len built-in
-                            if args.len() != 1 {
-                                return Err(BccError::runtime_error_with_help(
-                                    span.clone(),
-                                    format!("len() takes exactly 1 argument, got {}", args.len()),
-                                    "Usage: len(value) where value is a string, list, or dictionary.".to_string(),
-                                ));
-                            }
-                            
-                            let arg_value = self.evaluate_expression(&args[0])?;
-                            match arg_value {
-                                Value::String(s) => return Ok(Value::Int(s.chars().count() as i64)),
-                                Value::List(l) => return Ok(Value::Int(l.len() as i64)),
-                                Value::Dict(d) => return Ok(Value::Int(d.len() as i64)),
-                                _ => return Err(BccError::runtime_error_with_help(
-                                    span.clone(),
-                                    format!("len() not supported for type {}", arg_value.type_name()),
-                                    "len() only works with strings, lists, and dictionaries.".to_string(),
-                                )),
-                            }
-                        },
-                        "__builtin_type__" => {
-                            // Handle type built-in
-                            if args.len() != 1 {
-                                return Err(BccError::runtime_error_with_help(
-                                    span.clone(),
-                                    format!("type() takes exactly 1 argument, got {}", args.len()),
-                                    "Usage: type(value) returns the type name as a string.".to_string(),
-                                ));
-                            }
-                            
-                            let arg_value = self.evaluate_expression(&args[0])?;
-                            return Ok(Value::String(arg_value.type_name().to_string()));
-                        },
-                        "__builtin_case__" => {
-                            // Handle case built-in
-                            if args.len() < 2 || args.len() % 2 != 0 {
-                                return Err(BccError::runtime_error_with_help(
-                                    span.clone(),
-                                    format!("case() requires an even number of arguments (at least 2), got {}", args.len()),
-                                    "Usage: case(condition1, result1, condition2, result2, ...). Each condition is paired with its result.".to_string(),
-                                ));
-                            }
-                            
-                            // Evaluate condition-result pairs in order
-                            for i in (0..args.len()).step_by(2) {
-                                let condition_value = self.evaluate_expression(&args[i])?;
-                                if condition_value.is_truthy() {
-                                    let result_value = self.evaluate_expression(&args[i + 1])?;
-                                    return Ok(Value::CaseResult(CaseResult {
-                                        result: Box::new(result_value),
-                                    }));
-                                }
-                            }
-                            
-                            // If no condition matches, return nil wrapped in CaseResult
-                            return Ok(Value::CaseResult(CaseResult {
-                                result: Box::new(Value::Nil),
-                            }));
-                        },
-                        "__builtin_divmod__" => {
-                            // Handle divmod built-in without kwargs (default rounding mode)
-                            if args.len() != 2 {
-                                return Err(BccError::runtime_error_with_help(
-                                    span.clone(),
-                                    format!("divmod() takes exactly 2 arguments, got {}", args.len()),
-                                    "Usage: divmod(dividend, divisor) - use divmod(a, b, round_mode=\"up\") for keyword arguments.".to_string(),
-                                ));
-                            }
-                            
-                            let dividend = self.evaluate_expression(&args[0])?;
-                            let divisor = self.evaluate_expression(&args[1])?;
-                            
-                            return self.builtin_divmod(dividend, divisor, "down".to_string(), span);
-                        },
-                        _ => {}
+                let Some((function_value, method)) = self.evaluate_callee(callee)? else {
+                    return Ok(Value::Nil);
+                };
+                if let Some(method) = method {
+                    return self.call_method_with_args(function_value, method, args, &[], span);
+                }
+                match Self::builtin_name(&function_value) {
+                    Some(name) => {
+                        self.enter_call(name, span);
+                        let result = self.call_builtin(function_value, args, span);
+                        self.exit_call(result)
                     }
+                    None => self.call_builtin(function_value, args, span),
                 }
-                
-                // For other function calls, return error since we haven't implemented user-defined functions
-                Err(BccError::runtime_error_with_help(
-                    span.clone(),
-                    "User-defined functions not yet implemented".to_string(),
-                    "Only built-in functions like print(), len(), and type() are currently supported.".to_string(),
-                ))
             }
             Expr::Grouping { expr, .. } => self.evaluate_expression(expr),
             Expr::List { elements, .. } => {
@@ -326,167 +428,63 @@ impl Evaluator {
                 for element in elements {
                     list_values.push(self.evaluate_expression(element)?);
                 }
-                Ok(Value::List(list_values))
+                Ok(Value::list(list_values))
             }
             Expr::Dict { pairs, span } => {
-                let mut dict_values = HashMap::new();
+                let mut dict_values = OrderedMap::new();
                 for (key_expr, value_expr) in pairs {
                     let key_value = self.evaluate_expression(key_expr)?;
                     let value_value = self.evaluate_expression(value_expr)?;
                     
-                    // Dictionary keys must be strings
-                    let key_string = match key_value {
-                        Value::String(s) => s,
-                        _ => return Err(BccError::runtime_error(
-                            span.clone(),
-                            format!("Dictionary keys must be strings, got {}", key_value.type_name()),
-                        )),
-                    };
-                    
-                    dict_values.insert(key_string, value_value);
+                    let key = Self::dictionary_key(key_value, span, key_expr.span())?;
+                    dict_values.insert(key, value_value);
+                }
+                Ok(Value::dict(dict_values))
+            }
+            Expr::Set { elements, span } => {
+                let mut set_elements = OrderedMap::new();
+                for element in elements {
+                    let element_value = self.evaluate_expression(element)?;
+                    set_elements.insert(Self::set_element(element_value, span, element.span())?, ());
                 }
-                Ok(Value::Dict(dict_values))
+                Ok(Value::set(set_elements))
             }
-            Expr::PropertyAccess { object, property, span } => {
+            Expr::PropertyAccess { object, property, optional, span } => {
                 let object_value = self.evaluate_expression(object)?;
-                
-                match object_value {
-                    Value::CaseResult(case_result) => {
-                        match property.as_str() {
-                            "result" => Ok(*case_result.result),
-                            _ => Err(BccError::runtime_error_with_help(
-                                span.clone(),
-                                format!("Unknown property '{}' on case_result", property),
-                                "case_result objects only have a 'result' property.".to_string(),
-                            )),
-                        }
-                    },
-                    _ => Err(BccError::runtime_error_with_help(
-                        span.clone(),
-                        format!("Property access not supported for type {}", object_value.type_name()),
-                        "Property access is currently only supported for case_result objects.".to_string(),
-                    )),
+                if *optional && matches!(object_value, Value::Nil) {
+                    return Ok(Value::Nil);
                 }
+                Self::property(object_value, property, span, object.span())
             }
             Expr::MultiAssign { targets, value, span } => {
                 let values = self.evaluate_expression(value)?;
-                
-                // Handle multi-assignment unpacking
-                let unpacked_values = match &values {
-                    Value::Tuple(tuple_values) => tuple_values.clone(),
-                    Value::List(list_values) => list_values.clone(),
-                    single_value => vec![single_value.clone()], // Single value gets wrapped in a list
-                };
-                
-                // Check if we have the right number of values
-                let non_ignore_targets = targets.iter().filter(|t| matches!(t, crate::ast::AssignTarget::Variable { .. })).count();
-                
-                if unpacked_values.len() < non_ignore_targets {
-                    return Err(BccError::runtime_error_with_help(
-                        span.clone(),
-                        format!("Not enough values to unpack (expected {}, got {})", targets.len(), unpacked_values.len()),
-                        "Multi-assignment requires the same number of values as targets. Use _ to ignore extra values.".to_string(),
-                    ));
-                }
-                
-                // Assign values to targets
-                for (i, target) in targets.iter().enumerate() {
-                    match target {
-                        crate::ast::AssignTarget::Variable { name, .. } => {
-                            if i < unpacked_values.len() {
-                                self.environment.assign(name, unpacked_values[i].clone())?;
-                            }
-                        }
-                        crate::ast::AssignTarget::Ignore { .. } => {
-                            // Ignore this value
-                        }
-                    }
+                let mut bound = Vec::new();
+                Self::destructure(targets, &values, span, value.span(), &mut bound)?;
+
+                // Only variables bind a value; '_' targets skip theirs
+                let names = targets.iter().flat_map(AssignTarget::bindings);
+                for ((name, _), bound_value) in names.zip(bound) {
+                    self.environment.assign(name, bound_value)?;
                 }
-                
+
                 // Return the original tuple/list for chaining
                 Ok(values)
             }
             Expr::CallWithKwargs { callee, positional_args, keyword_args, span } => {
-                let function_value = self.evaluate_expression(callee)?;
-                
-                // Check if it's a built-in function
-                if let Value::String(name) = function_value {
-                    match name.as_str() {
-                        "__builtin_divmod__" => {
-                            // Example builtin showcasing kwargs: divmod(a, b, round_mode="down") 
-                            if positional_args.len() != 2 {
-                                return Err(BccError::runtime_error_with_help(
-                                    span.clone(),
-                                    format!("divmod() takes exactly 2 positional arguments, got {}", positional_args.len()),
-                                    "Usage: divmod(dividend, divisor, round_mode=\"down\")".to_string(),
-                                ));
-                            }
-                            
-                            let dividend = self.evaluate_expression(&positional_args[0])?;
-                            let divisor = self.evaluate_expression(&positional_args[1])?;
-                            
-                            // Parse keyword arguments for rounding mode
-                            let mut round_mode = "down".to_string();
-                            for kwarg in keyword_args {
-                                match kwarg.name.as_str() {
-                                    "round_mode" => {
-                                        let mode_value = self.evaluate_expression(&kwarg.value)?;
-                                        if let Value::String(mode) = mode_value {
-                                            round_mode = mode;
-                                        } else {
-                                            return Err(BccError::runtime_error_with_help(
-                                                span.clone(),
-                                                "round_mode must be a string".to_string(),
-                                                "Valid round modes: \"down\", \"up\", \"nearest\"".to_string(),
-                                            ));
-                                        }
-                                    }
-                                    _ => {
-                                        return Err(BccError::runtime_error_with_help(
-                                            span.clone(),
-                                            format!("Unknown keyword argument '{}' for divmod()", kwarg.name),
-                                            "Valid keyword arguments: round_mode".to_string(),
-                                        ));
-                                    }
-                                }
-                            }
-                            
-                            // Perform division with the specified rounding mode
-                            return self.builtin_divmod(dividend, divisor, round_mode, span);
-                        }
-                        _ => {
-                            // For now, fall back to regular call handling for other builtins
-                            // This allows kwargs calls to regular functions to work
-                            let all_args = positional_args.clone();
-                            
-                            // For now, we'll ignore keyword args for non-kwargs builtins
-                            // In a real implementation, you'd validate the kwargs
-                            if !keyword_args.is_empty() {
-                                return Err(BccError::runtime_error_with_help(
-                                    span.clone(),
-                                    format!("Function '{}' does not accept keyword arguments", name.replace("__builtin_", "").replace("__", "")),
-                                    "Only some built-in functions support keyword arguments.".to_string(),
-                                ));
-                            }
-                            
-                            // Reuse the regular call logic
-                            return self.evaluate_expression(&Expr::Call {
-                                callee: Box::new(Expr::Variable { 
-                                    name: name.clone(), 
-                                    span: callee.span().clone() 
-                                }),
-                                args: all_args,
-                                span: span.clone(),
-                            });
-                        }
+                let Some((function_value, method)) = self.evaluate_callee(callee)? else {
+                    return Ok(Value::Nil);
+                };
+                if let Some(method) = method {
+                    return self.call_method_with_args(function_value, method, positional_args, keyword_args, span);
+                }
+                match Self::builtin_name(&function_value) {
+                    Some(name) => {
+                        self.enter_call(name, span);
+                        let result = self.call_builtin_with_kwargs(function_value, positional_args, keyword_args, span);
+                        self.exit_call(result)
                     }
+                    None => self.call_builtin_with_kwargs(function_value, positional_args, keyword_args, span),
                 }
-                
-                Err(BccError::runtime_error_with_help(
-                    span.clone(),
-                    "User-defined functions with kwargs not yet implemented".to_string(),
-                    "Only built-in functions support keyword arguments currently.".to_string(),
-                ))
             }
             Expr::MultiReturn { values, .. } => {
                 let mut result_values = Vec::new();
@@ -502,46 +500,597 @@ impl Evaluator {
                 }
                 Ok(Value::Tuple(tuple_values))
             }
+            Expr::Match { subject, arms, .. } => {
+                let value = self.evaluate_expression(subject)?;
+                for arm in arms {
+                    let mut bound = Vec::new();
+                    if !Self::match_pattern(&arm.pattern, &value, &mut bound) {
+                        continue;
+                    }
+                    // Names are assigned before the guard runs, so the guard can read them
+                    for ((name, _), bound_value) in arm.pattern.bindings().into_iter().zip(bound) {
+                        self.environment.assign(name, bound_value)?;
+                    }
+                    if let Some(guard) = &arm.guard {
+                        if !self.evaluate_expression(guard)?.is_truthy() {
+                            continue;
+                        }
+                    }
+                    return self.evaluate_expression(&arm.body);
+                }
+                Err(Self::no_matching_arm_error(&value, subject.span()))
+            }
         }
     }
 
-    fn builtin_divmod(&self, dividend: Value, divisor: Value, round_mode: String, span: &Span) -> Result<Value, BccError> {
-        let dividend_type = dividend.type_name();
-        let divisor_type = divisor.type_name();
-        match (dividend, divisor) {
-            (Value::Int(a), Value::Int(b)) => {
-                if b == 0 {
-                    return Err(BccError::runtime_error(
-                        span.clone(),
-                        "Division by zero".to_string(),
-                    ));
+    /// Whether `value` matches `pattern`. The values of the names the pattern binds are
+    /// pushed onto `bound` in the order `Pattern::bindings` lists them.
+    pub(crate) fn match_pattern(pattern: &Pattern, value: &Value, bound: &mut Vec<Value>) -> bool {
+        match pattern {
+            Pattern::Target(target) => Self::destructure_target(target, value, &mut Vec::new(), target.span(), bound).is_ok(),
+            Pattern::Literal { value: literal, .. } => value == literal,
+            Pattern::Sequence { elements, .. } => {
+                let values = match value {
+                    Value::Tuple(values) => values.clone(),
+                    Value::List(values) => values.borrow().clone(),
+                    _ => return false,
+                };
+                let star = elements.iter().position(|element| matches!(element, Pattern::Target(AssignTarget::Starred { .. })));
+                Self::split_for_targets(values, elements.len(), star).is_some_and(|values| {
+                    elements.iter().zip(&values).all(|(element, value)| Self::match_pattern(element, value, bound))
+                })
+            }
+            Pattern::Dict { entries, .. } => {
+                let Value::Dict(dict) = value else {
+                    return false;
+                };
+                entries.iter().all(|(key, pattern)| {
+                    let entry = Key::from_value(key).ok().and_then(|key| dict.borrow().get(&key).cloned());
+                    entry.is_some_and(|entry| Self::match_pattern(pattern, &entry, bound))
+                })
+            }
+        }
+    }
+
+    pub(crate) fn no_matching_arm_error(value: &Value, subject_span: &Span) -> BccError {
+        BccError::runtime_error_with_help(
+            subject_span.clone(),
+            format!("No match arm matches this {}", value.type_name()),
+            "Add a final '_ => ...' arm to handle every other value.".to_string(),
+        )
+        .with_code(error_codes::NO_MATCHING_ARM)
+    }
+
+    /// Evaluate the callee of a call. `object.name` on a list or dictionary is a method,
+    /// returned with the object it is called on; anything else is called as a value.
+    /// `None` means `object?.name` found a nil object, so the call is skipped.
+    fn evaluate_callee(&mut self, callee: &Expr) -> Result<Option<(Value, Option<Method>)>, BccError> {
+        match callee {
+            Expr::PropertyAccess { object, property, optional, span } => {
+                let object_value = self.evaluate_expression(object)?;
+                if *optional && matches!(object_value, Value::Nil) {
+                    return Ok(None);
                 }
-                
-                let (quotient, remainder) = match round_mode.as_str() {
-                    "down" => (a / b, a % b),
-                    "up" => {
-                        let q = if a * b >= 0 { (a + b - 1) / b } else { a / b };
-                        (q, a - q * b)
+                match Self::method(&object_value, property, span, object.span())? {
+                    Some(method) => Ok(Some((object_value, Some(method)))),
+                    None => Ok(Some((Self::property(object_value, property, span, object.span())?, None))),
+                }
+            }
+            callee => Ok(Some((self.evaluate_expression(callee)?, None))),
+        }
+    }
+
+    fn call_method_with_args(
+        &mut self,
+        receiver: Value,
+        method: Method,
+        args: &[Expr],
+        keyword_args: &[KeywordArg],
+        span: &Span,
+    ) -> Result<Value, BccError> {
+        self.enter_call(method.name().to_string(), span);
+        let result = (|| {
+            if let Some(kwarg) = keyword_args.first() {
+                return Err(Self::keywords_not_accepted_error(method.name(), &kwarg.span, span));
+            }
+            method.check_arity(args.len(), span)?;
+            let mut values = Vec::new();
+            for arg in args {
+                values.push(self.evaluate_argument(arg)?);
+            }
+            let arg_spans: Vec<Span> = args.iter().map(|arg| arg.span().clone()).collect();
+            Self::call_method(receiver, method, values, span, &arg_spans)
+        })();
+        self.exit_call(result)
+    }
+
+    fn call_builtin(&mut self, function_value: Value, args: &[Expr], span: &Span) -> Result<Value, BccError> {
+        // Check if it's a built-in function
+        if let Value::String(name) = function_value {
+            Self::check_arity(&name, args.len(), span)?;
+            match name.as_str() {
+                "__builtin_print__" => {
+                    // Handle print built-in
+                    for arg in args {
+                        let value = self.evaluate_argument(arg)?;
+                        println!("{}", value);
                     }
-                    "nearest" => {
-                        let q = ((a as f64) / (b as f64)).round() as i64;
-                        (q, a - q * b)
+                    return Ok(Value::Nil);
+                },
+                "__builtin_len__" => {
+                    let arg_value = self.evaluate_argument(&args[0])?;
+                    return Self::builtin_len(arg_value, span, args[0].span());
+                },
+                "__builtin_type__" => {
+                    let arg_value = self.evaluate_argument(&args[0])?;
+                    return Ok(Value::String(arg_value.type_name().to_string()));
+                },
+                "__builtin_case__" => {
+                    // Evaluate condition-result pairs in order
+                    for i in (0..args.len()).step_by(2) {
+                        let condition_value = self.evaluate_argument(&args[i])?;
+                        if condition_value.is_truthy() {
+                            let result_value = self.evaluate_argument(&args[i + 1])?;
+                            return Ok(Value::CaseResult(CaseResult {
+                                result: Box::new(result_value),
+                            }));
+                        }
                     }
-                    _ => return Err(BccError::runtime_error_with_help(
+                    
+                    // If no condition matches, return nil wrapped in CaseResult
+                    return Ok(Value::CaseResult(CaseResult {
+                        result: Box::new(Value::Nil),
+                    }));
+                },
+                "__builtin_set__" => {
+                    let Some(arg) = args.first() else {
+                        return Ok(Value::set(OrderedMap::new()));
+                    };
+                    let arg_value = self.evaluate_argument(arg)?;
+                    return Self::builtin_set(arg_value, span, arg.span());
+                },
+                "__builtin_divmod__" => {
+                    // Handle divmod built-in without kwargs (default rounding mode)
+                    let dividend = self.evaluate_argument(&args[0])?;
+                    let divisor = self.evaluate_argument(&args[1])?;
+                    
+                    return self.builtin_divmod(dividend, divisor, "down".to_string(), span);
+                },
+                _ => {}
+            }
+        }
+        
+        // For other function calls, return error since we haven't implemented user-defined functions
+        Err(Self::not_callable_error(span, false))
+    }
+
+    fn call_builtin_with_kwargs(
+        &mut self,
+        function_value: Value,
+        positional_args: &[Expr],
+        keyword_args: &[KeywordArg],
+        span: &Span,
+    ) -> Result<Value, BccError> {
+        // Check if it's a built-in function
+        if let Value::String(name) = function_value {
+            match name.as_str() {
+                "__builtin_divmod__" => {
+                    // Example builtin showcasing kwargs: divmod(a, b, round_mode="down") 
+                    if positional_args.len() != 2 {
+                        return Err(Self::divmod_positional_count_error(positional_args.len(), span));
+                    }
+                    
+                    let dividend = self.evaluate_argument(&positional_args[0])?;
+                    let divisor = self.evaluate_argument(&positional_args[1])?;
+                    
+                    // Parse keyword arguments for rounding mode
+                    let mut round_mode = "down".to_string();
+                    for kwarg in keyword_args {
+                        if !DIVMOD_KEYWORDS.contains(&kwarg.name.as_str()) {
+                            return Err(Self::unknown_keyword_error(&kwarg.name, &kwarg.span, span));
+                        }
+                        let mode_value = self.evaluate_argument(&kwarg.value)?;
+                        round_mode = Self::round_mode(mode_value, span, kwarg.value.span())?;
+                    }
+                    
+                    // Perform division with the specified rounding mode
+                    return self.builtin_divmod(dividend, divisor, round_mode, span);
+                }
+                _ => {
+                    // Other builtins take no keyword arguments; without any, this is a regular call
+                    if let Some(kwarg) = keyword_args.first() {
+                        return Err(Self::keywords_not_accepted_error(&name, &kwarg.span, span));
+                    }
+                    
+                    // Reuse the regular call logic; the caller has already pushed this call's frame
+                    return self.call_builtin(Value::String(name), positional_args, span);
+                }
+            }
+        }
+        
+        Err(Self::not_callable_error(span, true))
+    }
+
+    // The helpers below work on already-evaluated values so the tree-walker and the
+    // bytecode VM share one definition of every built-in result and diagnostic.
+
+    /// Reject a wrong positional argument count before any argument is evaluated
+    pub(crate) fn check_arity(builtin: &str, count: usize, span: &Span) -> Result<(), BccError> {
+        let (message, help) = match builtin {
+            "__builtin_len__" if count != 1 => (
+                format!("len() takes exactly 1 argument, got {}", count),
+                "Usage: len(value) where value is a string, list, dictionary, or set.",
+            ),
+            "__builtin_type__" if count != 1 => (
+                format!("type() takes exactly 1 argument, got {}", count),
+                "Usage: type(value) returns the type name as a string.",
+            ),
+            "__builtin_case__" if count < 2 || !count.is_multiple_of(2) => (
+                format!("case() requires an even number of arguments (at least 2), got {}", count),
+                "Usage: case(condition1, result1, condition2, result2, ...). Each condition is paired with its result.",
+            ),
+            "__builtin_set__" if count > 1 => (
+                format!("set() takes at most 1 argument, got {}", count),
+                "Usage: set() makes an empty set; set(values) collects the elements of a list, tuple, set, dictionary or string.",
+            ),
+            "__builtin_divmod__" if count != 2 => (
+                format!("divmod() takes exactly 2 arguments, got {}", count),
+                "Usage: divmod(dividend, divisor) - use divmod(a, b, round_mode=\"up\") for keyword arguments.",
+            ),
+            _ => return Ok(()),
+        };
+        Err(BccError::runtime_error_with_help(span.clone(), message, help.to_string())
+            .with_code(error_codes::WRONG_ARGUMENT_COUNT))
+    }
+
+    pub(crate) fn divmod_positional_count_error(count: usize, span: &Span) -> BccError {
+        BccError::runtime_error_with_help(
+            span.clone(),
+            format!("divmod() takes exactly 2 positional arguments, got {}", count),
+            "Usage: divmod(dividend, divisor, round_mode=\"down\")".to_string(),
+        ).with_code(error_codes::WRONG_ARGUMENT_COUNT)
+    }
+
+    pub(crate) fn not_callable_error(span: &Span, with_keywords: bool) -> BccError {
+        let (message, help) = if with_keywords {
+            ("User-defined functions with kwargs not yet implemented", "Only built-in functions support keyword arguments currently.")
+        } else {
+            ("User-defined functions not yet implemented", "Only built-in functions like print(), len(), and type() are currently supported.")
+        };
+        BccError::runtime_error_with_help(span.clone(), message.to_string(), help.to_string())
+            .with_code(error_codes::NOT_CALLABLE)
+    }
+
+    pub(crate) fn keywords_not_accepted_error(builtin: &str, keyword_span: &Span, span: &Span) -> BccError {
+        BccError::runtime_error_with_help(
+            span.clone(),
+            format!("Function '{}' does not accept keyword arguments", builtin.replace("__builtin_", "").replace("__", "")),
+            "Only some built-in functions support keyword arguments.".to_string(),
+        ).with_code(error_codes::INVALID_KEYWORD_ARGUMENT).with_label(keyword_span.clone(), "keyword argument passed here".to_string())
+        .with_note("divmod() is currently the only built-in that takes keyword arguments".to_string())
+    }
+
+    pub(crate) fn unknown_keyword_error(keyword: &str, keyword_span: &Span, span: &Span) -> BccError {
+        BccError::runtime_error_with_help(
+            span.clone(),
+            format!("Unknown keyword argument '{}' for divmod()", keyword),
+            match suggest::help_for(keyword, DIVMOD_KEYWORDS.iter().copied()) {
+                Some(suggestion) => format!("{} Valid keyword arguments: round_mode", suggestion),
+                None => "Valid keyword arguments: round_mode".to_string(),
+            },
+        ).with_code(error_codes::INVALID_KEYWORD_ARGUMENT).with_label(keyword_span.clone(), "unknown keyword argument".to_string())
+    }
+
+    /// Validate the value passed as divmod's round_mode
+    pub(crate) fn round_mode(mode_value: Value, span: &Span, value_span: &Span) -> Result<String, BccError> {
+        match mode_value {
+            Value::String(mode) => Ok(mode),
+            _ => Err(BccError::runtime_error_with_help(
+                span.clone(),
+                "round_mode must be a string".to_string(),
+                "Valid round modes: \"down\", \"up\", \"nearest\"".to_string(),
+            ).with_code(error_codes::UNSUPPORTED_ARGUMENT_TYPE).with_label(value_span.clone(), format!("this has type {}", mode_value.type_name()))),
+        }
+    }
+
+    pub(crate) fn builtin_len(arg_value: Value, span: &Span, arg_span: &Span) -> Result<Value, BccError> {
+        match arg_value {
+            Value::String(s) => Ok(Value::Int(s.chars().count() as i64)),
+            Value::List(l) => Ok(Value::Int(l.borrow().len() as i64)),
+            Value::Dict(d) => Ok(Value::Int(d.borrow().len() as i64)),
+            Value::Set(s) => Ok(Value::Int(s.borrow().len() as i64)),
+            _ => Err(BccError::runtime_error_with_help(
+                span.clone(),
+                format!("len() not supported for type {}", arg_value.type_name()),
+                "len() only works with strings, lists, dictionaries, and sets.".to_string(),
+            ).with_code(error_codes::UNSUPPORTED_ARGUMENT_TYPE).with_label(arg_span.clone(), format!("this argument has type {}", arg_value.type_name()))),
+        }
+    }
+
+    pub(crate) fn dictionary_key(key_value: Value, span: &Span, key_span: &Span) -> Result<Key, BccError> {
+        Self::hashable(key_value, "a dictionary key", "key", span, key_span)
+    }
+
+    pub(crate) fn set_element(element_value: Value, span: &Span, element_span: &Span) -> Result<Key, BccError> {
+        Self::hashable(element_value, "a set element", "element", span, element_span)
+    }
+
+    fn hashable(value: Value, role: &str, noun: &str, span: &Span, value_span: &Span) -> Result<Key, BccError> {
+        Key::from_value(&value).map_err(|unhashable| {
+            BccError::runtime_error_with_help(
+                span.clone(),
+                format!("Cannot use {} as {}", unhashable, role),
+                HASHABLE_KEYS_HELP.to_string(),
+            ).with_code(error_codes::INVALID_DICTIONARY_KEY).with_label(value_span.clone(), format!("this {} has type {}", noun, value.type_name()))
+        })
+    }
+
+    /// Elements of an iterable value, in iteration order; dictionaries yield their keys
+    pub(crate) fn elements(value: &Value) -> Option<Vec<Value>> {
+        match value {
+            Value::List(items) => Some(items.borrow().clone()),
+            Value::Tuple(items) => Some(items.clone()),
+            Value::Set(elements) => Some(elements.borrow().keys().map(Key::to_value).collect()),
+            Value::Dict(entries) => Some(entries.borrow().keys().map(Key::to_value).collect()),
+            Value::String(s) => Some(s.chars().map(|c| Value::String(c.to_string())).collect()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn builtin_set(arg_value: Value, span: &Span, arg_span: &Span) -> Result<Value, BccError> {
+        let Some(values) = Self::elements(&arg_value) else {
+            return Err(BccError::runtime_error_with_help(
+                span.clone(),
+                format!("set() not supported for type {}", arg_value.type_name()),
+                "set() collects the elements of a list, tuple, set, dictionary or string.".to_string(),
+            ).with_code(error_codes::UNSUPPORTED_ARGUMENT_TYPE).with_label(arg_span.clone(), format!("this argument has type {}", arg_value.type_name())));
+        };
+        let mut elements = OrderedMap::new();
+        for value in values {
+            elements.insert(Self::set_element(value, span, arg_span)?, ());
+        }
+        Ok(Value::set(elements))
+    }
+
+    pub(crate) fn property(object_value: Value, property: &str, span: &Span, object_span: &Span) -> Result<Value, BccError> {
+        match object_value {
+            Value::CaseResult(case_result) => {
+                match property {
+                    "result" => Ok(*case_result.result),
+                    _ => Err(BccError::runtime_error_with_help(
                         span.clone(),
-                        format!("Unknown rounding mode '{}'", round_mode),
-                        "Valid rounding modes: \"down\", \"up\", \"nearest\"".to_string(),
-                    )),
-                };
-                
-                Ok(Value::Tuple(vec![Value::Int(quotient), Value::Int(remainder)]))
+                        format!("Unknown property '{}' on case_result", property),
+                        match suggest::help_for(property, CASE_RESULT_PROPERTIES.iter().copied()) {
+                            Some(suggestion) => format!("{} case_result objects only have a 'result' property.", suggestion),
+                            None => "case_result objects only have a 'result' property.".to_string(),
+                        },
+                    ).with_code(error_codes::UNKNOWN_PROPERTY)),
+                }
+            },
+            _ => Err(BccError::runtime_error_with_help(
+                span.clone(),
+                format!("Property access not supported for type {}", object_value.type_name()),
+                "Property access is currently only supported for case_result objects.".to_string(),
+            ).with_code(error_codes::UNKNOWN_PROPERTY).with_label(object_span.clone(), format!("this has type {}", object_value.type_name()))),
+        }
+    }
+
+    /// Look up a method on a list, dictionary or set; other types have no methods, so their
+    /// properties are called as values instead
+    pub(crate) fn method(object_value: &Value, name: &str, span: &Span, object_span: &Span) -> Result<Option<Method>, BccError> {
+        let methods = match object_value {
+            Value::List(_) => LIST_METHODS,
+            Value::Dict(_) => DICT_METHODS,
+            Value::Set(_) => SET_METHODS,
+            _ => return Ok(None),
+        };
+        match (object_value, name) {
+            (Value::List(_), "append") => Ok(Some(Method::Append)),
+            (Value::Dict(_), "update") => Ok(Some(Method::Update)),
+            (Value::Set(_), "add") => Ok(Some(Method::Add)),
+            (Value::Set(_), "remove") => Ok(Some(Method::Remove)),
+            _ => {
+                let available = format!("{} values have the methods: {}.", object_value.type_name(), methods.join(", "));
+                Err(BccError::runtime_error_with_help(
+                    span.clone(),
+                    format!("Unknown method '{}' on {}", name, object_value.type_name()),
+                    match suggest::help_for(name, methods.iter().copied()) {
+                        Some(suggestion) => format!("{} {}", suggestion, available),
+                        None => available,
+                    },
+                ).with_code(error_codes::UNKNOWN_METHOD).with_label(object_span.clone(), format!("this has type {}", object_value.type_name())))
+            }
+        }
+    }
+
+    /// Run a method on the container it was looked up on. Containers are shared, so the
+    /// change is visible through every name that refers to the receiver.
+    pub(crate) fn call_method(receiver: Value, method: Method, mut args: Vec<Value>, span: &Span, arg_spans: &[Span]) -> Result<Value, BccError> {
+        let argument = args.remove(0);
+        match (method, receiver) {
+            (Method::Append, Value::List(list)) => list.borrow_mut().push(argument),
+            (Method::Update, Value::Dict(dict)) => match argument {
+                Value::Dict(other) => {
+                    // Copy first: `d.update(d)` borrows the same dictionary twice
+                    let entries = other.borrow().clone();
+                    dict.borrow_mut().extend(entries);
+                }
+                _ => return Err(BccError::runtime_error_with_help(
+                    span.clone(),
+                    format!("update() requires a dict, got {}", argument.type_name()),
+                    "Usage: dict.update(other) copies every entry of other into dict.".to_string(),
+                ).with_code(error_codes::UNSUPPORTED_ARGUMENT_TYPE).with_label(arg_spans[0].clone(), format!("this argument has type {}", argument.type_name()))),
+            },
+            (Method::Add, Value::Set(set)) => {
+                let element = Self::set_element(argument, span, &arg_spans[0])?;
+                set.borrow_mut().insert(element, ());
+            }
+            (Method::Remove, Value::Set(set)) => {
+                let element = Self::set_element(argument, span, &arg_spans[0])?;
+                if set.borrow_mut().remove(&element).is_none() {
+                    return Err(BccError::runtime_error_with_help(
+                        span.clone(),
+                        format!("{} is not in the set", element),
+                        "Check membership with 'in' before removing an element.".to_string(),
+                    ).with_code(error_codes::MISSING_ELEMENT).with_label(arg_spans[0].clone(), "this element is missing".to_string()));
+                }
+            }
+            (method, receiver) => unreachable!("{} is not a method of {}", method.name(), receiver.type_name()),
+        }
+        Ok(Value::Nil)
+    }
+
+    /// Destructure the right-hand side of a multi-assignment into its targets. The values
+    /// of the names the targets bind are pushed onto `bound` in the order
+    /// `AssignTarget::bindings` lists them.
+    pub(crate) fn destructure(
+        targets: &[AssignTarget],
+        value: &Value,
+        span: &Span,
+        value_span: &Span,
+        bound: &mut Vec<Value>,
+    ) -> Result<(), BccError> {
+        Self::destructure_into(targets, value, &mut Vec::new(), span, value_span, bound)
+    }
+
+    /// `path` holds the 1-based positions of the nested target list being filled, and
+    /// `label_span` is the source of its values: the right-hand side at the top level,
+    /// the nested target below it
+    fn destructure_into(
+        targets: &[AssignTarget],
+        value: &Value,
+        path: &mut Vec<usize>,
+        span: &Span,
+        label_span: &Span,
+        bound: &mut Vec<Value>,
+    ) -> Result<(), BccError> {
+        let values = match value {
+            Value::Tuple(tuple_values) => tuple_values.clone(),
+            Value::List(list_values) => list_values.borrow().clone(),
+            Value::Set(elements) => elements.borrow().keys().map(Key::to_value).collect(),
+            single_value => vec![single_value.clone()], // Single value gets wrapped in a list
+        };
+        let star = targets.iter().position(|target| matches!(target, AssignTarget::Starred { .. }));
+        let got = values.len();
+
+        let Some(values) = Self::split_for_targets(values, targets.len(), star) else {
+            let required = targets.len() - star.map_or(0, |_| 1);
+            let message = match star {
+                Some(_) => format!("Not enough values to unpack (expected at least {}, got {})", required, got),
+                None if got < required => format!("Not enough values to unpack (expected {}, got {})", required, got),
+                None => format!("Too many values to unpack (expected {}, got {})", required, got),
+            };
+            let (message, label) = if path.is_empty() {
+                (message, format!("this produces {} value(s)", got))
+            } else {
+                let position: Vec<String> = path.iter().map(usize::to_string).collect();
+                (format!("{} at position {}", message, position.join(".")), format!("this target gets {} value(s)", got))
+            };
+            return Err(BccError::runtime_error_with_help(
+                span.clone(),
+                message,
+                "Multi-assignment requires the same number of values as targets. Use _ to ignore a value or *rest to collect the extra ones.".to_string(),
+            ).with_code(error_codes::UNPACK_MISMATCH).with_label(label_span.clone(), label));
+        };
+
+        for (position, (target, value)) in targets.iter().zip(&values).enumerate() {
+            path.push(position + 1);
+            Self::destructure_target(target, value, path, span, bound)?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    fn destructure_target(
+        target: &AssignTarget,
+        value: &Value,
+        path: &mut Vec<usize>,
+        span: &Span,
+        bound: &mut Vec<Value>,
+    ) -> Result<(), BccError> {
+        match target {
+            AssignTarget::Variable { .. } => bound.push(value.clone()),
+            AssignTarget::Ignore { .. } => {}
+            AssignTarget::Starred { target, .. } => Self::destructure_target(target, value, path, span, bound)?,
+            AssignTarget::Nested { targets, span: nested_span } => {
+                Self::destructure_into(targets, value, path, span, nested_span, bound)?
             }
+        }
+        Ok(())
+    }
+
+    /// One value per target, or `None` if the counts don't fit. The starred target at
+    /// `star`, if any, takes a list of whatever the targets around it leave over.
+    pub(crate) fn split_for_targets(mut values: Vec<Value>, count: usize, star: Option<usize>) -> Option<Vec<Value>> {
+        let Some(star) = star else {
+            return (values.len() == count).then_some(values);
+        };
+        let after = count - star - 1;
+        if values.len() < count - 1 {
+            return None;
+        }
+        let rest: Vec<Value> = values.drain(star..values.len() - after).collect();
+        values.insert(star, Value::list(rest));
+        Some(values)
+    }
+
+    /// Undefined variable error with "did you mean" help drawn from every visible name
+    pub(crate) fn undefined_variable_error(name: &str, span: &Span, visible: &[String]) -> BccError {
+        let message = format!("Undefined variable '{}'", name);
+        let candidates = visible.iter().map(String::as_str).chain(BUILTIN_NAMES.iter().copied());
+        match suggest::help_for(name, candidates) {
+            Some(help) => BccError::runtime_error_with_help(span.clone(), message, help),
+            None => BccError::runtime_error(span.clone(), message),
+        }
+        .with_code(error_codes::UNDEFINED_VARIABLE)
+    }
+
+    /// User-facing name of a built-in function value, e.g. "len" for len()
+    pub(crate) fn builtin_name(value: &Value) -> Option<String> {
+        match value {
+            Value::String(name) => name
+                .strip_prefix("__builtin_")
+                .and_then(|rest| rest.strip_suffix("__"))
+                .map(|rest| rest.to_string()),
+            _ => None,
+        }
+    }
+
+    fn enter_call(&mut self, function: String, call_site: &Span) {
+        self.call_stack.push(StackFrame {
+            function,
+            call_site: call_site.clone(),
+        });
+    }
+
+    /// Pop the innermost frame, recording it on any error the call itself raised
+    fn exit_call(&mut self, result: Result<Value, BccError>) -> Result<Value, BccError> {
+        let frame = self.call_stack.pop().expect("exit_call without matching enter_call");
+        let entered = !std::mem::take(&mut self.argument_failed);
+        result.map_err(|error| if entered { error.with_frame(frame) } else { error })
+    }
+
+    /// Evaluate an argument of the innermost call. Its errors belong to the caller,
+    /// so they leave the call without its frame
+    fn evaluate_argument(&mut self, arg: &Expr) -> Result<Value, BccError> {
+        let result = self.evaluate_expression(arg);
+        self.argument_failed = result.is_err();
+        result
+    }
+
+    pub(crate) fn builtin_divmod(&self, dividend: Value, divisor: Value, round_mode: String, span: &Span) -> Result<Value, BccError> {
+        let dividend_type = dividend.type_name();
+        let divisor_type = divisor.type_name();
+        if let (Some(a), Some(b)) = (dividend.to_bigint(), divisor.to_bigint()) {
+            return Self::integer_divmod(a, b, &round_mode, span);
+        }
+        match (dividend, divisor) {
             (Value::Double(a), Value::Double(b)) => {
                 if b == 0.0 {
                     return Err(BccError::runtime_error(
                         span.clone(),
                         "Division by zero".to_string(),
-                    ));
+                    ).with_code(error_codes::DIVISION_BY_ZERO));
                 }
                 
                 let quotient = match round_mode.as_str() {
@@ -552,7 +1101,7 @@ impl Evaluator {
                         span.clone(),
                         format!("Unknown rounding mode '{}'", round_mode),
                         "Valid rounding modes: \"down\", \"up\", \"nearest\"".to_string(),
-                    )),
+                    ).with_code(error_codes::INVALID_ARGUMENT_VALUE)),
                 };
                 let remainder = a - quotient * b;
                 
@@ -564,24 +1113,93 @@ impl Evaluator {
             (Value::Double(a), Value::Int(b)) => {
                 self.builtin_divmod(Value::Double(a), Value::Double(b as f64), round_mode, span)
             }
+            (Value::BigInt(a), Value::Double(b)) => {
+                self.builtin_divmod(Value::Double(a.to_f64()), Value::Double(b), round_mode, span)
+            }
+            (Value::Double(a), Value::BigInt(b)) => {
+                self.builtin_divmod(Value::Double(a), Value::Double(b.to_f64()), round_mode, span)
+            }
             _ => Err(BccError::runtime_error_with_help(
                 span.clone(),
                 format!("divmod() not supported for types {} and {}", dividend_type, divisor_type),
                 "divmod() only works with numbers (int and double).".to_string(),
-            )),
+            ).with_code(error_codes::UNSUPPORTED_ARGUMENT_TYPE)),
+        }
+    }
+
+    /// divmod() of two ints, computed exactly at any size: "down" truncates toward zero,
+    /// "up" rounds toward positive infinity and "nearest" rounds halves away from zero
+    fn integer_divmod(a: BigInt, b: BigInt, round_mode: &str, span: &Span) -> Result<Value, BccError> {
+        if b.is_zero() {
+            return Err(BccError::runtime_error(
+                span.clone(),
+                "Division by zero".to_string(),
+            ).with_code(error_codes::DIVISION_BY_ZERO));
+        }
+
+        let (truncated, remainder) = a.div_rem(&b);
+        let same_sign = a.is_negative() == b.is_negative();
+        let away_from_zero = match round_mode {
+            "down" => false,
+            "up" => same_sign && !remainder.is_zero(),
+            "nearest" => (&remainder + &remainder).abs() >= b.abs(),
+            _ => return Err(BccError::runtime_error_with_help(
+                span.clone(),
+                format!("Unknown rounding mode '{}'", round_mode),
+                "Valid rounding modes: \"down\", \"up\", \"nearest\"".to_string(),
+            ).with_code(error_codes::INVALID_ARGUMENT_VALUE)),
+        };
+        let quotient = if away_from_zero {
+            &truncated + &BigInt::from(if same_sign { 1 } else { -1 })
+        } else {
+            truncated
+        };
+        let remainder = &a - &(&quotient * &b);
+        Ok(Value::Tuple(vec![Value::integer(quotient), Value::integer(remainder)]))
+    }
+
+    /// A binary operation whose errors label both operands with their types, or
+    /// only the divisor when it is zero
+    #[inline]
+    pub(crate) fn labeled_binary_op(
+        &self,
+        operator: &BinaryOp,
+        left: Value,
+        right: Value,
+        span: &Span,
+        left_span: &Span,
+        right_span: &Span,
+    ) -> Result<Value, BccError> {
+        if let Some(result) = small_int_op(operator, &left, &right) {
+            return Ok(result);
         }
+        let left_type = left.type_name();
+        let right_type = right.type_name();
+        self.evaluate_binary_op(operator, left, right, span).map_err(|error| {
+            if error.code == Some(error_codes::DIVISION_BY_ZERO) {
+                return error.with_label(right_span.clone(), "divisor is zero".to_string());
+            }
+            error
+                .with_label(left_span.clone(), format!("this has type {}", left_type))
+                .with_label(right_span.clone(), format!("this has type {}", right_type))
+        })
     }
 
-    fn evaluate_binary_op(
+    pub(crate) fn evaluate_binary_op(
         &self,
         operator: &BinaryOp,
         left: Value,
         right: Value,
         span: &Span,
     ) -> Result<Value, BccError> {
+        if matches!(left, Value::BigInt(_)) || matches!(right, Value::BigInt(_)) {
+            if let Some(result) = self.big_integer_op(operator, &left, &right, span) {
+                return result;
+            }
+        }
         match operator {
             BinaryOp::Add => match (left, right) {
-                (Value::Int(l), Value::Int(r)) => Ok(Value::Int(l + r)),
+                (Value::Int(l), Value::Int(r)) => Ok(promote(l.checked_add(r), || &BigInt::from(l) + &BigInt::from(r))),
                 (Value::Double(l), Value::Double(r)) => Ok(Value::Double(l + r)),
                 (Value::Int(l), Value::Double(r)) => Ok(Value::Double(l as f64 + r)),
                 (Value::Double(l), Value::Int(r)) => Ok(Value::Double(l + r as f64)),
@@ -593,13 +1211,14 @@ impl Evaluator {
                         l.type_name(),
                         r.type_name()
                     ),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
             BinaryOp::Subtract => match (left, right) {
-                (Value::Int(l), Value::Int(r)) => Ok(Value::Int(l - r)),
+                (Value::Int(l), Value::Int(r)) => Ok(promote(l.checked_sub(r), || &BigInt::from(l) - &BigInt::from(r))),
                 (Value::Double(l), Value::Double(r)) => Ok(Value::Double(l - r)),
                 (Value::Int(l), Value::Double(r)) => Ok(Value::Double(l as f64 - r)),
                 (Value::Double(l), Value::Int(r)) => Ok(Value::Double(l - r as f64)),
+                (Value::Set(l), Value::Set(r)) => Ok(set_operation(&l.borrow(), &r.borrow(), |in_left, in_right| in_left && !in_right)),
                 (l, r) => Err(BccError::runtime_error(
                     span.clone(),
                     format!(
@@ -607,10 +1226,10 @@ impl Evaluator {
                         l.type_name(),
                         r.type_name()
                     ),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
             BinaryOp::Multiply => match (left, right) {
-                (Value::Int(l), Value::Int(r)) => Ok(Value::Int(l * r)),
+                (Value::Int(l), Value::Int(r)) => Ok(promote(l.checked_mul(r), || &BigInt::from(l) * &BigInt::from(r))),
                 (Value::Double(l), Value::Double(r)) => Ok(Value::Double(l * r)),
                 (Value::Int(l), Value::Double(r)) => Ok(Value::Double(l as f64 * r)),
                 (Value::Double(l), Value::Int(r)) => Ok(Value::Double(l * r as f64)),
@@ -621,7 +1240,7 @@ impl Evaluator {
                         l.type_name(),
                         r.type_name()
                     ),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
             BinaryOp::Divide => match (left, right) {
                 (Value::Int(l), Value::Int(r)) => {
@@ -629,7 +1248,7 @@ impl Evaluator {
                         Err(BccError::runtime_error(
                             span.clone(),
                             "Division by zero".to_string(),
-                        ))
+                        ).with_code(error_codes::DIVISION_BY_ZERO))
                     } else {
                         Ok(Value::Double(l as f64 / r as f64))
                     }
@@ -639,7 +1258,7 @@ impl Evaluator {
                         Err(BccError::runtime_error(
                             span.clone(),
                             "Division by zero".to_string(),
-                        ))
+                        ).with_code(error_codes::DIVISION_BY_ZERO))
                     } else {
                         Ok(Value::Double(l / r))
                     }
@@ -649,7 +1268,7 @@ impl Evaluator {
                         Err(BccError::runtime_error(
                             span.clone(),
                             "Division by zero".to_string(),
-                        ))
+                        ).with_code(error_codes::DIVISION_BY_ZERO))
                     } else {
                         Ok(Value::Double(l as f64 / r))
                     }
@@ -659,7 +1278,7 @@ impl Evaluator {
                         Err(BccError::runtime_error(
                             span.clone(),
                             "Division by zero".to_string(),
-                        ))
+                        ).with_code(error_codes::DIVISION_BY_ZERO))
                     } else {
                         Ok(Value::Double(l / r as f64))
                     }
@@ -671,10 +1290,12 @@ impl Evaluator {
                         l.type_name(),
                         r.type_name()
                     ),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
-            BinaryOp::Equal => Ok(Value::Bool(self.is_equal(&left, &right))),
-            BinaryOp::NotEqual => Ok(Value::Bool(!self.is_equal(&left, &right))),
+            BinaryOp::Equal => Ok(Value::Bool(left == right)),
+            BinaryOp::NotEqual => Ok(Value::Bool(left != right)),
+            BinaryOp::Is => Ok(Value::Bool(left.is_identical(&right))),
+            BinaryOp::IsNot => Ok(Value::Bool(!left.is_identical(&right))),
             BinaryOp::Greater => match (left, right) {
                 (Value::Int(l), Value::Int(r)) => Ok(Value::Bool(l > r)),
                 (Value::Double(l), Value::Double(r)) => Ok(Value::Bool(l > r)),
@@ -687,7 +1308,7 @@ impl Evaluator {
                         l.type_name(),
                         r.type_name()
                     ),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
             BinaryOp::GreaterEqual => match (left, right) {
                 (Value::Int(l), Value::Int(r)) => Ok(Value::Bool(l >= r)),
@@ -701,7 +1322,7 @@ impl Evaluator {
                         l.type_name(),
                         r.type_name()
                     ),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
             BinaryOp::Less => match (left, right) {
                 (Value::Int(l), Value::Int(r)) => Ok(Value::Bool(l < r)),
@@ -715,7 +1336,7 @@ impl Evaluator {
                         l.type_name(),
                         r.type_name()
                     ),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
             BinaryOp::LessEqual => match (left, right) {
                 (Value::Int(l), Value::Int(r)) => Ok(Value::Bool(l <= r)),
@@ -729,15 +1350,78 @@ impl Evaluator {
                         l.type_name(),
                         r.type_name()
                     ),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
             BinaryOp::In => {
                 self.evaluate_in_operation(left, right, span)
             },
+            BinaryOp::NotIn => {
+                let contained = self.evaluate_in_operation(left, right, span)?;
+                Ok(Value::Bool(!contained.is_truthy()))
+            },
+            BinaryOp::Union | BinaryOp::Intersection | BinaryOp::SymmetricDifference => match (left, right) {
+                (Value::Set(l), Value::Set(r)) => {
+                    let keep = match operator {
+                        BinaryOp::Union => |in_left, in_right| in_left || in_right,
+                        BinaryOp::Intersection => |in_left, in_right| in_left && in_right,
+                        _ => |in_left: bool, in_right: bool| in_left != in_right,
+                    };
+                    Ok(set_operation(&l.borrow(), &r.borrow(), keep))
+                }
+                (l, r) => Err(BccError::runtime_error_with_help(
+                    span.clone(),
+                    format!(
+                        "Cannot apply '{}' to {} and {}",
+                        match operator {
+                            BinaryOp::Union => "|",
+                            BinaryOp::Intersection => "&",
+                            _ => "^",
+                        },
+                        l.type_name(),
+                        r.type_name()
+                    ),
+                    "'|', '&' and '^' combine two sets. Use set(values) to make a set.".to_string(),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
+            },
         }
     }
 
-    fn evaluate_unary_op(
+    /// Arithmetic and ordering with an int outside the i64 range. Other operators, and
+    /// operands that are not numbers, return `None` and take the ordinary paths.
+    fn big_integer_op(&self, operator: &BinaryOp, left: &Value, right: &Value, span: &Span) -> Option<Result<Value, BccError>> {
+        let (Some(l), Some(r)) = (left.to_bigint(), right.to_bigint()) else {
+            // Mixed with a double, the int takes part as a double, as an i64 would
+            let is_double = |value: &Value| matches!(value, Value::Double(_));
+            if !(is_double(left) || is_double(right)) || matches!(operator, BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::Is | BinaryOp::IsNot | BinaryOp::In | BinaryOp::NotIn) {
+                return None;
+            }
+            let as_double = |value: &Value| match value {
+                Value::BigInt(n) => Value::Double(n.to_f64()),
+                value => value.clone(),
+            };
+            return Some(self.evaluate_binary_op(operator, as_double(left), as_double(right), span));
+        };
+        let result = match operator {
+            BinaryOp::Add => Value::integer(&l + &r),
+            BinaryOp::Subtract => Value::integer(&l - &r),
+            BinaryOp::Multiply => Value::integer(&l * &r),
+            BinaryOp::Divide if r.is_zero() => {
+                return Some(Err(BccError::runtime_error(
+                    span.clone(),
+                    "Division by zero".to_string(),
+                ).with_code(error_codes::DIVISION_BY_ZERO)));
+            }
+            BinaryOp::Divide => Value::Double(l.to_f64() / r.to_f64()),
+            BinaryOp::Less => Value::Bool(l < r),
+            BinaryOp::LessEqual => Value::Bool(l <= r),
+            BinaryOp::Greater => Value::Bool(l > r),
+            BinaryOp::GreaterEqual => Value::Bool(l >= r),
+            _ => return None,
+        };
+        Some(Ok(result))
+    }
+
+    pub(crate) fn evaluate_unary_op(
         &self,
         operator: &UnaryOp,
         operand: Value,
@@ -745,12 +1429,13 @@ impl Evaluator {
     ) -> Result<Value, BccError> {
         match operator {
             UnaryOp::Negate => match operand {
-                Value::Int(n) => Ok(Value::Int(-n)),
+                Value::Int(n) => Ok(promote(n.checked_neg(), || -BigInt::from(n))),
+                Value::BigInt(n) => Ok(Value::integer(-n)),
                 Value::Double(n) => Ok(Value::Double(-n)),
                 _ => Err(BccError::runtime_error(
                     span.clone(),
                     format!("Cannot negate {}", operand.type_name()),
-                )),
+                ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES)),
             },
             UnaryOp::Not => Ok(Value::Bool(!operand.is_truthy())),
         }
@@ -765,24 +1450,17 @@ impl Evaluator {
         match right {
             Value::List(list) => {
                 // Check if left value is in the list
-                for item in &list {
-                    if self.is_equal(&left, item) {
-                        return Ok(Value::Bool(true));
-                    }
-                }
-                Ok(Value::Bool(false))
+                Ok(Value::Bool(list.borrow().contains(&left)))
             },
             Value::Dict(dict) => {
                 // Check if left value is a key in the dictionary
-                match left {
-                    Value::String(key) => {
-                        Ok(Value::Bool(dict.contains_key(&key)))
-                    },
-                    _ => Err(BccError::runtime_error_with_help(
+                match Key::from_value(&left) {
+                    Ok(key) => Ok(Value::Bool(dict.borrow().contains_key(&key))),
+                    Err(unhashable) => Err(BccError::runtime_error_with_help(
                         span.clone(),
-                        format!("Dictionary key lookup requires a string, got {}", left.type_name()),
-                        "Use 'in' with dictionaries like: \"key\" in {\"key\": \"value\"}. Only string keys are supported.".to_string(),
-                    ))
+                        format!("Cannot look up {} in a dictionary", unhashable),
+                        HASHABLE_KEYS_HELP.to_string(),
+                    ).with_code(error_codes::INVALID_DICTIONARY_KEY))
                 }
             },
             Value::String(string) => {
@@ -795,51 +1473,72 @@ impl Evaluator {
                         span.clone(),
                         format!("String containment check requires a string, got {}", left.type_name()),
                         "Use 'in' with strings like: \"sub\" in \"substring\". Both values must be strings.".to_string(),
-                    ))
+                    ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES))
                 }
             },
             Value::Tuple(tuple) => {
                 // Check if left value is in the tuple
-                for item in &tuple {
-                    if self.is_equal(&left, item) {
-                        return Ok(Value::Bool(true));
-                    }
+                Ok(Value::Bool(tuple.contains(&left)))
+            },
+            Value::Set(set) => {
+                match Key::from_value(&left) {
+                    Ok(element) => Ok(Value::Bool(set.borrow().contains_key(&element))),
+                    Err(unhashable) => Err(BccError::runtime_error_with_help(
+                        span.clone(),
+                        format!("Cannot look up {} in a set", unhashable),
+                        HASHABLE_KEYS_HELP.to_string(),
+                    ).with_code(error_codes::INVALID_DICTIONARY_KEY))
                 }
-                Ok(Value::Bool(false))
             },
             _ => Err(BccError::runtime_error_with_help(
                 span.clone(),
                 format!("'in' operator not supported for type {}", right.type_name()),
-                "The 'in' operator works with lists, tuples, dictionaries, and strings. Examples: item in [1, 2, 3], item in (1, 2, 3), \"key\" in {\"key\": \"value\"}, \"sub\" in \"substring\".".to_string(),
-            ))
-        }
-    }
-
-    fn is_equal(&self, left: &Value, right: &Value) -> bool {
-        match (left, right) {
-            (Value::Nil, Value::Nil) => true,
-            (Value::Bool(l), Value::Bool(r)) => l == r,
-            (Value::Int(l), Value::Int(r)) => l == r,
-            (Value::Double(l), Value::Double(r)) => l == r,
-            (Value::Int(l), Value::Double(r)) => (*l as f64) == *r,
-            (Value::Double(l), Value::Int(r)) => *l == (*r as f64),
-            (Value::String(l), Value::String(r)) => l == r,
-            (Value::List(l), Value::List(r)) => {
-                if l.len() != r.len() {
-                    false
-                } else {
-                    l.iter().zip(r.iter()).all(|(a, b)| self.is_equal(a, b))
-                }
-            },
-            (Value::Tuple(l), Value::Tuple(r)) => {
-                if l.len() != r.len() {
-                    false
-                } else {
-                    l.iter().zip(r.iter()).all(|(a, b)| self.is_equal(a, b))
-                }
-            },
-            // Note: We don't implement Dict equality here as it's complex and not essential for basic functionality
-            _ => false,
+                "The 'in' operator works with lists, tuples, dictionaries, sets, and strings. Examples: item in [1, 2, 3], item in (1, 2, 3), \"key\" in {\"key\": \"value\"}, \"sub\" in \"substring\".".to_string(),
+            ).with_code(error_codes::UNSUPPORTED_OPERAND_TYPES))
         }
     }
-}
\ No newline at end of file
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Elements of `left` and then of `right` for which `keep(in_left, in_right)` holds,
+/// each once and in that order
+fn set_operation(left: &OrderedMap<Key, ()>, right: &OrderedMap<Key, ()>, keep: fn(bool, bool) -> bool) -> Value {
+    let result = left
+        .keys()
+        .chain(right.keys())
+        .filter(|element| keep(left.contains_key(element), right.contains_key(element)))
+        .map(|element| (element.clone(), ()))
+        .collect();
+    Value::set(result)
+}
+
+/// Int arithmetic and comparisons that stay within i64, which make up most of a
+/// loop's work; anything else goes through `evaluate_binary_op`
+#[inline]
+fn small_int_op(operator: &BinaryOp, left: &Value, right: &Value) -> Option<Value> {
+    let (&Value::Int(l), &Value::Int(r)) = (left, right) else {
+        return None;
+    };
+    match operator {
+        BinaryOp::Add => l.checked_add(r).map(Value::Int),
+        BinaryOp::Subtract => l.checked_sub(r).map(Value::Int),
+        BinaryOp::Multiply => l.checked_mul(r).map(Value::Int),
+        BinaryOp::Equal => Some(Value::Bool(l == r)),
+        BinaryOp::NotEqual => Some(Value::Bool(l != r)),
+        BinaryOp::Less => Some(Value::Bool(l < r)),
+        BinaryOp::LessEqual => Some(Value::Bool(l <= r)),
+        BinaryOp::Greater => Some(Value::Bool(l > r)),
+        BinaryOp::GreaterEqual => Some(Value::Bool(l >= r)),
+        _ => None,
+    }
+}
+
+/// An i64 result, or the exact result from `big` when the i64 operation overflowed
+fn promote(small: Option<i64>, big: impl FnOnce() -> BigInt) -> Value {
+    small.map_or_else(|| Value::integer(big()), Value::Int)
+}
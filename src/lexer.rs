@@ -1,4 +1,6 @@
+use crate::bigint::BigInt;
 use crate::error::{BccError, Span};
+use crate::error_codes;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,12 +20,19 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Pipe,
+    Ampersand,
+    Caret,
 
     // One or two character tokens
+    Question,
+    QuestionQuestion,
+    QuestionDot,
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
@@ -37,12 +46,15 @@ pub enum TokenType {
 
     // Keywords
     And,
+    Assert,
     Else,
     False,
     For,
     Fun,
     If,
     In,
+    Is,
+    Match,
     Nil,
     Not,
     Or,
@@ -60,6 +72,9 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,  // Simplified: owned string for better maintainability
     pub span: Span,
+    /// A newline separates this token from the one before it. Newlines are not
+    /// tokens, but the parser needs this to keep `x \n if (c) ...` two statements.
+    pub starts_line: bool,
 }
 
 impl Token {
@@ -68,6 +83,7 @@ impl Token {
             token_type,
             lexeme,
             span,
+            starts_line: false,
         }
     }
 }
@@ -75,6 +91,7 @@ impl Token {
 pub struct Lexer {
     source: String,  // Simplified: owned string for better maintainability
     tokens: Vec<Token>,
+    errors: Vec<BccError>,
     start: usize,
     current: usize,
     keywords: HashMap<&'static str, TokenType>,
@@ -84,12 +101,15 @@ impl Lexer {
     pub fn new(source: String) -> Self {
         let mut keywords = HashMap::new();
         keywords.insert("and", TokenType::And);
+        keywords.insert("assert", TokenType::Assert);
         keywords.insert("else", TokenType::Else);
         keywords.insert("false", TokenType::False);
         keywords.insert("for", TokenType::For);
         keywords.insert("fun", TokenType::Fun);
         keywords.insert("if", TokenType::If);
         keywords.insert("in", TokenType::In);
+        keywords.insert("is", TokenType::Is);
+        keywords.insert("match", TokenType::Match);
         keywords.insert("nil", TokenType::Nil);
         keywords.insert("not", TokenType::Not);
         keywords.insert("or", TokenType::Or);
@@ -100,16 +120,25 @@ impl Lexer {
         Self {
             source,
             tokens: Vec::new(),
+            errors: Vec::new(),
             start: 0,
             current: 0,
             keywords,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, BccError> {
+    /// Scan the whole source, collecting every lexical error instead of stopping at the first
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<BccError>> {
         while !self.is_at_end() {
             self.start = self.current;
-            self.scan_token()?;
+            if let Err(error) = self.scan_token() {
+                // The offending character or literal has been consumed, so just keep scanning
+                self.errors.push(error);
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
         }
 
         self.tokens.push(Token::new(
@@ -118,7 +147,7 @@ impl Lexer {
             Span::single(self.current),
         ));
 
-        Ok(self.tokens.clone())
+        Ok(std::mem::take(&mut self.tokens))
     }
 
     fn is_at_end(&self) -> bool {
@@ -137,11 +166,27 @@ impl Lexer {
             ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
             ':' => self.add_token(TokenType::Colon),
+            '.' if self.peek().is_ascii_digit() => self.number()?,
             '.' => self.add_token(TokenType::Dot),
             '-' => self.add_token(TokenType::Minus),
             '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::Semicolon),
             '*' => self.add_token(TokenType::Star),
+            '|' => self.add_token(TokenType::Pipe),
+            '&' => self.add_token(TokenType::Ampersand),
+            '^' => self.add_token(TokenType::Caret),
+            '?' => {
+                let token_type = if self.match_char('?') {
+                    TokenType::QuestionQuestion
+                } else if self.peek() == '.' && !self.peek_next().is_ascii_digit() {
+                    // `c ?.5 : 1` is a conditional with a double, not optional chaining
+                    self.advance();
+                    TokenType::QuestionDot
+                } else {
+                    TokenType::Question
+                };
+                self.add_token(token_type);
+            }
             '!' => {
                 let token_type = if self.match_char('=') {
                     TokenType::BangEqual
@@ -153,6 +198,8 @@ impl Lexer {
             '=' => {
                 let token_type = if self.match_char('=') {
                     TokenType::EqualEqual
+                } else if self.match_char('>') {
+                    TokenType::FatArrow
                 } else {
                     TokenType::Equal
                 };
@@ -188,16 +235,16 @@ impl Lexer {
                 // Ignore whitespace
             }
             '\n' => {
-                // Ignore newlines but track them for line numbers if needed later
+                // Ignore newlines; line numbers come from LineIndex when needed
             }
             '"' => self.string()?,
             c if c.is_ascii_digit() => self.number()?,
             c if c.is_alphabetic() || c == '_' => self.identifier(),
             _ => {
                 return Err(BccError::lex_error(
-                    Span::single(self.current - 1),
+                    Span::new(self.start, self.current),
                     format!("Unexpected character: '{}'", c),
-                ));
+                ).with_code(error_codes::UNEXPECTED_CHARACTER));
             }
         }
 
@@ -205,12 +252,10 @@ impl Lexer {
     }
 
     fn advance(&mut self) -> char {
-        if self.current >= self.source.len() {
-            return '\0';
+        let c = self.peek();
+        if !self.is_at_end() {
+            self.current += c.len_utf8();
         }
-        
-        let c = self.source.chars().nth(self.char_count()).unwrap_or('\0');
-        self.current += c.len_utf8();
         c
     }
 
@@ -223,23 +268,26 @@ impl Lexer {
         }
     }
 
+    // The cursor is a byte offset that always sits on a char boundary. ASCII
+    // bytes are returned directly; only non-ASCII characters need UTF-8 decoding,
+    // so every lookahead is O(1) and lexing is linear in the source length.
     fn peek(&self) -> char {
-        if self.current >= self.source.len() {
-            return '\0';
-        }
-        self.source.chars().nth(self.char_count()).unwrap_or('\0')
+        self.char_at(self.current)
     }
 
     fn peek_next(&self) -> char {
-        let char_pos = self.char_count();
-        if char_pos + 1 >= self.source.chars().count() {
+        if self.is_at_end() {
             return '\0';
         }
-        self.source.chars().nth(char_pos + 1).unwrap_or('\0')
+        self.char_at(self.current + self.peek().len_utf8())
     }
 
-    fn char_count(&self) -> usize {
-        self.source[..self.current].chars().count()
+    fn char_at(&self, offset: usize) -> char {
+        match self.source.as_bytes().get(offset) {
+            Some(&byte) if byte.is_ascii() => byte as char,
+            Some(_) => self.source[offset..].chars().next().unwrap_or('\0'),
+            None => '\0',
+        }
     }
 
     fn string(&mut self) -> Result<(), BccError> {
@@ -251,7 +299,7 @@ impl Lexer {
             return Err(BccError::lex_error(
                 Span::new(self.start, self.current),
                 "Unterminated string".to_string(),
-            ));
+            ).with_code(error_codes::UNTERMINATED_STRING));
         }
 
         // Consume the closing "
@@ -266,49 +314,127 @@ impl Lexer {
         Ok(())
     }
 
+    /// Scan a numeric literal: `0xFF`, `0o755` and `0b1010` ints, decimal ints such
+    /// as `1_000_000`, and doubles such as `3.14`, `.5` and `1e-9`. The token keeps
+    /// the literal as written; `integer_value` and `double_value` convert it.
     fn number(&mut self) -> Result<(), BccError> {
-        while self.peek().is_ascii_digit() {
-            self.advance();
+        let first = self.source.as_bytes()[self.start];
+        if first == b'0' {
+            if let Some((radix, name)) = radix_prefix(self.peek()) {
+                self.advance();
+                return self.radix_number(radix, name);
+            }
         }
 
-        let mut is_double = false;
-        
-        // Look for fractional part
-        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+        let mut is_double = first == b'.';
+        self.decimal_digits();
+        if !is_double && self.peek() == '.' && (self.peek_next().is_ascii_digit() || self.peek_next() == '_') {
             is_double = true;
-            // Consume the "."
             self.advance();
-
-            while self.peek().is_ascii_digit() {
+            self.decimal_digits();
+        }
+        if matches!(self.peek(), 'e' | 'E') {
+            is_double = true;
+            let exponent_start = self.current;
+            self.advance();
+            if matches!(self.peek(), '+' | '-') {
                 self.advance();
             }
+            if !self.peek().is_ascii_digit() && self.peek() != '_' {
+                return Err(BccError::lex_error_with_help(
+                    Span::new(exponent_start, self.current),
+                    "Exponent has no digits".to_string(),
+                    "Write digits after the 'e', as in 1e9 or 2.5e-3.".to_string(),
+                ).with_code(error_codes::INVALID_NUMBER));
+            }
+            self.decimal_digits();
+        }
+        if is_double && self.peek() == '.' && self.peek_next().is_ascii_digit() {
+            let point = self.current;
+            self.advance();
+            self.decimal_digits();
+            return Err(BccError::lex_error(
+                Span::single(point),
+                "Number has more than one decimal point".to_string(),
+            ).with_code(error_codes::INVALID_NUMBER));
         }
 
+        self.check_underscores(self.start, 10)?;
         let number_slice = &self.source[self.start..self.current];
-        
         if is_double {
-            // Validate the double
-            if number_slice.parse::<f64>().is_err() {
-                return Err(BccError::lex_error(
+            if double_value(number_slice).is_none_or(f64::is_infinite) {
+                return Err(BccError::lex_error_with_help(
                     Span::new(self.start, self.current),
-                    format!("Invalid double: {}", number_slice),
-                ));
+                    format!("Double literal {} is too large", number_slice),
+                    "Doubles go up to about 1.8e308; use an int literal for larger exact values.".to_string(),
+                ).with_code(error_codes::INVALID_NUMBER));
             }
             self.add_token_with_content(TokenType::Double, number_slice.to_string());
         } else {
-            // Validate the integer
-            if number_slice.parse::<i64>().is_err() {
-                return Err(BccError::lex_error(
-                    Span::new(self.start, self.current),
-                    format!("Invalid integer: {}", number_slice),
-                ));
-            }
+            // Any run of digits is an int; the parser turns long ones into bignums
             self.add_token_with_content(TokenType::Integer, number_slice.to_string());
         }
-        
+
+        Ok(())
+    }
+
+    /// The digits of a `0x`, `0o` or `0b` literal, after its prefix
+    fn radix_number(&mut self, radix: u32, name: &str) -> Result<(), BccError> {
+        // Take every character that could belong to the literal, so a bad digit is
+        // reported here rather than starting an identifier
+        let digits_start = self.current;
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+        let prefix = &self.source[self.start..digits_start];
+        if digits_start == self.current {
+            return Err(BccError::lex_error_with_help(
+                Span::new(self.start, self.current),
+                format!("{} literal has no digits", name),
+                format!("Write at least one digit after '{}', as in {}.", prefix, match radix {
+                    16 => "0xFF",
+                    8 => "0o755",
+                    _ => "0b1010",
+                }),
+            ).with_code(error_codes::INVALID_NUMBER));
+        }
+        if let Some((offset, digit)) = self.source[digits_start..self.current]
+            .char_indices()
+            .find(|&(_, c)| c != '_' && !c.is_digit(radix))
+        {
+            return Err(BccError::lex_error(
+                Span::single(digits_start + offset),
+                format!("Invalid digit '{}' in {} literal", digit, name.to_lowercase()),
+            ).with_code(error_codes::INVALID_NUMBER));
+        }
+        self.check_underscores(digits_start, radix)?;
+        self.add_token(TokenType::Integer);
         Ok(())
     }
 
+    fn decimal_digits(&mut self) {
+        while self.peek().is_ascii_digit() || self.peek() == '_' {
+            self.advance();
+        }
+    }
+
+    /// Underscores may only separate two digits, as in `1_000`
+    fn check_underscores(&self, digits_start: usize, radix: u32) -> Result<(), BccError> {
+        let digits = &self.source.as_bytes()[digits_start..self.current];
+        let is_digit = |position: Option<&u8>| position.is_some_and(|&byte| (byte as char).is_digit(radix));
+        let misplaced = digits.iter().enumerate().position(|(i, &byte)| {
+            byte == b'_' && !(i > 0 && is_digit(digits.get(i - 1)) && is_digit(digits.get(i + 1)))
+        });
+        match misplaced {
+            Some(offset) => Err(BccError::lex_error_with_help(
+                Span::single(digits_start + offset),
+                "Misplaced '_' in numeric literal".to_string(),
+                "'_' may only separate digits, as in 1_000_000 or 0xFF_FF.".to_string(),
+            ).with_code(error_codes::INVALID_NUMBER)),
+            None => Ok(()),
+        }
+    }
+
     fn identifier(&mut self) {
         while self.peek().is_alphanumeric() || self.peek() == '_' {
             self.advance();
@@ -330,10 +456,33 @@ impl Lexer {
     }
 
     fn add_token_with_content(&mut self, token_type: TokenType, lexeme: String) {
-        self.tokens.push(Token::new(
-            token_type,
-            lexeme,
-            Span::new(self.start, self.current),
-        ));
+        let previous_end = self.tokens.last().map_or(0, |token| token.span.end);
+        let mut token = Token::new(token_type, lexeme, Span::new(self.start, self.current));
+        token.starts_line = self.source[previous_end..self.start].contains('\n');
+        self.tokens.push(token);
     }
-}
\ No newline at end of file
+}
+
+fn radix_prefix(c: char) -> Option<(u32, &'static str)> {
+    match c {
+        'x' | 'X' => Some((16, "Hexadecimal")),
+        'o' | 'O' => Some((8, "Octal")),
+        'b' | 'B' => Some((2, "Binary")),
+        _ => None,
+    }
+}
+
+/// The value of an Integer token's lexeme, such as `1_000` or `0xFF`
+pub fn integer_value(lexeme: &str) -> Option<BigInt> {
+    let digits = lexeme.replace('_', "");
+    let mut chars = digits.chars();
+    match (chars.next(), chars.next().and_then(radix_prefix)) {
+        (Some('0'), Some((radix, _))) => BigInt::from_str_radix(&digits[2..], radix),
+        _ => BigInt::from_str_radix(&digits, 10),
+    }
+}
+
+/// The value of a Double token's lexeme, such as `1_000.5` or `1e-9`
+pub fn double_value(lexeme: &str) -> Option<f64> {
+    lexeme.replace('_', "").parse().ok()
+}
@@ -5,22 +5,38 @@
 
 // Public modules
 pub mod ast;
+pub mod bigint;
+pub mod bytecode;
+pub mod compiled;
+pub mod compiler;
+pub mod disassembler;
 pub mod error;
+pub mod error_codes;
 pub mod evaluator;
 pub mod lexer;
+pub mod optimizer;
+pub mod ordered_map;
 pub mod parser;
 pub mod repl;
+pub mod resolver;
 pub mod runner;
+pub mod suggest;
 pub mod value;
+pub mod vm;
 
 // Re-export commonly used items
 pub use ast::{Expr, Stmt, Program};
-pub use error::{BccError, Span};
+pub use error::{BccError, ErrorFormat, LineCol, LineIndex, Span, StackFrame};
+pub use compiled::CompiledScript;
+pub use compiler::Compiler;
 pub use evaluator::Evaluator;
 pub use lexer::{Lexer, Token, TokenType};
+pub use optimizer::Optimizer;
 pub use parser::Parser;
+pub use resolver::{Resolution, Resolver};
 pub use value::Value;
+pub use vm::Vm;
 
 // Re-export main functions
 pub use repl::start as start_repl;
-pub use runner::run;
\ No newline at end of file
+pub use runner::{run, run_with_options, Backend, RunOptions};
\ No newline at end of file
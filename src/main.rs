@@ -1,15 +1,9 @@
-mod lexer;
-mod parser;
-mod evaluator;
-mod error;
-mod repl;
-mod runner;
-mod value;
-mod ast;
-
+use bcc::error::ErrorFormat;
+use bcc::runner::{Backend, RunOptions};
+use bcc::{error_codes, repl, runner, CompiledScript};
 use clap::{Arg, Command};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 fn main() {
     let matches = Command::new("bcc")
@@ -27,16 +21,131 @@ fn main() {
                 .help("Start in interactive REPL mode")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("explain")
+                .long("explain")
+                .help("Print a detailed explanation of an error code, e.g. E0102")
+                .value_name("CODE"),
+        )
+        .arg(
+            Arg::new("error-format")
+                .long("error-format")
+                .help("How to print diagnostics")
+                .value_name("FORMAT")
+                .value_parser(["human", "json"])
+                .default_value("human"),
+        )
+        .arg(
+            Arg::new("optimize")
+                .short('O')
+                .long("optimize")
+                .help("Fold constant expressions and remove dead branches before running")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("backend")
+                .long("backend")
+                .help("Execution engine: the tree-walking evaluator or the bytecode VM")
+                .value_name("BACKEND")
+                .value_parser(["tree", "vm"])
+                .default_value("tree"),
+        )
+        .arg(
+            Arg::new("disassemble")
+                .long("disassemble")
+                .help("Print the compiled bytecode of FILE instead of running it")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("compile")
+                .about("Compile a script to bytecode that `bcc FILE.bcco` runs without parsing")
+                .arg(
+                    Arg::new("file")
+                        .help("The script file to compile")
+                        .value_name("FILE")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Where to write the compiled script [default: FILE with a .bcco extension]")
+                        .value_name("OUTPUT"),
+                ),
+        )
         .get_matches();
 
-    if let Some(file_path) = matches.get_one::<String>("file") {
-        run_file(file_path);
+    let error_format = match matches.get_one::<String>("error-format").map(String::as_str) {
+        Some("json") => ErrorFormat::Json,
+        _ => ErrorFormat::Human,
+    };
+    let optimize = matches.get_flag("optimize");
+    let backend = match matches.get_one::<String>("backend").map(String::as_str) {
+        Some("vm") => Backend::Vm,
+        _ => Backend::Tree,
+    };
+    let options = RunOptions { error_format, optimize, backend };
+
+    if let Some(code) = matches.get_one::<String>("explain") {
+        explain(code);
+    } else if let Some(("compile", compile_matches)) = matches.subcommand() {
+        let file_path = compile_matches.get_one::<String>("file").unwrap();
+        let output = match compile_matches.get_one::<String>("output") {
+            Some(output) => PathBuf::from(output),
+            None => Path::new(file_path).with_extension("bcco"),
+        };
+        compile_file(file_path, &output, &options);
+    } else if let Some(file_path) = matches.get_one::<String>("file") {
+        if matches.get_flag("disassemble") {
+            disassemble_file(file_path, &options);
+        } else {
+            run_file(file_path, &options);
+        }
     } else if matches.get_flag("interactive") || matches.get_one::<String>("file").is_none() {
         repl::start();
     }
 }
 
-fn run_file(path: &str) {
+fn run_file(path: &str, options: &RunOptions) {
+    match read_script(path) {
+        Script::Source(source) => runner::run_with_options(&source, Some(path), options),
+        Script::Compiled(script) => runner::run_compiled(&script, options),
+    }
+}
+
+fn compile_file(path: &str, output: &Path, options: &RunOptions) {
+    let Script::Source(source) = read_script(path) else {
+        eprintln!("Error: '{}' is already compiled", path);
+        std::process::exit(1);
+    };
+    let Some(script) = runner::compile(&source, path, options) else {
+        std::process::exit(1);
+    };
+    if let Err(e) = fs::write(output, script.to_bytes()) {
+        eprintln!("Error writing file '{}': {}", output.display(), e);
+        std::process::exit(1);
+    }
+}
+
+fn disassemble_file(path: &str, options: &RunOptions) {
+    let listing = match read_script(path) {
+        Script::Source(source) => runner::disassemble(&source, path, options),
+        Script::Compiled(script) => Some(bcc::disassembler::disassemble(&script.chunk, &script.source, &script.filename)),
+    };
+    match listing {
+        Some(listing) => print!("{}", listing),
+        None => std::process::exit(1),
+    }
+}
+
+/// A script file: source text, or bytecode written by `bcc compile`
+enum Script {
+    Source(String),
+    Compiled(Box<CompiledScript>),
+}
+
+fn read_script(path: &str) -> Script {
     let path = Path::new(path);
     
     if !path.exists() {
@@ -44,13 +153,42 @@ fn run_file(path: &str) {
         std::process::exit(1);
     }
 
-    match fs::read_to_string(path) {
-        Ok(source) => {
-            runner::run(&source, Some(path.to_str().unwrap()));
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Error reading file '{}': {}", path.display(), e);
+            std::process::exit(1);
         }
+    };
+    // Compiled scripts are recognized by their header, whatever the file is called
+    if CompiledScript::is_compiled(&bytes) {
+        return match CompiledScript::from_bytes(&bytes) {
+            Ok(script) => Script::Compiled(Box::new(script)),
+            Err(e) => {
+                eprintln!("Error loading '{}': {}", path.display(), e);
+                std::process::exit(1);
+            }
+        };
+    }
+    match String::from_utf8(bytes) {
+        Ok(source) => Script::Source(source),
         Err(e) => {
             eprintln!("Error reading file '{}': {}", path.display(), e);
             std::process::exit(1);
         }
     }
-}
\ No newline at end of file
+}
+
+fn explain(code: &str) {
+    match error_codes::lookup(code) {
+        Some(info) => {
+            println!("{}: {}", info.code, info.title);
+            println!();
+            println!("{}", info.explanation);
+        }
+        None => {
+            eprintln!("Error: '{}' is not a known error code", code);
+            std::process::exit(1);
+        }
+    }
+}
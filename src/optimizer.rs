@@ -0,0 +1,288 @@
+use crate::ast::{Expr, KeywordArg, LogicalOp, MatchArm, Program, Stmt};
+use crate::evaluator::Evaluator;
+use crate::value::Value;
+
+/// Optional AST pass that folds constant expressions and drops branches that can never run.
+///
+/// Folding goes through the evaluator's own operator implementations, so a folded
+/// program computes exactly what the original would. Operations that fail, such as
+/// division by zero or mixing types, are left in place to fail at runtime with the
+/// usual diagnostic.
+pub struct Optimizer {
+    evaluator: Evaluator,
+}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Self {
+            evaluator: Evaluator::new(),
+        }
+    }
+
+    pub fn optimize(&self, program: Program) -> Program {
+        Program {
+            statements: self.optimize_statements(program.statements),
+        }
+    }
+
+    fn optimize_statements(&self, statements: Vec<Stmt>) -> Vec<Stmt> {
+        statements
+            .into_iter()
+            .filter_map(|statement| self.optimize_statement(statement))
+            .collect()
+    }
+
+    /// Returns None when the statement can never have an effect
+    fn optimize_statement(&self, stmt: Stmt) -> Option<Stmt> {
+        match stmt {
+            Stmt::Expression { expr, span } => Some(Stmt::Expression {
+                expr: self.optimize_expression(expr),
+                span,
+            }),
+            Stmt::Block { statements, span } => Some(Stmt::Block {
+                statements: self.optimize_statements(statements),
+                span,
+            }),
+            Stmt::If { condition, then_branch, else_branch, span } => {
+                let condition = self.optimize_expression(condition);
+                if let Expr::Literal { value, .. } = &condition {
+                    return if value.is_truthy() {
+                        self.optimize_statement(*then_branch)
+                    } else {
+                        else_branch.and_then(|branch| self.optimize_statement(*branch))
+                    };
+                }
+                Some(Stmt::If {
+                    condition,
+                    then_branch: Box::new(self.optimize_nested(*then_branch)),
+                    else_branch: else_branch.map(|branch| Box::new(self.optimize_nested(*branch))),
+                    span,
+                })
+            }
+            Stmt::While { condition, body, span } => {
+                let condition = self.optimize_expression(condition);
+                if matches!(&condition, Expr::Literal { value, .. } if !value.is_truthy()) {
+                    return None;
+                }
+                Some(Stmt::While {
+                    condition,
+                    body: Box::new(self.optimize_nested(*body)),
+                    span,
+                })
+            }
+            Stmt::For { initializer, condition, increment, body, span } => {
+                let initializer = initializer.and_then(|init| self.optimize_statement(*init));
+                let condition = condition.map(|cond| self.optimize_expression(cond));
+                if matches!(&condition, Some(Expr::Literal { value, .. }) if !value.is_truthy()) {
+                    // Only the initializer ever runs
+                    return initializer;
+                }
+                Some(Stmt::For {
+                    initializer: initializer.map(Box::new),
+                    condition,
+                    increment: increment.map(|inc| self.optimize_expression(inc)),
+                    body: Box::new(self.optimize_nested(*body)),
+                    span,
+                })
+            }
+            Stmt::Assert { condition, message, span } => {
+                // A failing comparison labels both operand values, so keep the comparison itself
+                let condition = match condition {
+                    Expr::Binary { left, operator, right, span } if operator.is_comparison() => Expr::Binary {
+                        left: Box::new(self.optimize_expression(*left)),
+                        operator,
+                        right: Box::new(self.optimize_expression(*right)),
+                        span,
+                    },
+                    condition => self.optimize_expression(condition),
+                };
+                Some(Stmt::Assert {
+                    condition,
+                    message: message.map(|message| self.optimize_expression(message)),
+                    span,
+                })
+            }
+        }
+    }
+
+    /// Branches and loop bodies must stay statements, so a pruned one becomes an empty block
+    fn optimize_nested(&self, stmt: Stmt) -> Stmt {
+        let span = stmt.span().clone();
+        self.optimize_statement(stmt).unwrap_or(Stmt::Block {
+            statements: Vec::new(),
+            span,
+        })
+    }
+
+    fn optimize_expression(&self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Binary { left, operator, right, span } => {
+                let left = self.optimize_expression(*left);
+                let right = self.optimize_expression(*right);
+                if let (Expr::Literal { value: l, .. }, Expr::Literal { value: r, .. }) = (&left, &right) {
+                    if let Ok(value) = self.evaluator.evaluate_binary_op(&operator, l.clone(), r.clone(), &span) {
+                        return Expr::Literal { value, span };
+                    }
+                }
+                Expr::Binary {
+                    left: Box::new(left),
+                    operator,
+                    right: Box::new(right),
+                    span,
+                }
+            }
+            Expr::Comparison { operands, operators, span } => Expr::Comparison {
+                operands: operands.into_iter().map(|operand| self.optimize_expression(operand)).collect(),
+                operators,
+                span,
+            },
+            Expr::Unary { operator, operand, span } => {
+                let operand = self.optimize_expression(*operand);
+                if let Expr::Literal { value, .. } = &operand {
+                    if let Ok(value) = self.evaluator.evaluate_unary_op(&operator, value.clone(), &span) {
+                        return Expr::Literal { value, span };
+                    }
+                }
+                Expr::Unary {
+                    operator,
+                    operand: Box::new(operand),
+                    span,
+                }
+            }
+            Expr::Logical { left, operator, right, span } => {
+                let left = self.optimize_expression(*left);
+                let right = self.optimize_expression(*right);
+                if let Expr::Literal { value, .. } = &left {
+                    // Same short-circuit rules as the evaluator: the deciding operand is the result
+                    let left_decides = match operator {
+                        LogicalOp::Or => value.is_truthy(),
+                        LogicalOp::And => !value.is_truthy(),
+                        LogicalOp::Coalesce => !matches!(value, Value::Nil),
+                    };
+                    return if left_decides {
+                        Expr::Literal { value: value.clone(), span }
+                    } else {
+                        // Grouping evaluates to its inner value and keeps the original span for labels
+                        Expr::Grouping {
+                            expr: Box::new(right),
+                            span,
+                        }
+                    };
+                }
+                Expr::Logical {
+                    left: Box::new(left),
+                    operator,
+                    right: Box::new(right),
+                    span,
+                }
+            }
+            Expr::Conditional { condition, then_branch, else_branch, span } => {
+                let condition = self.optimize_expression(*condition);
+                let then_branch = self.optimize_expression(*then_branch);
+                let else_branch = self.optimize_expression(*else_branch);
+                if let Expr::Literal { value, .. } = &condition {
+                    let chosen = if value.is_truthy() { then_branch } else { else_branch };
+                    return Expr::Grouping {
+                        expr: Box::new(chosen),
+                        span,
+                    };
+                }
+                Expr::Conditional {
+                    condition: Box::new(condition),
+                    then_branch: Box::new(then_branch),
+                    else_branch: Box::new(else_branch),
+                    span,
+                }
+            }
+            Expr::Grouping { expr, span } => match self.optimize_expression(*expr) {
+                // Keep the parenthesized span so labels on the folded value still cover the parentheses
+                Expr::Literal { value, .. } => Expr::Literal { value, span },
+                expr => Expr::Grouping {
+                    expr: Box::new(expr),
+                    span,
+                },
+            },
+            Expr::Assign { name, value, span } => Expr::Assign {
+                name,
+                value: Box::new(self.optimize_expression(*value)),
+                span,
+            },
+            Expr::MultiAssign { targets, value, span } => Expr::MultiAssign {
+                targets,
+                value: Box::new(self.optimize_expression(*value)),
+                span,
+            },
+            Expr::Call { callee, args, span } => Expr::Call {
+                callee: Box::new(self.optimize_expression(*callee)),
+                args: self.optimize_expressions(args),
+                span,
+            },
+            Expr::CallWithKwargs { callee, positional_args, keyword_args, span } => Expr::CallWithKwargs {
+                callee: Box::new(self.optimize_expression(*callee)),
+                positional_args: self.optimize_expressions(positional_args),
+                keyword_args: keyword_args
+                    .into_iter()
+                    .map(|kwarg| KeywordArg {
+                        name: kwarg.name,
+                        value: self.optimize_expression(kwarg.value),
+                        span: kwarg.span,
+                    })
+                    .collect(),
+                span,
+            },
+            Expr::MultiReturn { values, span } => Expr::MultiReturn {
+                values: self.optimize_expressions(values),
+                span,
+            },
+            Expr::List { elements, span } => Expr::List {
+                elements: self.optimize_expressions(elements),
+                span,
+            },
+            Expr::Tuple { elements, span } => Expr::Tuple {
+                elements: self.optimize_expressions(elements),
+                span,
+            },
+            Expr::Set { elements, span } => Expr::Set {
+                elements: self.optimize_expressions(elements),
+                span,
+            },
+            Expr::Dict { pairs, span } => Expr::Dict {
+                pairs: pairs
+                    .into_iter()
+                    .map(|(key, value)| (self.optimize_expression(key), self.optimize_expression(value)))
+                    .collect(),
+                span,
+            },
+            Expr::PropertyAccess { object, property, optional, span } => Expr::PropertyAccess {
+                object: Box::new(self.optimize_expression(*object)),
+                property,
+                optional,
+                span,
+            },
+            Expr::Match { subject, arms, span } => Expr::Match {
+                subject: Box::new(self.optimize_expression(*subject)),
+                arms: arms
+                    .into_iter()
+                    .map(|arm| MatchArm {
+                        pattern: arm.pattern,
+                        guard: arm.guard.map(|guard| self.optimize_expression(guard)),
+                        body: self.optimize_expression(arm.body),
+                    })
+                    .collect(),
+                span,
+            },
+            Expr::Literal { .. } | Expr::Variable { .. } => expr,
+        }
+    }
+
+    fn optimize_expressions(&self, exprs: Vec<Expr>) -> Vec<Expr> {
+        exprs.into_iter().map(|expr| self.optimize_expression(expr)).collect()
+    }
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
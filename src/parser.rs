@@ -1,43 +1,131 @@
-use crate::ast::{BinaryOp, Expr, LogicalOp, Program, Stmt, UnaryOp};
+use crate::ast::{AssignTarget, BinaryOp, Expr, LogicalOp, MatchArm, Pattern, Program, Stmt, UnaryOp};
 use crate::error::{BccError, Span};
-use crate::lexer::{Token, TokenType};
+use crate::error_codes;
+use crate::lexer::{self, Token, TokenType};
 use crate::value::Value;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<BccError>,
+}
+
+/// Logical operators, loosest first
+const LOGICAL_LEVELS: [(TokenType, LogicalOp); 3] = [
+    (TokenType::QuestionQuestion, LogicalOp::Coalesce),
+    (TokenType::Or, LogicalOp::Or),
+    (TokenType::And, LogicalOp::And),
+];
+
+/// Operators that bind tighter than comparisons, loosest first, with the help shown
+/// when one lacks its right operand. Set operators sit below arithmetic as in
+/// Python: `|` loosest, then `^`, then `&`.
+const ARITHMETIC_LEVELS: [(&[(TokenType, BinaryOp)], &str); 5] = [
+    (&[(TokenType::Pipe, BinaryOp::Union)], SET_OPERATOR_HELP),
+    (&[(TokenType::Caret, BinaryOp::SymmetricDifference)], SET_OPERATOR_HELP),
+    (&[(TokenType::Ampersand, BinaryOp::Intersection)], SET_OPERATOR_HELP),
+    (
+        &[(TokenType::Minus, BinaryOp::Subtract), (TokenType::Plus, BinaryOp::Add)],
+        "Arithmetic operators like '+' and '-' require expressions on both sides.",
+    ),
+    (
+        &[(TokenType::Slash, BinaryOp::Divide), (TokenType::Star, BinaryOp::Multiply)],
+        "Multiplication and division operators require expressions on both sides.",
+    ),
+];
+
+const SET_OPERATOR_HELP: &str = "Set operators like '|', '&' and '^' require expressions on both sides.";
+
+/// What a '{' at the start of a statement opens
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BraceKind {
+    Block,
+    Dictionary,
+    Set,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Program, BccError> {
+    /// Parse the whole program, recovering after each syntax error so that
+    /// every error in the file is reported in one run
+    pub fn parse(&mut self) -> Result<Program, Vec<BccError>> {
         let mut statements = Vec::new();
 
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            let before = self.current;
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize();
+                    // A stray closing brace at the top level stops synchronization without being consumed
+                    if self.current == before {
+                        self.advance();
+                    }
+                }
+            }
         }
 
-        Ok(Program { statements })
+        if self.errors.is_empty() {
+            Ok(Program { statements })
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 
     fn declaration(&mut self) -> Result<Stmt, BccError> {
         self.statement()
     }
 
+    /// Panic-mode recovery: skip tokens until a likely statement boundary,
+    /// i.e. just after a ';', or before a '}' or a statement keyword
+    fn synchronize(&mut self) {
+        // Braces opened while skipping are skipped as a whole, so only a '}'
+        // belonging to an enclosing block stops recovery
+        let mut brace_depth = 0;
+
+        while !self.is_at_end() {
+            if brace_depth == 0 && self.current > 0 && self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::LeftBrace => brace_depth += 1,
+                TokenType::RightBrace if brace_depth > 0 => brace_depth -= 1,
+                _ if brace_depth > 0 => {}
+                // An `if` inside a line is more likely a conditional expression
+                TokenType::If if self.peek().starts_line => return,
+                TokenType::RightBrace
+                | TokenType::While
+                | TokenType::For
+                | TokenType::Assert
+                | TokenType::Fun
+                | TokenType::Return => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+
     fn statement(&mut self) -> Result<Stmt, BccError> {
         if self.check(&TokenType::LeftBrace) {
             // Look ahead to determine if this is a dictionary expression or a block statement
-            if self.is_dictionary_literal() {
-                // Parse as expression statement containing a dictionary
+            if self.brace_kind() != BraceKind::Block {
+                // Parse as expression statement containing a dictionary or set
                 self.expression_statement()
             } else {
                 // Parse as block statement
-                self.advance(); // consume the '{'
+                let open_brace = self.advance().span.clone(); // consume the '{'
                 Ok(Stmt::Block {
-                    statements: self.block()?,
+                    statements: self.block(open_brace)?,
                     span: self.previous().span.clone(),
                 })
             }
@@ -47,40 +135,53 @@ impl Parser {
             self.while_statement()
         } else if self.match_types(&[TokenType::For]) {
             self.for_statement()
+        } else if self.match_types(&[TokenType::Assert]) {
+            self.assert_statement()
         } else {
             self.expression_statement()
         }
     }
 
-    fn block(&mut self) -> Result<Vec<Stmt>, BccError> {
+    fn block(&mut self, open_brace: Span) -> Result<Vec<Stmt>, BccError> {
         let mut statements = Vec::new();
 
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-            statements.push(self.declaration()?);
+            let before = self.current;
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) => {
+                    // Recover inside the block so its closing '}' is still matched
+                    self.errors.push(error);
+                    self.synchronize();
+                    if self.current == before && !self.check(&TokenType::RightBrace) {
+                        self.advance();
+                    }
+                }
+            }
         }
 
         self.consume_with_help(
             TokenType::RightBrace, 
             "Expected '}' after block",
             "Block statements must be closed with '}' after the opening '{'.".to_string()
-        )?;
+        ).map_err(|error| error.with_label(open_brace, "block opened here".to_string()))?;
         Ok(statements)
     }
 
     fn if_statement(&mut self) -> Result<Stmt, BccError> {
         let start_span = self.previous().span.start;
         
-        self.consume_with_help(
+        let open_paren = self.consume_with_help(
             TokenType::LeftParen, 
             "Expected '(' after 'if'",
             "If statements require parentheses around the condition: if (condition) { ... }".to_string()
-        )?;
+        )?.span.clone();
         let condition = self.expression()?;
         self.consume_with_help(
             TokenType::RightParen, 
             "Expected ')' after if condition",
             "If conditions must be enclosed in parentheses: if (condition) { ... }".to_string()
-        )?;
+        ).map_err(|error| error.with_label(open_paren, "condition opened here".to_string()))?;
 
         let then_branch = Box::new(self.statement()?);
         let else_branch = if self.match_types(&[TokenType::Else]) {
@@ -157,10 +258,45 @@ impl Parser {
         })
     }
 
+    fn assert_statement(&mut self) -> Result<Stmt, BccError> {
+        let start_span = self.previous().span.start;
+
+        if self.is_at_end() {
+            return Err(BccError::parse_error_with_help(
+                self.previous().span.clone(),
+                "Expected condition after 'assert'".to_string(),
+                "Assertions take a condition and an optional message: assert x == 1, \"x should be 1\"".to_string(),
+            ).with_code(error_codes::EXPECTED_EXPRESSION));
+        }
+
+        let condition = self.expression()?;
+        let message = if self.match_types(&[TokenType::Comma]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        // Make semicolon optional
+        if self.check(&TokenType::Semicolon) {
+            self.advance();
+        }
+
+        let end_span = self.previous().span.end;
+
+        Ok(Stmt::Assert {
+            condition,
+            message,
+            span: Span::new(start_span, end_span),
+        })
+    }
+
     fn expression_statement(&mut self) -> Result<Stmt, BccError> {
         let start_span = self.peek().span.start;
-        let expr = self.expression()?;
-        
+        let mut expr = self.expression()?;
+        if matches!(expr, Expr::Assign { .. } | Expr::MultiAssign { .. }) {
+            expr = self.tuple_assignment_value(expr)?;
+        }
+
         // Make semicolon optional
         if self.check(&TokenType::Semicolon) {
             self.advance();
@@ -178,286 +314,406 @@ impl Parser {
         self.assignment()
     }
 
+    /// `x = 1, 2` and `a, b = b, a` assign a tuple. Only an assignment statement takes
+    /// one, so commas in calls, collections and match arms still separate those.
+    /// The innermost value of a chain like `a = b = 1, 2` becomes the tuple.
+    fn tuple_assignment_value(&mut self, expr: Expr) -> Result<Expr, BccError> {
+        if !self.check(&TokenType::Comma) {
+            return Ok(expr);
+        }
+        match expr {
+            Expr::Assign { name, value, span } => {
+                let value = self.tuple_assignment_value(*value)?;
+                let span = Span::new(span.start, value.span().end);
+                Ok(Expr::Assign { name, value: Box::new(value), span })
+            }
+            Expr::MultiAssign { targets, value, span } => {
+                let value = self.tuple_assignment_value(*value)?;
+                let span = Span::new(span.start, value.span().end);
+                Ok(Expr::MultiAssign { targets, value: Box::new(value), span })
+            }
+            value => {
+                let start = value.span().start;
+                let mut elements = vec![value];
+                while self.match_types(&[TokenType::Comma]) {
+                    elements.push(self.conditional()?);
+                }
+                Ok(Expr::Tuple { elements, span: Span::new(start, self.previous().span.end) })
+            }
+        }
+    }
+
     fn assignment(&mut self) -> Result<Expr, BccError> {
-        // First, try to parse comma-separated variables (for multi-assignment)
-        // We need to look ahead to see if this is multi-assignment
+        if let Some(multi_assign) = self.multi_assignment()? {
+            return Ok(multi_assign);
+        }
+
+        let expr = self.conditional()?;
+        if self.match_types(&[TokenType::Equal]) {
+            return self.finish_assignment(expr);
+        }
+        Ok(expr)
+    }
+
+    /// `targets = value` when the next tokens are comma-separated targets followed
+    /// by '='; otherwise nothing is consumed
+    fn multi_assignment(&mut self) -> Result<Option<Expr>, BccError> {
         let checkpoint = self.current;
-        
-        // Try to parse as multi-assignment pattern: var, var, ... = expr
+        let start_span = self.peek().span.start;
+
         if let Ok(targets) = self.try_parse_assignment_targets() {
             if self.check(&TokenType::Equal) {
-                // This is indeed multi-assignment
+                Self::check_starred_targets(&targets)?;
                 self.advance(); // consume '='
                 let value = self.assignment()?;
                 let end_span = self.previous().span.end;
-                let start_span = targets.first().unwrap().span().start;
-                
-                return Ok(Expr::MultiAssign {
+
+                return Ok(Some(Expr::MultiAssign {
                     targets,
                     value: Box::new(value),
                     span: Span::new(start_span, end_span),
-                });
+                }));
             }
         }
-        
-        // Backtrack and parse as regular expression
+
+        // Backtrack and let the caller parse a regular expression
         self.current = checkpoint;
-        let expr = self.or()?;
+        Ok(None)
+    }
 
-        if self.match_types(&[TokenType::Equal]) {
-            let value = self.assignment()?;
+    /// The value of `target = value`, after the '='
+    fn finish_assignment(&mut self, target: Expr) -> Result<Expr, BccError> {
+        let value = self.assignment()?;
 
-            // Check if it's a single variable assignment
-            if let Expr::Variable { name, span } = expr {
-                return Ok(Expr::Assign {
-                    name,
-                    value: Box::new(value),
-                    span: Span::new(span.start, self.previous().span.end),
-                });
-            }
-
-            return Err(BccError::parse_error_with_help(
-                expr.span().clone(),
-                "Invalid assignment target".to_string(),
-                "Assignment target must be a variable or comma-separated variables. Examples: 'x = 10' or 'a, b = expr'".to_string(),
-            ));
+        // Check if it's a single variable assignment
+        if let Expr::Variable { name, span } = target {
+            return Ok(Expr::Assign {
+                name,
+                value: Box::new(value),
+                span: Span::new(span.start, self.previous().span.end),
+            });
         }
 
-        Ok(expr)
+        Err(BccError::parse_error_with_help(
+            target.span().clone(),
+            "Invalid assignment target".to_string(),
+            "Assignment target must be a variable or comma-separated variables. Examples: 'x = 10' or 'a, b = expr'".to_string(),
+        ).with_code(error_codes::INVALID_ASSIGNMENT_TARGET))
     }
-    
-    fn try_parse_assignment_targets(&mut self) -> Result<Vec<crate::ast::AssignTarget>, BccError> {
-        let mut targets = Vec::new();
-        
-        // Parse first target
-        if self.check(&TokenType::Identifier) {
-            let token = self.advance();
-            if token.lexeme == "_" {
-                targets.push(crate::ast::AssignTarget::Ignore { span: token.span.clone() });
-            } else {
-                targets.push(crate::ast::AssignTarget::Variable { 
-                    name: token.lexeme.clone(), 
-                    span: token.span.clone() 
-                });
-            }
-        } else {
-            return Err(BccError::parse_error_with_help(
-                self.peek().span.clone(),
-                "Expected variable name".to_string(),
-                "Assignment targets must be variable names or underscores".to_string(),
-            ));
-        }
-        
-        // Parse additional comma-separated targets
-        while self.check(&TokenType::Comma) {
-            self.advance(); // consume comma
-            
-            if self.check(&TokenType::Identifier) {
-                let token = self.advance();
-                if token.lexeme == "_" {
-                    targets.push(crate::ast::AssignTarget::Ignore { span: token.span.clone() });
-                } else {
-                    targets.push(crate::ast::AssignTarget::Variable { 
-                        name: token.lexeme.clone(), 
-                        span: token.span.clone() 
-                    });
-                }
-            } else {
-                return Err(BccError::parse_error_with_help(
-                    self.peek().span.clone(),
-                    "Expected variable name after comma".to_string(),
-                    "Multi-assignment targets must be variable names or underscores".to_string(),
-                ));
-            }
+
+    /// Targets of a multi-assignment: `a, b`, `(a, (b, c))` or `first, *rest`. Fails
+    /// for anything else, including a lone name, which is a plain assignment.
+    fn try_parse_assignment_targets(&mut self) -> Result<Vec<AssignTarget>, BccError> {
+        let mut targets = vec![self.assignment_target()?];
+        while self.match_types(&[TokenType::Comma]) {
+            targets.push(self.assignment_target()?);
         }
-        
-        // Only return success if we have multiple targets (multi-assignment)
+
+        // Only return success if we have multiple targets (multi-assignment);
+        // `(a, b) = t` is the same as `a, b = t`
         if targets.len() > 1 {
             Ok(targets)
+        } else if let Some(AssignTarget::Nested { targets, .. }) = targets.pop() {
+            Ok(targets)
         } else {
             Err(BccError::parse_error_with_help(
                 self.peek().span.clone(),
                 "Single target doesn't need comma".to_string(),
                 "Use regular assignment for single variables".to_string(),
-            ))
+            ).with_code(error_codes::INVALID_ASSIGNMENT_TARGET))
         }
     }
 
-    fn or(&mut self) -> Result<Expr, BccError> {
-        let mut expr = self.and()?;
+    /// Whether a `*` or `(` that starts a line begins a multi-assignment such as
+    /// `*init, last = xs` instead of continuing the expression on the line before
+    fn starts_multi_assignment(&mut self) -> bool {
+        if !self.peek().starts_line || !matches!(self.peek().token_type, TokenType::Star | TokenType::LeftParen) {
+            return false;
+        }
+        let checkpoint = self.current;
+        let found = self.try_parse_assignment_targets().is_ok() && self.check(&TokenType::Equal);
+        self.current = checkpoint;
+        found
+    }
 
-        while self.match_types(&[TokenType::Or]) {
-            let start = expr.span().start;
-            let right = self.and()?;
-            let end = right.span().end;
-            
-            expr = Expr::Logical {
-                left: Box::new(expr),
-                operator: LogicalOp::Or,
-                right: Box::new(right),
-                span: Span::new(start, end),
-            };
+    /// One target: a name, `_`, `*name`, or a parenthesized or bracketed target list
+    fn assignment_target(&mut self) -> Result<AssignTarget, BccError> {
+        let token = self.peek().clone();
+        match token.token_type {
+            TokenType::Identifier => {
+                self.advance();
+                Ok(if token.lexeme == "_" {
+                    AssignTarget::Ignore { span: token.span }
+                } else {
+                    AssignTarget::Variable { name: token.lexeme, span: token.span }
+                })
+            }
+            TokenType::Star => {
+                self.advance();
+                let target = match self.assignment_target()? {
+                    target @ (AssignTarget::Variable { .. } | AssignTarget::Ignore { .. }) => target,
+                    target => {
+                        return Err(BccError::parse_error_with_help(
+                            target.span().clone(),
+                            "Expected a variable name after '*'".to_string(),
+                            "A starred target collects values into one list: first, *rest = items".to_string(),
+                        ).with_code(error_codes::INVALID_ASSIGNMENT_TARGET))
+                    }
+                };
+                let span = Span::new(token.span.start, target.span().end);
+                Ok(AssignTarget::Starred { target: Box::new(target), span })
+            }
+            TokenType::LeftParen | TokenType::LeftBracket => {
+                self.advance();
+                let close = if token.token_type == TokenType::LeftParen {
+                    TokenType::RightParen
+                } else {
+                    TokenType::RightBracket
+                };
+                let mut targets = Vec::new();
+                let mut is_tuple = close == TokenType::RightBracket;
+                while !self.check(&close) {
+                    targets.push(self.assignment_target()?);
+                    if !self.match_types(&[TokenType::Comma]) {
+                        break;
+                    }
+                    is_tuple = true;
+                }
+                let end = self.consume(close, "Expected the target list to be closed here.")?.span.end;
+                // `(a)` is just `a`, as `(x)` is just `x` in an expression
+                if !is_tuple && targets.len() == 1 {
+                    return Ok(targets.pop().unwrap());
+                }
+                Ok(AssignTarget::Nested { targets, span: Span::new(token.span.start, end) })
+            }
+            _ => Err(BccError::parse_error_with_help(
+                token.span,
+                "Expected variable name".to_string(),
+                "Assignment targets must be variable names or underscores".to_string(),
+            ).with_code(error_codes::INVALID_ASSIGNMENT_TARGET)),
         }
+    }
 
-        Ok(expr)
+    /// Each list of targets can hold one starred target, which takes what the others leave
+    fn check_starred_targets(targets: &[AssignTarget]) -> Result<(), BccError> {
+        let starred: Vec<&Span> = targets
+            .iter()
+            .filter(|target| matches!(target, AssignTarget::Starred { .. }))
+            .map(AssignTarget::span)
+            .collect();
+        if let [first, second, ..] = starred[..] {
+            return Err(BccError::parse_error_with_help(
+                second.clone(),
+                "Only one starred target is allowed in a target list".to_string(),
+                "With two, there is no way to tell how many values each should take.".to_string(),
+            ).with_code(error_codes::INVALID_ASSIGNMENT_TARGET)
+            .with_label(first.clone(), "first starred target here".to_string()));
+        }
+        for target in targets {
+            if let AssignTarget::Nested { targets, .. } = target {
+                Self::check_starred_targets(targets)?;
+            }
+        }
+        Ok(())
     }
 
-    fn and(&mut self) -> Result<Expr, BccError> {
-        let mut expr = self.equality()?;
+    /// `a if c else b` and `c ? a : b`, both right-associative. A postfix `if` that
+    /// starts a new line begins an if statement instead.
+    fn conditional(&mut self) -> Result<Expr, BccError> {
+        let expr = self.logical(0)?;
 
-        while self.match_types(&[TokenType::And]) {
-            let start = expr.span().start;
-            let right = self.equality()?;
-            let end = right.span().end;
-            
-            expr = Expr::Logical {
-                left: Box::new(expr),
-                operator: LogicalOp::And,
-                right: Box::new(right),
-                span: Span::new(start, end),
-            };
+        if self.check(&TokenType::If) && !self.peek().starts_line {
+            self.advance();
+            return self.postfix_conditional(expr);
+        }
+        if self.match_types(&[TokenType::Question]) {
+            return self.ternary_conditional(expr);
         }
-
         Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<Expr, BccError> {
-        let mut expr = self.comparison()?;
+    // The two forms of conditional are parsed outside `conditional()` so that every
+    // nesting level of an expression doesn't pay for their stack space.
+
+    /// The rest of `then_branch if condition else else_branch`, after the `if`
+    fn postfix_conditional(&mut self, then_branch: Expr) -> Result<Expr, BccError> {
+        let condition = self.logical(0)?;
+        self.consume(TokenType::Else, "Expected 'else' after the condition of a conditional expression.")?;
+        let else_branch = self.conditional()?;
+        let span = Span::new(then_branch.span().start, else_branch.span().end);
+        Ok(Expr::Conditional {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+            span,
+        })
+    }
 
-        while self.match_types(&[TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator_token = self.previous().clone();
-            let operator = match operator_token.token_type {
-                TokenType::BangEqual => BinaryOp::NotEqual,
-                TokenType::EqualEqual => BinaryOp::Equal,
-                _ => unreachable!(),
-            };
-            
-            let start = expr.span().start;
-            let right = self.comparison().map_err(|_| {
-                BccError::parse_error_with_help(
-                    operator_token.span.clone(),
-                    format!("Expected expression after '{}'", operator_token.lexeme),
-                    "Equality operators like '==' and '!=' require expressions on both sides.".to_string(),
-                )
-            })?;
-            let end = right.span().end;
-            
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                span: Span::new(start, end),
-            };
-        }
+    /// The rest of `condition ? then_branch : else_branch`, after the `?`
+    fn ternary_conditional(&mut self, condition: Expr) -> Result<Expr, BccError> {
+        let then_branch = self.conditional()?;
+        self.consume(TokenType::Colon, "Expected ':' between the branches of a conditional expression.")?;
+        let else_branch = self.conditional()?;
+        let span = Span::new(condition.span().start, else_branch.span().end);
+        Ok(Expr::Conditional {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+            span,
+        })
+    }
 
+    /// `??`, `or` and `and`, loosest first. Operators of one level associate to the
+    /// left; the right operand of each is parsed at the next level.
+    fn logical(&mut self, min_level: usize) -> Result<Expr, BccError> {
+        let expr = self.comparison()?;
+        if self.logical_operator(min_level).is_some() {
+            return self.finish_logical(expr, min_level);
+        }
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, BccError> {
-        let mut expr = self.term()?;
-
-        while self.match_types(&[
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-            TokenType::In,
-        ]) {
-            let operator_token = self.previous().clone();
-            let operator = match operator_token.token_type {
-                TokenType::Greater => BinaryOp::Greater,
-                TokenType::GreaterEqual => BinaryOp::GreaterEqual,
-                TokenType::Less => BinaryOp::Less,
-                TokenType::LessEqual => BinaryOp::LessEqual,
-                TokenType::In => BinaryOp::In,
-                _ => unreachable!(),
-            };
-            
-            let start = expr.span().start;
-            let right = self.term().map_err(|_| {
-                BccError::parse_error_with_help(
-                    operator_token.span.clone(),
-                    format!("Expected expression after '{}'", operator_token.lexeme),
-                    "Comparison operators like '>', '<', '>=', '<=' and 'in' require expressions on both sides.".to_string(),
-                )
-            })?;
-            let end = right.span().end;
-            
-            expr = Expr::Binary {
+    fn finish_logical(&mut self, mut expr: Expr, min_level: usize) -> Result<Expr, BccError> {
+        while let Some(level) = self.logical_operator(min_level) {
+            let (_, operator) = LOGICAL_LEVELS[level];
+            self.advance();
+            let right = self.logical(level + 1)?;
+            let span = Span::new(expr.span().start, right.span().end);
+            expr = Expr::Logical {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
-                span: Span::new(start, end),
+                span,
             };
         }
 
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, BccError> {
-        let mut expr = self.factor()?;
+    /// Equality, identity, ordering and membership operators share one precedence
+    /// level and chain as in Python: `a < b <= c` means `a < b and b <= c` with `b`
+    /// evaluated once. A lone comparison stays an `Expr::Binary`.
+    fn comparison(&mut self) -> Result<Expr, BccError> {
+        let first = self.arithmetic(0)?;
+        match self.comparison_operator() {
+            Some(operator) => self.finish_comparison(first, operator),
+            None => Ok(first),
+        }
+    }
 
-        while self.match_types(&[TokenType::Minus, TokenType::Plus]) {
-            let operator_token = self.previous().clone();
-            let operator = match operator_token.token_type {
-                TokenType::Minus => BinaryOp::Subtract,
-                TokenType::Plus => BinaryOp::Add,
-                _ => unreachable!(),
-            };
-            
-            let start = expr.span().start;
-            let right = self.factor().map_err(|_| {
+    /// The rest of a comparison after its first operand and operator
+    fn finish_comparison(&mut self, first: Expr, operator: (BinaryOp, Span, String)) -> Result<Expr, BccError> {
+        let mut operands = vec![first];
+        let mut operators = Vec::new();
+        let mut next = Some(operator);
+
+        while let Some((operator, operator_span, operator_text)) = next {
+            let right = self.arithmetic(0).map_err(|_| {
                 BccError::parse_error_with_help(
-                    operator_token.span.clone(),
-                    format!("Expected expression after '{}'", operator_token.lexeme),
-                    "Arithmetic operators like '+' and '-' require expressions on both sides.".to_string(),
-                )
+                    operator_span,
+                    format!("Expected expression after '{}'", operator_text),
+                    "Comparison operators like '==', '<', 'is' and 'in' require expressions on both sides.".to_string(),
+                ).with_code(error_codes::MISSING_OPERAND)
             })?;
-            let end = right.span().end;
-            
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-                span: Span::new(start, end),
-            };
+            operands.push(right);
+            operators.push(operator);
+            next = self.comparison_operator();
         }
 
-        Ok(expr)
+        let span = Span::new(operands[0].span().start, operands[operands.len() - 1].span().end);
+        Ok(match operators.len() {
+            1 => {
+                let right = operands.pop().expect("a comparison has two operands");
+                let left = operands.pop().expect("a comparison has two operands");
+                Expr::Binary {
+                    left: Box::new(left),
+                    operator: operators[0],
+                    right: Box::new(right),
+                    span,
+                }
+            }
+            _ => Expr::Comparison { operands, operators, span },
+        })
     }
 
-    fn factor(&mut self) -> Result<Expr, BccError> {
-        let mut expr = self.unary()?;
+    /// Consume a comparison operator, including the two-word `is not` and `not in`,
+    /// returning it with its span and source text
+    fn comparison_operator(&mut self) -> Option<(BinaryOp, Span, String)> {
+        if self.check(&TokenType::Not) && self.tokens[self.current + 1].token_type == TokenType::In {
+            let start = self.advance().span.start;
+            let end = self.advance().span.end;
+            return Some((BinaryOp::NotIn, Span::new(start, end), "not in".to_string()));
+        }
+        let operator = match self.peek().token_type {
+            TokenType::EqualEqual => BinaryOp::Equal,
+            TokenType::BangEqual => BinaryOp::NotEqual,
+            TokenType::Is => BinaryOp::Is,
+            TokenType::Less => BinaryOp::Less,
+            TokenType::LessEqual => BinaryOp::LessEqual,
+            TokenType::Greater => BinaryOp::Greater,
+            TokenType::GreaterEqual => BinaryOp::GreaterEqual,
+            TokenType::In => BinaryOp::In,
+            _ => return None,
+        };
+        let token = self.advance().clone();
+        if operator == BinaryOp::Is && self.match_types(&[TokenType::Not]) {
+            let end = self.previous().span.end;
+            return Some((BinaryOp::IsNot, Span::new(token.span.start, end), "is not".to_string()));
+        }
+        Some((operator, token.span, token.lexeme))
+    }
 
-        while self.match_types(&[TokenType::Slash, TokenType::Star]) {
-            let operator_token = self.previous().clone();
-            let operator = match operator_token.token_type {
-                TokenType::Slash => BinaryOp::Divide,
-                TokenType::Star => BinaryOp::Multiply,
-                _ => unreachable!(),
-            };
-            
-            let start = expr.span().start;
-            let right = self.unary().map_err(|_| {
+    /// The level of the next token, if it is a logical operator binding at least as
+    /// tightly as `min_level`
+    fn logical_operator(&self, min_level: usize) -> Option<usize> {
+        (min_level..LOGICAL_LEVELS.len()).find(|&level| self.check(&LOGICAL_LEVELS[level].0))
+    }
+
+    /// Set and arithmetic operators, loosest first, climbing the levels of
+    /// `ARITHMETIC_LEVELS` in one frame per operator instead of one per level.
+    /// A missing right operand is reported at the outermost operator.
+    fn arithmetic(&mut self, min_level: usize) -> Result<Expr, BccError> {
+        let expr = self.unary()?;
+        if self.arithmetic_operator(min_level).is_some() {
+            return self.finish_arithmetic(expr, min_level);
+        }
+        Ok(expr)
+    }
+
+    fn finish_arithmetic(&mut self, mut expr: Expr, min_level: usize) -> Result<Expr, BccError> {
+        while let Some((level, operator)) = self.arithmetic_operator(min_level) {
+            let operator_token = self.advance().clone();
+            let right = self.arithmetic(level + 1).map_err(|_| {
                 BccError::parse_error_with_help(
                     operator_token.span.clone(),
                     format!("Expected expression after '{}'", operator_token.lexeme),
-                    "Multiplication and division operators require expressions on both sides.".to_string(),
-                )
+                    ARITHMETIC_LEVELS[level].1.to_string(),
+                ).with_code(error_codes::MISSING_OPERAND)
             })?;
-            let end = right.span().end;
-            
+            let span = Span::new(expr.span().start, right.span().end);
             expr = Expr::Binary {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
-                span: Span::new(start, end),
+                span,
             };
         }
 
         Ok(expr)
     }
 
+    /// The level and operator of the next token, if it is an arithmetic or set
+    /// operator binding at least as tightly as `min_level`
+    fn arithmetic_operator(&mut self, min_level: usize) -> Option<(usize, BinaryOp)> {
+        if self.check(&TokenType::Star) && self.starts_multi_assignment() {
+            return None;
+        }
+        let token_type = &self.peek().token_type;
+        ARITHMETIC_LEVELS.iter().enumerate().skip(min_level).find_map(|(level, (operators, _))| {
+            operators.iter().find(|(operator_token, _)| operator_token == token_type).map(|&(_, operator)| (level, operator))
+        })
+    }
+
     fn unary(&mut self) -> Result<Expr, BccError> {
         if self.match_types(&[TokenType::Bang, TokenType::Not, TokenType::Minus]) {
             let operator = match self.previous().token_type {
@@ -481,33 +737,20 @@ impl Parser {
     }
 
     fn call(&mut self) -> Result<Expr, BccError> {
-        let mut expr = self.primary()?;
+        let expr = self.primary()?;
+        if self.check(&TokenType::LeftParen) || self.check(&TokenType::Dot) || self.check(&TokenType::QuestionDot) {
+            return self.call_suffixes(expr);
+        }
+        Ok(expr)
+    }
 
+    /// Calls and property accesses applied to `expr`, as in `a.b(c).d`
+    fn call_suffixes(&mut self, mut expr: Expr) -> Result<Expr, BccError> {
         loop {
-            if self.match_types(&[TokenType::LeftParen]) {
+            if !self.starts_multi_assignment() && self.match_types(&[TokenType::LeftParen]) {
                 expr = self.finish_call(expr)?;
-            } else if self.match_types(&[TokenType::Dot]) {
-                let property_token = self.consume(
-                    TokenType::Identifier,
-                    "Expected property name after '.'.",
-                )?;
-                
-                let property_name = if let TokenType::Identifier = &property_token.token_type {
-                    property_token.lexeme.clone()
-                } else {
-                    return Err(BccError::parse_error(
-                        property_token.span.clone(),
-                        "Expected property name after '.'".to_string(),
-                    ));
-                };
-
-                let start_span = expr.span().start;
-                let end_span = property_token.span.end;
-                expr = Expr::PropertyAccess {
-                    object: Box::new(expr),
-                    property: property_name,
-                    span: Span::new(start_span, end_span),
-                };
+            } else if self.match_types(&[TokenType::Dot, TokenType::QuestionDot]) {
+                expr = self.property_access(expr)?;
             } else {
                 break;
             }
@@ -516,7 +759,35 @@ impl Parser {
         Ok(expr)
     }
 
+    /// The property of `object.property` or `object?.property`, after the dot
+    fn property_access(&mut self, object: Expr) -> Result<Expr, BccError> {
+        let optional = self.previous().token_type == TokenType::QuestionDot;
+        let property_token = self.consume(
+            TokenType::Identifier,
+            "Expected property name after '.'.",
+        )?;
+
+        let property_name = if let TokenType::Identifier = &property_token.token_type {
+            property_token.lexeme.clone()
+        } else {
+            return Err(BccError::parse_error(
+                property_token.span.clone(),
+                "Expected property name after '.'".to_string(),
+            ).with_code(error_codes::EXPECTED_TOKEN));
+        };
+
+        let start_span = object.span().start;
+        let end_span = property_token.span.end;
+        Ok(Expr::PropertyAccess {
+            object: Box::new(object),
+            property: property_name,
+            optional,
+            span: Span::new(start_span, end_span),
+        })
+    }
+
     fn finish_call(&mut self, callee: Expr) -> Result<Expr, BccError> {
+        let open_paren = self.previous().span.clone();
         let mut positional_args = Vec::new();
         let mut keyword_args = Vec::new();
         let start_span = callee.span().start;
@@ -527,10 +798,10 @@ impl Parser {
                 // Check if we've hit EOF or other invalid tokens before trying to parse expression
                 if self.is_at_end() {
                     return Err(BccError::parse_error_with_help(
-                        Span::single(self.current),
+                        Span::single(self.previous().span.end),
                         "Unexpected end of input in function call".to_string(),
                         "Function calls must be closed with ')' after the arguments. Example: func(arg1, arg2)".to_string(),
-                    ));
+                    ).with_code(error_codes::UNCLOSED_DELIMITER));
                 }
                 
                 // Check for common error cases that could cause infinite recursion
@@ -539,7 +810,7 @@ impl Parser {
                         self.peek().span.clone(),
                         "Expected ')' to close function call".to_string(),
                         "Function calls must be closed with ')' after the arguments. Example: func(arg1, arg2)".to_string(),
-                    ));
+                    ).with_code(error_codes::UNCLOSED_DELIMITER));
                 }
                 
                 // Check for keyword argument: identifier=expression
@@ -555,7 +826,7 @@ impl Parser {
                                 self.peek().span.clone(),
                                 "Invalid expression in keyword argument".to_string(),
                                 "Keyword arguments must have valid expressions. Example: func(name=value)".to_string(),
-                            )
+                            ).with_code(error_codes::INVALID_ARGUMENT_SYNTAX)
                         })?;
                         
                         keyword_args.push(crate::ast::KeywordArg {
@@ -572,7 +843,7 @@ impl Parser {
                                 self.peek().span.clone(),
                                 "Positional argument after keyword argument".to_string(),
                                 "All positional arguments must come before keyword arguments. Example: func(pos1, pos2, kw1=val1, kw2=val2)".to_string(),
-                            ));
+                            ).with_code(error_codes::POSITIONAL_AFTER_KEYWORD).with_label(keyword_args[0].span.clone(), "first keyword argument here".to_string()));
                         }
                         
                         positional_args.push(self.expression().map_err(|_e| {
@@ -580,7 +851,7 @@ impl Parser {
                                 self.peek().span.clone(),
                                 "Invalid expression in function call arguments".to_string(),
                                 "Function arguments must be valid expressions separated by commas. Example: func(arg1, arg2)".to_string(),
-                            )
+                            ).with_code(error_codes::INVALID_ARGUMENT_SYNTAX)
                         })?);
                     }
                 } else {
@@ -590,7 +861,7 @@ impl Parser {
                             self.peek().span.clone(),
                             "Positional argument after keyword argument".to_string(),
                             "All positional arguments must come before keyword arguments. Example: func(pos1, pos2, kw1=val1, kw2=val2)".to_string(),
-                        ));
+                        ).with_code(error_codes::POSITIONAL_AFTER_KEYWORD).with_label(keyword_args[0].span.clone(), "first keyword argument here".to_string()));
                     }
                     
                     positional_args.push(self.expression().map_err(|_e| {
@@ -598,7 +869,7 @@ impl Parser {
                             self.peek().span.clone(),
                             "Invalid expression in function call arguments".to_string(),
                             "Function arguments must be valid expressions separated by commas. Example: func(arg1, arg2)".to_string(),
-                        )
+                        ).with_code(error_codes::INVALID_ARGUMENT_SYNTAX)
                     })?);
                 }
                 
@@ -609,10 +880,10 @@ impl Parser {
                 // After consuming a comma, check if we immediately hit EOF or invalid tokens
                 if self.is_at_end() {
                     return Err(BccError::parse_error_with_help(
-                        Span::single(self.current),
+                        Span::single(self.previous().span.end),
                         "Unexpected end of input after ',' in function call".to_string(),
                         "Function calls must be closed with ')' after the arguments. You have a trailing comma.".to_string(),
-                    ));
+                    ).with_code(error_codes::UNCLOSED_DELIMITER));
                 }
             }
         }
@@ -621,7 +892,7 @@ impl Parser {
             TokenType::RightParen, 
             "Expected ')' after arguments",
             "Function calls must be closed with ')' after the arguments. Example: func(arg1, arg2)".to_string()
-        )?;
+        ).map_err(|error| error.with_label(open_paren, "call opened here".to_string()))?;
 
         // Return appropriate call type based on whether we found keyword arguments
         if keyword_args.is_empty() {
@@ -641,17 +912,62 @@ impl Parser {
     }
 
     fn primary(&mut self) -> Result<Expr, BccError> {
-        // Check for EOF before advancing to prevent infinite recursion
+        // Check for EOF before advancing to prevent infinite recursion, and leave
+        // stray closers and the keyword of the next statement in place so error
+        // recovery can still match them
+        if self.is_at_end()
+            || matches!(self.peek().token_type, TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket)
+            || Self::begins_statement(self.peek())
+        {
+            return Err(self.missing_expression_error());
+        }
+
+        let token = self.advance().clone();
+
+        match token.token_type {
+            TokenType::LeftParen => self.grouping_or_tuple(token.span),
+            TokenType::LeftBracket => self.list_literal(token.span),
+            TokenType::LeftBrace => self.brace_literal(token.span),
+            TokenType::Match => self.match_expression(token.span),
+            _ => Self::token_expression(token),
+        }
+    }
+
+    /// The error for an expression that is missing before the current token
+    fn missing_expression_error(&self) -> BccError {
+        let token = self.peek();
         if self.is_at_end() {
-            return Err(BccError::parse_error_with_help(
-                self.peek().span.clone(),
+            return BccError::parse_error_with_help(
+                token.span.clone(),
                 "Unexpected end of input".to_string(),
                 "Expected an expression here. Check for unmatched parentheses, brackets, or incomplete statements.".to_string(),
-            ));
+            ).with_code(error_codes::EXPECTED_EXPRESSION);
         }
-        
-        let token = self.advance().clone();
+        let help_msg = match token.token_type {
+            TokenType::RightParen => "Found ')' without matching '('. Check for unbalanced parentheses.",
+            TokenType::RightBrace => "Found '}' without matching '{'. Check for unbalanced braces.",
+            TokenType::RightBracket => "Found ']' without matching '['. Check for unbalanced brackets.",
+            _ => "A new statement starts here, so the previous line is incomplete.",
+        };
+        BccError::parse_error_with_help(
+            token.span.clone(),
+            format!("Expected expression, found '{}'", token.lexeme),
+            help_msg.to_string(),
+        ).with_code(error_codes::EXPECTED_EXPRESSION)
+    }
 
+    /// A statement keyword at the start of a line. It cannot continue an expression
+    /// from the line before, so a missing operand is reported in front of it.
+    fn begins_statement(token: &Token) -> bool {
+        token.starts_line
+            && matches!(
+                token.token_type,
+                TokenType::If | TokenType::While | TokenType::For | TokenType::Assert | TokenType::Fun | TokenType::Return
+            )
+    }
+
+    /// A literal or variable made of a single token
+    fn token_expression(token: Token) -> Result<Expr, BccError> {
         match token.token_type {
             TokenType::False => Ok(Expr::Literal {
                 value: Value::Bool(false),
@@ -666,17 +982,17 @@ impl Parser {
                 span: token.span,
             }),
             TokenType::Integer => {
-                let value = token.lexeme.parse::<i64>().map_err(|_| {
-                    BccError::parse_error(token.span.clone(), "Invalid integer".to_string())
+                let value = lexer::integer_value(&token.lexeme).ok_or_else(|| {
+                    BccError::parse_error(token.span.clone(), "Invalid integer".to_string()).with_code(error_codes::INVALID_NUMBER)
                 })?;
                 Ok(Expr::Literal {
-                    value: Value::Int(value),
+                    value: Value::integer(value),
                     span: token.span,
                 })
             }
             TokenType::Double => {
-                let value = token.lexeme.parse::<f64>().map_err(|_| {
-                    BccError::parse_error(token.span.clone(), "Invalid double".to_string())
+                let value = lexer::double_value(&token.lexeme).ok_or_else(|| {
+                    BccError::parse_error(token.span.clone(), "Invalid double".to_string()).with_code(error_codes::INVALID_NUMBER)
                 })?;
                 Ok(Expr::Literal {
                     value: Value::Double(value),
@@ -691,88 +1007,89 @@ impl Parser {
                 name: token.lexeme, // Simplified: lexeme is already an owned String
                 span: token.span,
             }),
-            TokenType::LeftParen => {
-                let start_span = token.span.clone();
-                
-                // Check if we immediately hit EOF - this prevents infinite recursion
-                if self.is_at_end() {
-                    return Err(BccError::parse_error_with_help(
-                        start_span,
-                        "Expected expression after '('".to_string(),
-                        "Opening parentheses '(' must contain a valid expression. Example: (x + 1)".to_string(),
-                    ));
-                }
-                
-                // Check for empty parentheses () which is a syntax error in this language
-                if self.check(&TokenType::RightParen) {
-                    return Err(BccError::parse_error_with_help(
-                        Span::new(start_span.start, self.peek().span.end),
-                        "Empty parentheses are not allowed".to_string(),
-                        "Parentheses must contain an expression. Use 'nil' for a null value: (nil)".to_string(),
-                    ));
-                }
-                
-                // Parse potential tuple: (a, b, c) or grouping: (a)
-                let mut elements = Vec::new();
-                elements.push(self.expression()?);
-                
-                // Check if this is a tuple (has commas) or just a grouping
-                let mut is_tuple = false;
-                while self.match_types(&[TokenType::Comma]) {
-                    is_tuple = true;
-                    
-                    // Allow trailing commas in tuples: (a, b,)
-                    if self.check(&TokenType::RightParen) {
-                        break;
-                    }
-                    
-                    elements.push(self.expression()?);
-                }
-                
-                let end_token = self.consume_with_help(
-                    TokenType::RightParen, 
-                    "Expected ')' after expression",
-                    "Every opening parenthesis '(' must have a matching closing parenthesis ')'.".to_string()
-                )?;
-                
-                if is_tuple {
-                    // This is a tuple: (a, b) or (a,)
-                    Ok(Expr::Tuple {
-                        elements,
-                        span: Span::new(start_span.start, end_token.span.end),
-                    })
-                } else {
-                    // This is just a grouping: (a)
-                    Ok(Expr::Grouping {
-                        expr: Box::new(elements.into_iter().next().unwrap()),
-                        span: Span::new(start_span.start, end_token.span.end),
-                    })
-                }
-            }
-            TokenType::LeftBracket => {
-                self.list_literal(token.span)
-            }
-            TokenType::LeftBrace => {
-                self.dict_literal(token.span)
-            }
             _ => {
                 let help_msg = match token.token_type {
-                    TokenType::RightParen => "Found ')' without matching '('. Check for unbalanced parentheses.",
-                    TokenType::RightBrace => "Found '}' without matching '{'. Check for unbalanced braces.",
-                    TokenType::RightBracket => "Found ']' without matching '['. Check for unbalanced brackets.",
                     TokenType::Eof => "Reached end of input while expecting an expression.",
                     _ => "Expected a literal value, variable, or parenthesized expression here."
                 };
-                
+
                 Err(BccError::parse_error_with_help(
                     token.span,
                     format!("Expected expression, found '{}'", token.lexeme),
                     help_msg.to_string(),
-                ))
+                ).with_code(error_codes::EXPECTED_EXPRESSION))
             },
         }
     }
 
+    fn grouping_or_tuple(&mut self, start_span: Span) -> Result<Expr, BccError> {
+        if let Some(error) = self.empty_parentheses_error(&start_span) {
+            return Err(error);
+        }
+
+        let first = self.expression()?;
+        if self.check(&TokenType::Comma) {
+            return self.tuple(start_span, first);
+        }
+        let end = self.closing_paren(&start_span)?;
+        Ok(Expr::Grouping {
+            expr: Box::new(first),
+            span: Span::new(start_span.start, end),
+        })
+    }
+
+    /// `(` followed by the end of input or by `)` has no expression inside
+    fn empty_parentheses_error(&self, start_span: &Span) -> Option<BccError> {
+        // Check if we immediately hit EOF - this prevents infinite recursion
+        if self.is_at_end() {
+            return Some(BccError::parse_error_with_help(
+                start_span.clone(),
+                "Expected expression after '('".to_string(),
+                "Opening parentheses '(' must contain a valid expression. Example: (x + 1)".to_string(),
+            ).with_code(error_codes::EXPECTED_EXPRESSION));
+        }
+
+        // Check for empty parentheses () which is a syntax error in this language
+        if self.check(&TokenType::RightParen) {
+            return Some(BccError::parse_error_with_help(
+                Span::new(start_span.start, self.peek().span.end),
+                "Empty parentheses are not allowed".to_string(),
+                "Parentheses must contain an expression. Use 'nil' for a null value: (nil)".to_string(),
+            ).with_code(error_codes::EMPTY_PARENTHESES));
+        }
+        None
+    }
+
+    /// The rest of a tuple `(a, b, c)` or `(a,)` after its first element
+    fn tuple(&mut self, start_span: Span, first: Expr) -> Result<Expr, BccError> {
+        let mut elements = vec![first];
+        while self.match_types(&[TokenType::Comma]) {
+            // Allow trailing commas in tuples: (a, b,)
+            if self.check(&TokenType::RightParen) {
+                break;
+            }
+
+            elements.push(self.expression()?);
+        }
+
+        let end = self.closing_paren(&start_span)?;
+        Ok(Expr::Tuple {
+            elements,
+            span: Span::new(start_span.start, end),
+        })
+    }
+
+    /// Consume the ')' of a grouping or tuple, returning where it ends
+    fn closing_paren(&mut self, start_span: &Span) -> Result<usize, BccError> {
+        self.consume_with_help(
+            TokenType::RightParen,
+            "Expected ')' after expression",
+            "Every opening parenthesis '(' must have a matching closing parenthesis ')'.".to_string()
+        )
+        .map(|token| token.span.end)
+        .map_err(|error| error.with_label(start_span.clone(), "opening '(' here".to_string()))
+    }
+
     fn list_literal(&mut self, start_span: Span) -> Result<Expr, BccError> {
         let mut elements = Vec::new();
 
@@ -789,19 +1106,31 @@ impl Parser {
             TokenType::RightBracket, 
             "Expected ']' after list elements",
             "List literals must be closed with ']' after the opening '['. Example: [1, 2, 3]".to_string()
-        )?;
+        ).map_err(|error| error.with_label(start_span.clone(), "list opened here".to_string()))?;
         Ok(Expr::List {
             elements,
             span: Span::new(start_span.start, end_token.span.end),
         })
     }
 
-    fn dict_literal(&mut self, start_span: Span) -> Result<Expr, BccError> {
+    /// A dictionary or set literal; the first entry decides which. `{}` is an empty dictionary.
+    fn brace_literal(&mut self, start_span: Span) -> Result<Expr, BccError> {
+        if self.check(&TokenType::RightBrace) {
+            return self.dict_literal(start_span, None);
+        }
+        let first = self.expression()?;
+        if self.check(&TokenType::Colon) {
+            self.dict_literal(start_span, Some(first))
+        } else {
+            self.set_literal(start_span, first)
+        }
+    }
+
+    fn dict_literal(&mut self, start_span: Span, first_key: Option<Expr>) -> Result<Expr, BccError> {
         let mut pairs = Vec::new();
 
-        if !self.check(&TokenType::RightBrace) {
+        if let Some(mut key) = first_key {
             loop {
-                let key = self.expression()?;
                 self.consume_with_help(
                     TokenType::Colon, 
                     "Expected ':' after dictionary key",
@@ -813,6 +1142,7 @@ impl Parser {
                 if !self.match_types(&[TokenType::Comma]) {
                     break;
                 }
+                key = self.expression()?;
             }
         }
 
@@ -820,13 +1150,171 @@ impl Parser {
             TokenType::RightBrace, 
             "Expected '}' after dictionary pairs",
             "Dictionary literals must be closed with '}' after the opening '{'. Example: {\"key\": \"value\"}".to_string()
-        )?;
+        ).map_err(|error| error.with_label(start_span.clone(), "dictionary opened here".to_string()))?;
         Ok(Expr::Dict {
             pairs,
             span: Span::new(start_span.start, end_token.span.end),
         })
     }
 
+    fn set_literal(&mut self, start_span: Span, first: Expr) -> Result<Expr, BccError> {
+        let mut elements = vec![first];
+
+        while self.match_types(&[TokenType::Comma]) {
+            elements.push(self.expression()?);
+        }
+
+        let end_token = self.consume_with_help(
+            TokenType::RightBrace,
+            "Expected '}' after set elements",
+            "Set literals separate elements with commas and end with '}'. Example: {1, 2, 3}".to_string()
+        ).map_err(|error| error.with_label(start_span.clone(), "set opened here".to_string()))?;
+        Ok(Expr::Set {
+            elements,
+            span: Span::new(start_span.start, end_token.span.end),
+        })
+    }
+
+    /// `match subject { pattern if guard => body, ... }`; arms are separated by commas
+    fn match_expression(&mut self, start_span: Span) -> Result<Expr, BccError> {
+        let subject = self.expression()?;
+        let open_brace = self.consume_with_help(
+            TokenType::LeftBrace,
+            "Expected '{' after the match subject",
+            "Match arms go in braces: match value { 1 => \"one\", _ => \"other\" }".to_string(),
+        )?.span.clone();
+
+        let mut arms = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let pattern = self.pattern()?;
+            let guard = if self.match_types(&[TokenType::If]) {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.consume_with_help(
+                TokenType::FatArrow,
+                "Expected '=>' after the match pattern",
+                "Each arm is a pattern, an optional 'if' guard, '=>' and a value: n if n > 0 => \"positive\"".to_string(),
+            )?;
+            let body = self.expression()?;
+            arms.push(MatchArm { pattern, guard, body });
+            if !self.match_types(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        let end_token = self.consume_with_help(
+            TokenType::RightBrace,
+            "Expected '}' after the match arms",
+            "Separate match arms with commas and close them with '}'.".to_string(),
+        ).map_err(|error| error.with_label(open_brace, "arms opened here".to_string()))?;
+        Ok(Expr::Match {
+            subject: Box::new(subject),
+            arms,
+            span: Span::new(start_span.start, end_token.span.end),
+        })
+    }
+
+    /// A match pattern: a literal, `_`, a name, `(p, ...)`, `[p, ...]` or `{key: p, ...}`
+    fn pattern(&mut self) -> Result<Pattern, BccError> {
+        let token = self.peek().clone();
+        match token.token_type {
+            TokenType::Identifier => {
+                self.advance();
+                Ok(Pattern::Target(if token.lexeme == "_" {
+                    AssignTarget::Ignore { span: token.span }
+                } else {
+                    AssignTarget::Variable { name: token.lexeme, span: token.span }
+                }))
+            }
+            TokenType::LeftParen | TokenType::LeftBracket => {
+                self.advance();
+                let close = if token.token_type == TokenType::LeftParen {
+                    TokenType::RightParen
+                } else {
+                    TokenType::RightBracket
+                };
+                let mut elements = Vec::new();
+                let mut is_tuple = close == TokenType::RightBracket;
+                while !self.check(&close) && !self.is_at_end() {
+                    if self.check(&TokenType::Star) {
+                        elements.push(Pattern::Target(self.assignment_target()?));
+                    } else {
+                        elements.push(self.pattern()?);
+                    }
+                    if !self.match_types(&[TokenType::Comma]) {
+                        break;
+                    }
+                    is_tuple = true;
+                }
+                let end = self.consume(close, "Expected the pattern to be closed here.")
+                    .map_err(|error| error.with_label(token.span.clone(), "pattern opened here".to_string()))?
+                    .span.end;
+                // `(p)` only groups its pattern, as `(x)` groups an expression
+                if !is_tuple && elements.len() == 1 && !matches!(elements[0], Pattern::Target(AssignTarget::Starred { .. })) {
+                    return Ok(elements.pop().unwrap());
+                }
+                let stars: Vec<AssignTarget> = elements
+                    .iter()
+                    .filter_map(|element| match element {
+                        Pattern::Target(target @ AssignTarget::Starred { .. }) => Some(target.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                Self::check_starred_targets(&stars)?;
+                Ok(Pattern::Sequence { elements, span: Span::new(token.span.start, end) })
+            }
+            TokenType::LeftBrace => {
+                self.advance();
+                let mut entries = Vec::new();
+                while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+                    let (key, _) = self.literal_pattern()?;
+                    self.consume(TokenType::Colon, "Expected ':' after the key of a dictionary pattern.")?;
+                    entries.push((key, self.pattern()?));
+                    if !self.match_types(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+                let end = self.consume(TokenType::RightBrace, "Expected '}' after the dictionary pattern.")
+                    .map_err(|error| error.with_label(token.span.clone(), "pattern opened here".to_string()))?
+                    .span.end;
+                Ok(Pattern::Dict { entries, span: Span::new(token.span.start, end) })
+            }
+            _ => {
+                let (value, span) = self.literal_pattern()?;
+                Ok(Pattern::Literal { value, span })
+            }
+        }
+    }
+
+    /// A literal in a pattern, including negative numbers such as `-1`
+    fn literal_pattern(&mut self) -> Result<(Value, Span), BccError> {
+        let start = self.peek().span.start;
+        let negative = self.match_types(&[TokenType::Minus]);
+        let literal_types = if negative {
+            &[TokenType::Integer, TokenType::Double][..]
+        } else {
+            &[TokenType::Integer, TokenType::Double, TokenType::String, TokenType::True, TokenType::False, TokenType::Nil][..]
+        };
+        if !literal_types.contains(&self.peek().token_type) {
+            return Err(BccError::parse_error_with_help(
+                self.peek().span.clone(),
+                format!("Expected a pattern, found '{}'", self.peek().lexeme),
+                "Patterns are literals, names, '_', (a, b), [a, b] or {\"key\": pattern}.".to_string(),
+            ).with_code(error_codes::EXPECTED_EXPRESSION));
+        }
+        let Expr::Literal { value, span } = self.primary()? else {
+            unreachable!("literal tokens parse to literals")
+        };
+        let value = match value {
+            Value::Double(n) if negative => Value::Double(-n),
+            value if negative => Value::integer(-value.to_bigint().expect("integer literal")),
+            value => value,
+        };
+        Ok((value, Span::new(start, span.end)))
+    }
+
     fn match_types(&mut self, types: &[TokenType]) -> bool {
         for token_type in types {
             if self.check(token_type) {
@@ -886,7 +1374,7 @@ impl Parser {
             Err(BccError::parse_error(
                 error_span,
                 message.to_string(),
-            ))
+            ).with_code(Self::missing_token_code(&token_type)))
         }
     }
 
@@ -913,73 +1401,106 @@ impl Parser {
                 error_span,
                 message.to_string(),
                 help,
-            ))
+            ).with_code(Self::missing_token_code(&token_type)))
         }
     }
 
-    /// Look ahead to determine if a '{' starts a dictionary literal or a block statement.
-    /// Returns true if it's likely a dictionary, false if it's likely a block.
-    fn is_dictionary_literal(&self) -> bool {
-        assert!(self.check(&TokenType::LeftBrace), "is_dictionary_literal() called when current token is not '{{'");
+    /// Missing closers get their own code since the fix is usually elsewhere in the file
+    fn missing_token_code(token_type: &TokenType) -> &'static str {
+        match token_type {
+            TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket => error_codes::UNCLOSED_DELIMITER,
+            _ => error_codes::EXPECTED_TOKEN,
+        }
+    }
+
+    /// Look ahead to determine if a '{' at the start of a statement opens a block, a
+    /// dictionary literal or a set literal.
+    fn brace_kind(&self) -> BraceKind {
+        assert!(self.check(&TokenType::LeftBrace), "brace_kind() called when current token is not '{{'");
         
         // Look at the token after the '{'
         if self.current + 1 >= self.tokens.len() {
-            return false; // EOF after '{', assume block
+            return BraceKind::Block; // EOF after '{', assume block
         }
         
         let token_after_brace = &self.tokens[self.current + 1];
         
         match token_after_brace.token_type {
             // Empty dictionary: {}
-            TokenType::RightBrace => true,
+            TokenType::RightBrace => BraceKind::Dictionary,
             
             // Dictionary patterns: {"key": value} or {variable: value} or {(expr): value}
-            // Look for the pattern: expression followed by ':'
+            // Set patterns: {1, 2} or {x, y}
             _ => {
-                // Scan ahead to find either ':' or patterns that suggest a block
-                self.scan_for_dictionary_pattern()
+                // Scan ahead to find ':', ',' or patterns that suggest a block
+                self.scan_brace_contents()
             }
         }
     }
     
-    /// Scan ahead from the current position to look for dictionary patterns.
-    /// Returns true if we find a colon that suggests this is a dictionary, or
-    /// if we find clear dictionary-like patterns (but only for unambiguous cases).
-    fn scan_for_dictionary_pattern(&self) -> bool {
+    /// Scan ahead from the current position to the matching '}' to classify what the
+    /// '{' opens. A top-level ':' means a dictionary. A top-level ',' means a set,
+    /// unless something only a block holds shows up: an '=' as in `{ a, b = pair }`,
+    /// a ';', a statement keyword as in `{ assert x, "message" }`, or a second
+    /// statement on a new line. Anything unclear is a block.
+    fn scan_brace_contents(&self) -> BraceKind {
         let mut pos = self.current + 1; // Start after the '{'
         let mut paren_depth = 0;
         let mut bracket_depth = 0;
+        let mut brace_depth = 0;
+        let mut found_comma = false;
+        // A ':' that closes a `c ? a : b` is not a dictionary colon
+        let mut pending_questions = 0;
         
-        // Scan up to a reasonable limit to avoid infinite loops
-        let limit = std::cmp::min(pos + 20, self.tokens.len());
-        
-        while pos < limit {
+        while pos < self.tokens.len() {
             let token = &self.tokens[pos];
-            
+            let top_level = paren_depth == 0 && bracket_depth == 0 && brace_depth == 0;
+            let starts_statement = pos == self.current + 1 || (token.starts_line && Self::ends_expression(&self.tokens[pos - 1]));
+
             match token.token_type {
-                // Track nested parentheses and brackets
+                // Track nested parentheses, brackets and braces
                 TokenType::LeftParen => paren_depth += 1,
                 TokenType::RightParen => paren_depth -= 1,
                 TokenType::LeftBracket => bracket_depth += 1,
                 TokenType::RightBracket => bracket_depth -= 1,
+                TokenType::LeftBrace => brace_depth += 1,
+                TokenType::RightBrace if brace_depth > 0 => brace_depth -= 1,
                 
+                TokenType::Question if top_level => pending_questions += 1,
+                TokenType::Colon if top_level && pending_questions > 0 => pending_questions -= 1,
+
                 // If we find a colon at the top level, it's definitely a dictionary
-                TokenType::Colon if paren_depth == 0 && bracket_depth == 0 => {
-                    return true;
+                TokenType::Colon if top_level => {
+                    return BraceKind::Dictionary;
                 }
                 
-                // If we find a semicolon at the top level, it's likely a block
-                TokenType::Semicolon if paren_depth == 0 && bracket_depth == 0 => {
-                    return false;
+                TokenType::Comma if top_level => found_comma = true,
+                
+                // Assignments, semicolons and statements only appear in blocks
+                TokenType::Equal | TokenType::Semicolon if top_level => {
+                    return BraceKind::Block;
+                }
+                TokenType::Assert | TokenType::While | TokenType::For | TokenType::Fun | TokenType::Return
+                    if top_level =>
+                {
+                    return BraceKind::Block;
+                }
+                // Mid-line, `if` may be a conditional expression inside a set element
+                TokenType::If if top_level && starts_statement => return BraceKind::Block,
+                TokenType::Identifier | TokenType::Integer | TokenType::Double | TokenType::String
+                | TokenType::True | TokenType::False | TokenType::Nil
+                    if top_level && starts_statement && pos > self.current + 1 =>
+                {
+                    return BraceKind::Block;
                 }
                 
-                // If we hit the closing brace without finding a colon, it's likely a block
-                TokenType::RightBrace if paren_depth == 0 && bracket_depth == 0 => {
-                    return false;
+                // The closing brace decides between a set and a block
+                TokenType::RightBrace => {
+                    return if found_comma { BraceKind::Set } else { BraceKind::Block };
                 }
                 
                 // EOF
-                TokenType::Eof => return false,
+                TokenType::Eof => return BraceKind::Block,
                 
                 _ => {}
             }
@@ -988,6 +1509,24 @@ impl Parser {
         }
         
         // If we couldn't determine, default to block (safer choice)
-        false
+        BraceKind::Block
     }
-}
\ No newline at end of file
+
+    /// Whether a token can end an expression, so that a name or literal on the next
+    /// line starts a new statement rather than continuing this one
+    fn ends_expression(token: &Token) -> bool {
+        matches!(
+            token.token_type,
+            TokenType::Identifier
+                | TokenType::Integer
+                | TokenType::Double
+                | TokenType::String
+                | TokenType::True
+                | TokenType::False
+                | TokenType::Nil
+                | TokenType::RightParen
+                | TokenType::RightBracket
+                | TokenType::RightBrace
+        )
+    }
+}
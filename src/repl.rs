@@ -1,11 +1,11 @@
 use crate::evaluator::Evaluator;
 use crate::lexer::Lexer;
 use crate::parser::Parser;
+use crate::resolver::Resolver;
 use std::io::{self, Write};
 
 /// Simplified REPL using owned strings for better maintainability.
 /// Prioritizes code clarity and maintains persistent state between commands.
-
 pub fn start() {
     println!("BCC Interpreter v0.1.0");
     println!("Type 'exit' or press Ctrl+C to quit");
@@ -51,8 +51,10 @@ fn run_repl_command(source: &str, evaluator: &mut Evaluator) {
     let mut lexer = Lexer::new(source.to_string());
     let tokens = match lexer.scan_tokens() {
         Ok(tokens) => tokens,
-        Err(error) => {
-            error.report(source, None);
+        Err(errors) => {
+            for error in errors {
+                error.report(source, None);
+            }
             return;
         }
     };
@@ -61,12 +63,26 @@ fn run_repl_command(source: &str, evaluator: &mut Evaluator) {
     let mut parser = Parser::new(tokens);
     let program = match parser.parse() {
         Ok(program) => program,
-        Err(error) => {
-            error.report(source, None);
+        Err(errors) => {
+            for error in errors {
+                error.report(source, None);
+            }
             return;
         }
     };
 
+    // Scope analysis against the globals defined by earlier commands
+    let resolution = Resolver::interactive(evaluator.global_names()).resolve(&program);
+    for warning in &resolution.warnings {
+        warning.report(source, None);
+    }
+    if !resolution.errors.is_empty() {
+        for error in &resolution.errors {
+            error.report(source, None);
+        }
+        return;
+    }
+
     // Check if it's a single expression statement and display its value (but not assignments)
     if program.statements.len() == 1 {
         if let crate::ast::Stmt::Expression { expr, .. } = &program.statements[0] {
@@ -87,7 +103,7 @@ fn run_repl_command(source: &str, evaluator: &mut Evaluator) {
     }
 
     // Otherwise, evaluate the program normally (for assignments, print statements, etc.)
-    if let Err(error) = evaluator.evaluate_program(&program) {
+    if let Err(error) = evaluator.evaluate_resolved(&program, &resolution) {
         error.report(source, None);
     }
 }
\ No newline at end of file
@@ -0,0 +1,624 @@
+use crate::ast::{AssignTarget, Expr, KeywordArg, MatchArm, Program, Stmt};
+use crate::error::{BccError, ErrorKind, Span};
+use crate::error_codes;
+use crate::evaluator::BUILTIN_NAMES;
+use crate::suggest;
+use std::collections::{HashMap, HashSet};
+
+/// Result of the static pass that runs between parsing and evaluation
+#[derive(Debug, Default)]
+pub struct Resolution {
+    /// Names that are read where no assignment can reach them; the program should not run
+    pub errors: Vec<BccError>,
+    pub warnings: Vec<BccError>,
+    /// Scope depth of each variable reference, keyed by the reference's start offset
+    pub(crate) depths: HashMap<usize, usize>,
+}
+
+impl Resolution {
+    /// How many scopes out from the reference the variable lives: 0 is the innermost block.
+    /// None when the depth depends on which branches ran at runtime.
+    pub fn depth(&self, reference: &Span) -> Option<usize> {
+        self.depths.get(&reference.start).copied()
+    }
+}
+
+/// One variable: every assignment to the same name in the same scope shares a binding
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    defined_at: Span,
+    read: bool,
+    /// Assigned on every path through its scope so far
+    definite: bool,
+    /// Builtins, underscore names and REPL globals never produce warnings
+    exempt: bool,
+    pending: Option<PendingAssignment>,
+}
+
+/// An assignment that nothing has read yet
+#[derive(Debug)]
+struct PendingAssignment {
+    span: Span,
+    region: usize,
+    sequence: usize,
+}
+
+#[derive(Debug)]
+struct Scope {
+    bindings: HashMap<String, usize>,
+    order: Vec<usize>,
+    /// Region the scope's own statements run in; assignments there always happen
+    region: usize,
+}
+
+/// Reads seen while resolving a loop, which may observe assignments from a previous iteration
+#[derive(Debug)]
+struct LoopFrame {
+    reads: HashSet<usize>,
+    first_sequence: usize,
+}
+
+/// Static scope analysis.
+///
+/// Mirrors the evaluator's scoping rules: blocks open a scope, assigning to a
+/// name visible in an enclosing scope updates that variable, and any other
+/// assignment creates the variable in the global scope, as `Environment::assign` does. Code that may not
+/// run (branches, loop bodies, short-circuited operands) is tracked as a
+/// nested "region" so that only facts true on every path become errors.
+pub struct Resolver {
+    bindings: Vec<Binding>,
+    scopes: Vec<Scope>,
+    region_parents: Vec<Option<usize>>,
+    current_region: usize,
+    loops: Vec<LoopFrame>,
+    sequence: usize,
+    /// Globals outlive a REPL entry, so they are never reported as unused
+    interactive: bool,
+    resolution: Resolution,
+}
+
+impl Resolver {
+    /// Resolver for a whole script; only the built-ins are predefined
+    pub fn new() -> Self {
+        let mut resolver = Self {
+            bindings: Vec::new(),
+            scopes: Vec::new(),
+            region_parents: vec![None],
+            current_region: 0,
+            loops: Vec::new(),
+            sequence: 0,
+            interactive: false,
+            resolution: Resolution::default(),
+        };
+        resolver.begin_scope();
+        for name in BUILTIN_NAMES {
+            resolver.predefine(name);
+        }
+        resolver
+    }
+
+    /// Resolver for one REPL entry. Globals from earlier entries are predefined,
+    /// and new globals are not reported as unused because later entries may read them.
+    pub fn interactive<I>(globals: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut resolver = Self::new();
+        for name in globals {
+            resolver.predefine(&name);
+        }
+        resolver.interactive = true;
+        resolver
+    }
+
+    pub fn resolve(mut self, program: &Program) -> Resolution {
+        for statement in &program.statements {
+            self.resolve_statement(statement);
+        }
+        self.end_scope();
+        self.resolution
+    }
+
+    fn resolve_statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression { expr, .. } => self.resolve_expression(expr),
+            Stmt::Block { statements, .. } => {
+                self.begin_scope();
+                for statement in statements {
+                    self.resolve_statement(statement);
+                }
+                self.end_scope();
+            }
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                self.resolve_expression(condition);
+                self.in_region(|resolver| resolver.resolve_statement(then_branch));
+                if let Some(else_branch) = else_branch {
+                    self.in_region(|resolver| resolver.resolve_statement(else_branch));
+                }
+            }
+            Stmt::While { condition, body, .. } => {
+                self.begin_loop(Some(condition), None, body);
+                self.resolve_expression(condition);
+                self.in_region(|resolver| resolver.resolve_statement(body));
+                self.end_loop();
+            }
+            Stmt::For { initializer, condition, increment, body, .. } => {
+                // The evaluator runs the initializer in the current scope, not a new one
+                if let Some(initializer) = initializer {
+                    self.resolve_statement(initializer);
+                }
+                self.begin_loop(condition.as_ref(), increment.as_ref(), body);
+                if let Some(condition) = condition {
+                    self.resolve_expression(condition);
+                }
+                self.in_region(|resolver| {
+                    resolver.resolve_statement(body);
+                    if let Some(increment) = increment {
+                        resolver.resolve_expression(increment);
+                    }
+                });
+                self.end_loop();
+            }
+            Stmt::Assert { condition, message, .. } => {
+                self.resolve_expression(condition);
+                if let Some(message) = message {
+                    // The message is only evaluated when the assertion fails
+                    self.in_region(|resolver| resolver.resolve_expression(message));
+                }
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal { .. } => {}
+            Expr::Variable { name, span } => self.read(name, span),
+            Expr::Assign { name, value, span } => {
+                self.resolve_expression(value);
+                self.assign(name, span);
+            }
+            Expr::MultiAssign { targets, value, .. } => {
+                self.resolve_expression(value);
+                for (name, span) in targets.iter().flat_map(AssignTarget::bindings) {
+                    self.assign(name, span);
+                }
+            }
+            Expr::Binary { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expr::Comparison { operands, .. } => {
+                self.resolve_expression(&operands[0]);
+                // Later operands are skipped once a comparison is false
+                self.in_region(|resolver| {
+                    for operand in &operands[1..] {
+                        resolver.resolve_expression(operand);
+                    }
+                });
+            }
+            Expr::Unary { operand, .. } => self.resolve_expression(operand),
+            Expr::Logical { left, right, .. } => {
+                self.resolve_expression(left);
+                // The right operand is skipped when the left one decides the result
+                self.in_region(|resolver| resolver.resolve_expression(right));
+            }
+            Expr::Conditional { condition, then_branch, else_branch, .. } => {
+                self.resolve_expression(condition);
+                // Only one branch runs
+                self.in_region(|resolver| resolver.resolve_expression(then_branch));
+                self.in_region(|resolver| resolver.resolve_expression(else_branch));
+            }
+            Expr::Call { callee, args, .. } => self.resolve_call(callee, args, &[]),
+            Expr::CallWithKwargs { callee, positional_args, keyword_args, .. } => {
+                self.resolve_call(callee, positional_args, keyword_args)
+            }
+            Expr::MultiReturn { values: elements, .. }
+            | Expr::List { elements, .. }
+            | Expr::Set { elements, .. }
+            | Expr::Tuple { elements, .. } => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expr::Grouping { expr, .. } => self.resolve_expression(expr),
+            Expr::Dict { pairs, .. } => {
+                for (key, value) in pairs {
+                    self.resolve_expression(key);
+                    self.resolve_expression(value);
+                }
+            }
+            Expr::PropertyAccess { object, .. } => self.resolve_expression(object),
+            Expr::Match { subject, arms, span } => {
+                self.resolve_expression(subject);
+                for arm in arms {
+                    // An arm's names are assigned only when its pattern matches, and its
+                    // body runs only when the guard holds as well
+                    self.in_region(|resolver| {
+                        for (name, span) in arm.pattern.bindings() {
+                            resolver.assign(name, span);
+                        }
+                        if let Some(guard) = &arm.guard {
+                            resolver.resolve_expression(guard);
+                        }
+                        resolver.in_region(|resolver| resolver.resolve_expression(&arm.body));
+                    });
+                }
+                if !arms.iter().any(MatchArm::is_catch_all) {
+                    self.resolution.warnings.push(
+                        BccError::new_with_help(
+                            ErrorKind::Warning,
+                            Span::new(span.start, subject.span().end),
+                            "This match has no arm that accepts every value".to_string(),
+                            "Add a final '_ => ...' arm; a value no arm matches is a runtime error.".to_string(),
+                        )
+                        .with_code(error_codes::NON_EXHAUSTIVE_MATCH),
+                    );
+                }
+            }
+        }
+    }
+
+    fn resolve_call(&mut self, callee: &Expr, args: &[Expr], keyword_args: &[KeywordArg]) {
+        self.resolve_expression(callee);
+        let resolve = |resolver: &mut Self| {
+            resolver.resolve_arguments(callee, args);
+            for kwarg in keyword_args {
+                resolver.resolve_expression(&kwarg.value);
+            }
+        };
+        // `object?.name(...)` skips its arguments when the object is nil
+        if matches!(callee, Expr::PropertyAccess { optional: true, .. }) {
+            self.in_region(resolve);
+        } else {
+            resolve(self);
+        }
+    }
+
+    fn resolve_arguments(&mut self, callee: &Expr, args: &[Expr]) {
+        // case() stops evaluating its arguments at the first true condition
+        let lazy = matches!(callee, Expr::Variable { name, .. } if name == "case");
+        for (i, arg) in args.iter().enumerate() {
+            if lazy && i > 0 {
+                self.in_region(|resolver| resolver.resolve_expression(arg));
+            } else {
+                self.resolve_expression(arg);
+            }
+        }
+    }
+
+    fn read(&mut self, name: &str, span: &Span) {
+        if name == "_" {
+            self.resolution.warnings.push(
+                BccError::new_with_help(
+                    ErrorKind::Warning,
+                    span.clone(),
+                    "'_' is read here, but it is meant for values that are ignored".to_string(),
+                    "Give the value a real name if you need to use it.".to_string(),
+                )
+                .with_code(error_codes::UNDERSCORE_READ),
+            );
+        }
+
+        let Some((index, depth)) = self.lookup(name) else {
+            let message = format!("Undefined variable '{}'", name);
+            let visible = self.visible_names();
+            let error = match suggest::help_for(name, visible.iter().map(String::as_str)) {
+                Some(help) => BccError::resolve_error_with_help(span.clone(), message, help),
+                None => BccError::resolve_error(span.clone(), message),
+            };
+            self.resolution.errors.push(
+                error
+                    .with_code(error_codes::UNDEFINED_VARIABLE)
+                    .with_note("no assignment to this name can run before this point".to_string()),
+            );
+            return;
+        };
+
+        let binding = &mut self.bindings[index];
+        binding.read = true;
+        binding.pending = None;
+        // Outside the innermost scope, a variable assigned on only some paths may not exist yet
+        if depth == 0 || binding.definite {
+            self.resolution.depths.insert(span.start, depth);
+        }
+        for frame in &mut self.loops {
+            frame.reads.insert(index);
+        }
+    }
+
+    fn assign(&mut self, name: &str, span: &Span) {
+        self.sequence += 1;
+        let (index, depth) = match self.lookup(name) {
+            Some(found) => found,
+            None => (self.define(0, name, span), self.scopes.len() - 1),
+        };
+        let scope_region = self.scopes[self.scopes.len() - 1 - depth].region;
+        let current_region = self.current_region;
+
+        // A value that was always going to be replaced before anything read it is dead
+        if let Some(pending) = &self.bindings[index].pending {
+            if self.region_within(pending.region, current_region) && !self.bindings[index].exempt {
+                let warning = BccError::new_with_help(
+                    ErrorKind::Warning,
+                    pending.span.clone(),
+                    format!("Value assigned to '{}' is never read", name),
+                    "It is overwritten before being used; remove the earlier assignment.".to_string(),
+                )
+                .with_code(error_codes::UNREAD_ASSIGNMENT)
+                .with_label(span.clone(), "overwritten here".to_string());
+                self.resolution.warnings.push(warning);
+            }
+        }
+
+        // Existing or not, the assigned variable ends up in the scope found above
+        self.resolution.depths.insert(span.start, depth);
+        let binding = &mut self.bindings[index];
+        if current_region == scope_region {
+            binding.definite = true;
+        }
+        binding.pending = Some(PendingAssignment {
+            span: span.clone(),
+            region: current_region,
+            sequence: self.sequence,
+        });
+    }
+
+    /// Innermost binding for a name, with how many scopes out it lives
+    fn lookup(&self, name: &str) -> Option<(usize, usize)> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| scope.bindings.get(name).map(|&index| (index, depth)))
+    }
+
+    fn visible_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.bindings.keys().cloned())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn define(&mut self, scope: usize, name: &str, span: &Span) -> usize {
+        let index = self.bindings.len();
+        self.bindings.push(Binding {
+            name: name.to_string(),
+            // Assignment spans cover the value too; point at just the name
+            defined_at: Span::new(span.start, span.start + name.len()),
+            read: false,
+            definite: false,
+            exempt: name.starts_with('_') || (self.interactive && scope == 0),
+            pending: None,
+        });
+        self.scopes[scope].bindings.insert(name.to_string(), index);
+        self.scopes[scope].order.push(index);
+        index
+    }
+
+    /// A name that exists before the program starts: a built-in or a REPL global
+    fn predefine(&mut self, name: &str) {
+        let index = self.define(0, name, &Span::single(0));
+        self.bindings[index].definite = true;
+        self.bindings[index].exempt = true;
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Scope {
+            bindings: HashMap::new(),
+            order: Vec::new(),
+            region: self.current_region,
+        });
+    }
+
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().expect("end_scope without begin_scope");
+        for index in scope.order {
+            let binding = &self.bindings[index];
+            if binding.exempt {
+                continue;
+            }
+            if !binding.read {
+                self.resolution.warnings.push(
+                    BccError::new_with_help(
+                        ErrorKind::Warning,
+                        binding.defined_at.clone(),
+                        format!("Unused variable '{}'", binding.name),
+                        format!("If this is intentional, prefix it with an underscore: '_{}'", binding.name),
+                    )
+                    .with_code(error_codes::UNUSED_VARIABLE),
+                );
+            } else if let Some(pending) = &binding.pending {
+                self.resolution.warnings.push(
+                    BccError::new(
+                        ErrorKind::Warning,
+                        pending.span.clone(),
+                        format!("Value assigned to '{}' is never read", binding.name),
+                    )
+                    .with_code(error_codes::UNREAD_ASSIGNMENT),
+                );
+            }
+        }
+    }
+
+    /// Run `resolve` for code that may or may not execute
+    fn in_region(&mut self, resolve: impl FnOnce(&mut Self)) {
+        let parent = self.current_region;
+        self.region_parents.push(Some(parent));
+        self.current_region = self.region_parents.len() - 1;
+        resolve(self);
+        self.current_region = parent;
+    }
+
+    /// Whether `region` is `ancestor` or nested inside it
+    fn region_within(&self, mut region: usize, ancestor: usize) -> bool {
+        loop {
+            if region == ancestor {
+                return true;
+            }
+            match self.region_parents[region] {
+                Some(parent) => region = parent,
+                None => return false,
+            }
+        }
+    }
+
+    fn begin_loop(&mut self, condition: Option<&Expr>, increment: Option<&Expr>, body: &Stmt) {
+        // A later iteration can read names the loop assigns directly in this scope,
+        // so declare them up front rather than reporting the first read as undefined
+        let mut assigned = Vec::new();
+        if let Some(condition) = condition {
+            collect_assignments_in_expression(condition, &mut assigned);
+        }
+        if let Some(increment) = increment {
+            collect_assignments_in_expression(increment, &mut assigned);
+        }
+        collect_assignments_in_statement(body, &mut assigned);
+        for (name, span) in assigned {
+            if self.lookup(&name).is_none() {
+                self.define(0, &name, &span);
+            }
+        }
+
+        self.loops.push(LoopFrame {
+            reads: HashSet::new(),
+            first_sequence: self.sequence + 1,
+        });
+    }
+
+    fn end_loop(&mut self) {
+        let frame = self.loops.pop().expect("end_loop without begin_loop");
+        // Assignments made in the loop may be read by the next iteration
+        for index in frame.reads {
+            let binding = &mut self.bindings[index];
+            if binding
+                .pending
+                .as_ref()
+                .is_some_and(|pending| pending.sequence >= frame.first_sequence)
+            {
+                binding.pending = None;
+            }
+        }
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Names a statement assigns, including inside nested blocks since new names are global
+fn collect_assignments_in_statement(stmt: &Stmt, assigned: &mut Vec<(String, Span)>) {
+    match stmt {
+        Stmt::Expression { expr, .. } => collect_assignments_in_expression(expr, assigned),
+        Stmt::Block { statements, .. } => {
+            for statement in statements {
+                collect_assignments_in_statement(statement, assigned);
+            }
+        }
+        Stmt::If { condition, then_branch, else_branch, .. } => {
+            collect_assignments_in_expression(condition, assigned);
+            collect_assignments_in_statement(then_branch, assigned);
+            if let Some(else_branch) = else_branch {
+                collect_assignments_in_statement(else_branch, assigned);
+            }
+        }
+        Stmt::While { condition, body, .. } => {
+            collect_assignments_in_expression(condition, assigned);
+            collect_assignments_in_statement(body, assigned);
+        }
+        Stmt::For { initializer, condition, increment, body, .. } => {
+            if let Some(initializer) = initializer {
+                collect_assignments_in_statement(initializer, assigned);
+            }
+            for expr in condition.iter().chain(increment.iter()) {
+                collect_assignments_in_expression(expr, assigned);
+            }
+            collect_assignments_in_statement(body, assigned);
+        }
+        Stmt::Assert { condition, message, .. } => {
+            collect_assignments_in_expression(condition, assigned);
+            if let Some(message) = message {
+                collect_assignments_in_expression(message, assigned);
+            }
+        }
+    }
+}
+
+fn collect_assignments_in_expression(expr: &Expr, assigned: &mut Vec<(String, Span)>) {
+    match expr {
+        Expr::Literal { .. } | Expr::Variable { .. } => {}
+        Expr::Assign { name, value, span } => {
+            collect_assignments_in_expression(value, assigned);
+            assigned.push((name.clone(), span.clone()));
+        }
+        Expr::MultiAssign { targets, value, .. } => {
+            collect_assignments_in_expression(value, assigned);
+            for (name, span) in targets.iter().flat_map(AssignTarget::bindings) {
+                assigned.push((name.to_string(), span.clone()));
+            }
+        }
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            collect_assignments_in_expression(left, assigned);
+            collect_assignments_in_expression(right, assigned);
+        }
+        Expr::Conditional { condition, then_branch, else_branch, .. } => {
+            collect_assignments_in_expression(condition, assigned);
+            collect_assignments_in_expression(then_branch, assigned);
+            collect_assignments_in_expression(else_branch, assigned);
+        }
+        Expr::Comparison { operands, .. } => {
+            for operand in operands {
+                collect_assignments_in_expression(operand, assigned);
+            }
+        }
+        Expr::Match { subject, arms, .. } => {
+            collect_assignments_in_expression(subject, assigned);
+            for arm in arms {
+                for (name, span) in arm.pattern.bindings() {
+                    assigned.push((name.to_string(), span.clone()));
+                }
+                for expr in arm.guard.iter().chain([&arm.body]) {
+                    collect_assignments_in_expression(expr, assigned);
+                }
+            }
+        }
+        Expr::Unary { operand: inner, .. }
+        | Expr::Grouping { expr: inner, .. }
+        | Expr::PropertyAccess { object: inner, .. } => collect_assignments_in_expression(inner, assigned),
+        Expr::Call { callee, args, .. } => {
+            collect_assignments_in_expression(callee, assigned);
+            for arg in args {
+                collect_assignments_in_expression(arg, assigned);
+            }
+        }
+        Expr::CallWithKwargs { callee, positional_args, keyword_args, .. } => {
+            collect_assignments_in_expression(callee, assigned);
+            for arg in positional_args {
+                collect_assignments_in_expression(arg, assigned);
+            }
+            for kwarg in keyword_args {
+                collect_assignments_in_expression(&kwarg.value, assigned);
+            }
+        }
+        Expr::MultiReturn { values: elements, .. }
+        | Expr::List { elements, .. }
+        | Expr::Set { elements, .. }
+        | Expr::Tuple { elements, .. } => {
+            for element in elements {
+                collect_assignments_in_expression(element, assigned);
+            }
+        }
+        Expr::Dict { pairs, .. } => {
+            for (key, value) in pairs {
+                collect_assignments_in_expression(key, assigned);
+                collect_assignments_in_expression(value, assigned);
+            }
+        }
+    }
+}
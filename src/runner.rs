@@ -1,18 +1,93 @@
+use crate::ast::Program;
+use crate::compiled::CompiledScript;
+use crate::compiler::Compiler;
+use crate::disassembler;
+use crate::error::{ErrorFormat, LineIndex};
 use crate::evaluator::Evaluator;
 use crate::lexer::Lexer;
+use crate::optimizer::Optimizer;
 use crate::parser::Parser;
+use crate::resolver::{Resolution, Resolver};
+use crate::vm::Vm;
+
+/// Options controlling how a script is run and how its diagnostics are reported
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub error_format: ErrorFormat,
+    /// Fold constant expressions and drop dead branches before evaluating
+    pub optimize: bool,
+    pub backend: Backend,
+}
+
+/// Which engine executes the checked program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Walk the AST directly
+    #[default]
+    Tree,
+    /// Compile to bytecode and run it on the stack VM
+    Vm,
+}
 
 /// Simplified runner using owned strings for better maintainability.
 /// Prioritizes code clarity over memory efficiency.
-
 pub fn run(source: &str, filename: Option<&str>) {
+    run_with_options(source, filename, &RunOptions::default());
+}
+
+pub fn run_with_options(source: &str, filename: Option<&str>, options: &RunOptions) {
+    let Some((program, resolution)) = check(source, filename, options) else {
+        return;
+    };
+
+    // Evaluation
+    let result = match options.backend {
+        Backend::Tree => Evaluator::new().evaluate_resolved(&program, &resolution),
+        Backend::Vm => Vm::new().run(&Compiler::compile(&program)),
+    };
+    if let Err(error) = result {
+        error.emit(source, filename, options.error_format, &LineIndex::new(source));
+    }
+}
+
+/// Check and compile a script for `bcc compile`; None if it has errors, which are reported
+pub fn compile(source: &str, filename: &str, options: &RunOptions) -> Option<CompiledScript> {
+    let (program, _) = check(source, Some(filename), options)?;
+    Some(CompiledScript {
+        filename: filename.to_string(),
+        source: source.to_string(),
+        chunk: Compiler::compile(&program),
+    })
+}
+
+/// Run a script loaded from a `.bcco` file; it was checked when it was compiled
+pub fn run_compiled(script: &CompiledScript, options: &RunOptions) {
+    if let Err(error) = Vm::new().run(&script.chunk) {
+        let line_index = LineIndex::new(&script.source);
+        error.emit(&script.source, Some(&script.filename), options.error_format, &line_index);
+    }
+}
+
+/// Instruction listing for `bcc --disassemble`; None if the script has errors, which are reported
+pub fn disassemble(source: &str, filename: &str, options: &RunOptions) -> Option<String> {
+    let script = compile(source, filename, options)?;
+    Some(disassembler::disassemble(&script.chunk, &script.source, &script.filename))
+}
+
+/// Lex, parse, resolve and optionally optimize a script, reporting every diagnostic.
+/// Returns the program to execute and its scope analysis, or None if it must not run.
+fn check(source: &str, filename: Option<&str>, options: &RunOptions) -> Option<(Program, Resolution)> {
+    let line_index = LineIndex::new(source);
+
     // Lexical analysis
     let mut lexer = Lexer::new(source.to_string());
     let tokens = match lexer.scan_tokens() {
         Ok(tokens) => tokens,
-        Err(error) => {
-            error.report(source, filename);
-            return;
+        Err(errors) => {
+            for error in errors {
+                error.emit(source, filename, options.error_format, &line_index);
+            }
+            return None;
         }
     };
 
@@ -20,15 +95,30 @@ pub fn run(source: &str, filename: Option<&str>) {
     let mut parser = Parser::new(tokens);
     let program = match parser.parse() {
         Ok(program) => program,
-        Err(error) => {
-            error.report(source, filename);
-            return;
+        Err(errors) => {
+            for error in errors {
+                error.emit(source, filename, options.error_format, &line_index);
+            }
+            return None;
         }
     };
 
-    // Evaluation
-    let mut evaluator = Evaluator::new();
-    if let Err(error) = evaluator.evaluate_program(&program) {
-        error.report(source, filename);
+    // Scope analysis; warnings are shown but only definite errors stop the run
+    let resolution = Resolver::new().resolve(&program);
+    for warning in &resolution.warnings {
+        warning.emit(source, filename, options.error_format, &line_index);
+    }
+    if !resolution.errors.is_empty() {
+        for error in &resolution.errors {
+            error.emit(source, filename, options.error_format, &line_index);
+        }
+        return None;
+    }
+
+    // The optimizer keeps every block, so the resolved scope depths still hold
+    if options.optimize {
+        Some((Optimizer::new().optimize(program), resolution))
+    } else {
+        Some((program, resolution))
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,31 @@
+use crate::bigint::BigInt;
+use crate::ordered_map::OrderedMap;
+use std::cell::RefCell;
 use std::fmt;
-use std::collections::HashMap;
+use std::rc::Rc;
 
-#[derive(Debug, Clone, PartialEq)]
+/// A runtime value.
+///
+/// Lists, dictionaries and sets are shared, mutable containers: cloning a `Value`
+/// clones the reference, so `b = a` makes both names refer to the same list, as in
+/// Python. Lists and dictionaries can therefore hold themselves, and equality and
+/// `Display` stop at a container they are already inside. Dictionaries and sets
+/// keep insertion order, so they print the same way on every run.
+#[derive(Debug, Clone)]
 pub enum Value {
     Nil,
     Bool(bool),
     Int(i64),
+    /// An int outside the i64 range. Arithmetic promotes to it instead of overflowing,
+    /// and `Value::integer` turns results that fit back into `Int`, so every int has
+    /// exactly one representation.
+    BigInt(BigInt),
     Double(f64),
     String(String),
-    List(Vec<Value>),
-    Dict(HashMap<String, Value>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Dict(Rc<RefCell<OrderedMap<Key, Value>>>),
+    /// Only hashable values can be elements, so a set never contains a container
+    Set(Rc<RefCell<OrderedMap<Key, ()>>>),
     CaseResult(CaseResult),
     /// Tuple type for multi-return values and grouped expressions
     Tuple(Vec<Value>),
@@ -20,16 +36,154 @@ pub struct CaseResult {
     pub result: Box<Value>,
 }
 
+/// A dictionary key or set element: an immutable value that can be hashed.
+///
+/// Lists, dictionaries and sets can change after they are used as a key, and
+/// doubles would have to hash equal to the ints they compare equal to, so none of
+/// them can be a key. `true` and `1` are different keys because they are not `==`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    BigInt(BigInt),
+    String(String),
+    Tuple(Vec<Key>),
+}
+
+impl Key {
+    /// The key for a value, or a description of the part that cannot be hashed,
+    /// such as "list" or "tuple containing a list"
+    pub fn from_value(value: &Value) -> Result<Key, String> {
+        match value {
+            Value::Nil => Ok(Key::Nil),
+            Value::Bool(b) => Ok(Key::Bool(*b)),
+            Value::Int(n) => Ok(Key::Int(*n)),
+            Value::BigInt(n) => Ok(Key::BigInt(n.clone())),
+            Value::String(s) => Ok(Key::String(s.clone())),
+            Value::Tuple(items) => items
+                .iter()
+                .map(Key::from_value)
+                .collect::<Result<Vec<Key>, String>>()
+                .map(Key::Tuple)
+                // A nested tuple already names the innermost unhashable item
+                .map_err(|unhashable| if unhashable.starts_with("tuple") {
+                    unhashable
+                } else {
+                    format!("tuple containing a {}", unhashable)
+                }),
+            _ => Err(value.type_name().to_string()),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            Key::Nil => Value::Nil,
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Int(n) => Value::Int(*n),
+            Key::BigInt(n) => Value::BigInt(n.clone()),
+            Key::String(s) => Value::String(s.clone()),
+            Key::Tuple(items) => Value::Tuple(items.iter().map(Key::to_value).collect()),
+        }
+    }
+}
+
+/// Keys print like their values, except that strings are quoted so `{"1": x}` and
+/// `{1: x}` can be told apart
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Key::String(s) => write!(f, "\"{}\"", s),
+            Key::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            key => write!(f, "{}", key.to_value()),
+        }
+    }
+}
+
 impl Value {
+    /// An int, stored as an i64 whenever it fits
+    pub fn integer(n: BigInt) -> Self {
+        match n.to_i64() {
+            Some(small) => Value::Int(small),
+            None => Value::BigInt(n),
+        }
+    }
+
+    /// Either kind of int, widened to a BigInt
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Value::Int(n) => Some(BigInt::from(*n)),
+            Value::BigInt(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn list(items: Vec<Value>) -> Self {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn dict(entries: OrderedMap<Key, Value>) -> Self {
+        Value::Dict(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn set(elements: OrderedMap<Key, ()>) -> Self {
+        Value::Set(Rc::new(RefCell::new(elements)))
+    }
+
+    /// Identity, as tested by `is`: lists and dictionaries are identical only when they
+    /// are the same container; other values are immutable, so equal values of the same
+    /// type are identical.
+    pub fn is_identical(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::List(l), Value::List(r)) => Rc::ptr_eq(l, r),
+            (Value::Dict(l), Value::Dict(r)) => Rc::ptr_eq(l, r),
+            (Value::Set(l), Value::Set(r)) => Rc::ptr_eq(l, r),
+            (Value::Tuple(l), Value::Tuple(r)) => {
+                l.len() == r.len() && l.iter().zip(r).all(|(a, b)| a.is_identical(b))
+            }
+            (Value::CaseResult(l), Value::CaseResult(r)) => l.result.is_identical(&r.result),
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Int(l), Value::Int(r)) => l == r,
+            (Value::BigInt(l), Value::BigInt(r)) => l == r,
+            (Value::Double(l), Value::Double(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            _ => false,
+        }
+    }
+
+    /// Address of a list or dictionary, used to detect cycles
+    fn container_address(&self) -> Option<usize> {
+        match self {
+            Value::List(list) => Some(Rc::as_ptr(list) as *const () as usize),
+            Value::Dict(dict) => Some(Rc::as_ptr(dict) as *const () as usize),
+            _ => None,
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::Nil => false,
             Value::Bool(b) => *b,
             Value::Int(n) => *n != 0,
+            Value::BigInt(_) => true,
             Value::Double(n) => *n != 0.0,
             Value::String(s) => !s.is_empty(),
-            Value::List(l) => !l.is_empty(),
-            Value::Dict(d) => !d.is_empty(),
+            Value::List(l) => !l.borrow().is_empty(),
+            Value::Dict(d) => !d.borrow().is_empty(),
+            Value::Set(s) => !s.borrow().is_empty(),
             Value::CaseResult(case_result) => case_result.result.is_truthy(),
             Value::Tuple(t) => !t.is_empty(),
         }
@@ -39,69 +193,154 @@ impl Value {
         match self {
             Value::Nil => "nil",
             Value::Bool(_) => "bool",
-            Value::Int(_) => "int",
+            Value::Int(_) | Value::BigInt(_) => "int",
             Value::Double(_) => "double", 
             Value::String(_) => "string",
             Value::List(_) => "list",
             Value::Dict(_) => "dict",
+            Value::Set(_) => "set",
             Value::CaseResult(_) => "case_result",
             Value::Tuple(_) => "tuple",
         }
     }
 }
 
+/// Structural equality, as tested by `==`. Ints and doubles compare by numeric value.
+///
+/// A pair of containers met again while it is still being compared is taken to be
+/// equal, so comparing self-referencing containers terminates.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        equal(self, other, &mut Vec::new())
+    }
+}
+
+fn equal(left: &Value, right: &Value, comparing: &mut Vec<(usize, usize)>) -> bool {
+    if let (Some(l), Some(r)) = (left.container_address(), right.container_address()) {
+        if l == r || comparing.contains(&(l, r)) {
+            return true;
+        }
+        comparing.push((l, r));
+        let result = match (left, right) {
+            (Value::List(l), Value::List(r)) => equal_items(&l.borrow(), &r.borrow(), comparing),
+            (Value::Dict(l), Value::Dict(r)) => {
+                let (l, r) = (l.borrow(), r.borrow());
+                l.len() == r.len()
+                    && l.iter().all(|(key, value)| r.get(key).is_some_and(|other| equal(value, other, comparing)))
+            }
+            _ => false,
+        };
+        comparing.pop();
+        return result;
+    }
+
+    match (left, right) {
+        (Value::Nil, Value::Nil) => true,
+        (Value::Bool(l), Value::Bool(r)) => l == r,
+        (Value::Int(l), Value::Int(r)) => l == r,
+        (Value::Double(l), Value::Double(r)) => l == r,
+        (Value::BigInt(l), Value::BigInt(r)) => l == r,
+        (Value::Int(l), Value::Double(r)) => (*l as f64) == *r,
+        (Value::Double(l), Value::Int(r)) => *l == (*r as f64),
+        (Value::BigInt(l), Value::Double(r)) => l.to_f64() == *r,
+        (Value::Double(l), Value::BigInt(r)) => *l == r.to_f64(),
+        (Value::String(l), Value::String(r)) => l == r,
+        (Value::Tuple(l), Value::Tuple(r)) => equal_items(l, r, comparing),
+        (Value::Set(l), Value::Set(r)) => {
+            let (l, r) = (l.borrow(), r.borrow());
+            l.len() == r.len() && l.keys().all(|element| r.contains_key(element))
+        }
+        (Value::CaseResult(l), Value::CaseResult(r)) => equal(&l.result, &r.result, comparing),
+        _ => false,
+    }
+}
+
+fn equal_items(left: &[Value], right: &[Value], comparing: &mut Vec<(usize, usize)>) -> bool {
+    left.len() == right.len() && left.iter().zip(right).all(|(l, r)| equal(l, r, comparing))
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Value::Nil => write!(f, "nil"),
-            Value::Bool(b) => write!(f, "{}", b),
-            Value::Int(n) => write!(f, "{}", n),
-            Value::Double(n) => {
-                // Always show at least one decimal place for doubles
-                if n.fract() == 0.0 {
-                    write!(f, "{:.1}", n)
-                } else {
-                    write!(f, "{}", n)
-                }
-            },
-            Value::String(s) => write!(f, "{}", s),
-            Value::List(l) => {
-                write!(f, "[")?;
-                for (i, item) in l.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{}", item)?;
-                }
-                write!(f, "]")
-            },
-            Value::Dict(d) => {
-                write!(f, "{{")?;
-                let mut first = true;
-                for (key, value) in d.iter() {
-                    if !first {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "\"{}\": {}", key, value)?;
-                    first = false;
-                }
-                write!(f, "}}")
-            },
-            Value::CaseResult(case_result) => write!(f, "<case_result: {}>", case_result.result),
-            Value::Tuple(t) => {
-                write!(f, "(")?;
-                for (i, item) in t.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{}", item)?;
-                }
-                // Always show trailing comma for single-element tuples to distinguish from grouping
-                if t.len() == 1 {
-                    write!(f, ",")?;
+        write_value(f, self, &mut Vec::new())
+    }
+}
+
+/// Write a value, showing a container that contains itself as `[...]` or `{...}`
+fn write_value(f: &mut fmt::Formatter, value: &Value, open: &mut Vec<usize>) -> fmt::Result {
+    if let Some(address) = value.container_address() {
+        if open.contains(&address) {
+            return match value {
+                Value::Dict(_) => write!(f, "{{...}}"),
+                _ => write!(f, "[...]"),
+            };
+        }
+        open.push(address);
+        let result = match value {
+            Value::List(list) => write_items(f, "[", &list.borrow(), "]", open),
+            Value::Dict(dict) => write_entries(f, &dict.borrow(), open),
+            _ => Ok(()),
+        };
+        open.pop();
+        return result;
+    }
+
+    match value {
+        Value::Nil => write!(f, "nil"),
+        Value::Bool(b) => write!(f, "{}", b),
+        Value::Int(n) => write!(f, "{}", n),
+        Value::BigInt(n) => write!(f, "{}", n),
+        Value::Double(n) => {
+            // Always show at least one decimal place for doubles
+            if n.fract() == 0.0 {
+                write!(f, "{:.1}", n)
+            } else {
+                write!(f, "{}", n)
+            }
+        },
+        Value::String(s) => write!(f, "{}", s),
+        Value::CaseResult(case_result) => {
+            write!(f, "<case_result: ")?;
+            write_value(f, &case_result.result, open)?;
+            write!(f, ">")
+        },
+        // Always show trailing comma for single-element tuples to distinguish from grouping
+        Value::Tuple(t) if t.len() == 1 => write_items(f, "(", t, ",)", open),
+        Value::Tuple(t) => write_items(f, "(", t, ")", open),
+        // `{}` is an empty dictionary
+        Value::Set(s) if s.borrow().is_empty() => write!(f, "set()"),
+        Value::Set(s) => {
+            write!(f, "{{")?;
+            for (i, element) in s.borrow().keys().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
                 }
-                write!(f, ")")
-            },
+                write!(f, "{}", element)?;
+            }
+            write!(f, "}}")
+        }
+        Value::List(_) | Value::Dict(_) => unreachable!("containers are written above"),
+    }
+}
+
+fn write_entries(f: &mut fmt::Formatter, entries: &OrderedMap<Key, Value>, open: &mut Vec<usize>) -> fmt::Result {
+    write!(f, "{{")?;
+    for (i, (key, value)) in entries.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: ", key)?;
+        write_value(f, value, open)?;
+    }
+    write!(f, "}}")
+}
+
+fn write_items(f: &mut fmt::Formatter, open_with: &str, items: &[Value], close_with: &str, open: &mut Vec<usize>) -> fmt::Result {
+    write!(f, "{}", open_with)?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
         }
+        write_value(f, item, open)?;
     }
-}
\ No newline at end of file
+    write!(f, "{}", close_with)
+}
@@ -0,0 +1,478 @@
+use crate::bytecode::{Chunk, Instruction};
+use crate::error::{BccError, Span, StackFrame};
+use crate::error_codes;
+use crate::evaluator::{Evaluator, Method, BUILTIN_NAMES, DIVMOD_KEYWORDS};
+use crate::ordered_map::OrderedMap;
+use crate::value::{CaseResult, Value};
+
+/// Stack-based virtual machine running bytecode produced by the `Compiler`.
+///
+/// Operators, built-ins and diagnostics are shared with the `Evaluator`, so a
+/// program behaves the same on either backend. As in the `Environment`, new names
+/// always live in the global scope, so each variable is a slot indexed by its name.
+pub struct Vm {
+    evaluator: Evaluator,
+    stack: Vec<Value>,
+    variables: Vec<Option<Value>>,
+    calls: Vec<ActiveCall>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Builtin {
+    Print,
+    Len,
+    Type,
+    Case,
+    Divmod,
+    Set,
+    Method(Method),
+}
+
+/// Progress of a case() call through its condition-result pairs
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CaseState {
+    Searching,
+    Matched,
+    Done,
+}
+
+/// A call whose arguments are being evaluated. Its frame is only recorded on errors the call raises itself
+struct ActiveCall {
+    site: u32,
+    builtin: Builtin,
+    frame: Option<StackFrame>,
+    args: Vec<Value>,
+    case: CaseState,
+    round_mode: String,
+    /// The list or dictionary a method is called on
+    receiver: Option<Value>,
+}
+
+impl ActiveCall {
+    fn new(site: u32, builtin: Builtin, frame: Option<StackFrame>, receiver: Option<Value>) -> Self {
+        Self {
+            site,
+            builtin,
+            frame,
+            args: Vec::new(),
+            case: CaseState::Searching,
+            round_mode: "down".to_string(),
+            receiver,
+        }
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self {
+            evaluator: Evaluator::new(),
+            stack: Vec::new(),
+            variables: Vec::new(),
+            calls: Vec::new(),
+        }
+    }
+
+    pub fn run(&mut self, chunk: &Chunk) -> Result<(), BccError> {
+        self.stack.clear();
+        self.calls.clear();
+        self.variables = chunk
+            .names
+            .iter()
+            .map(|name| BUILTIN_NAMES.contains(&name.as_str()).then(|| Value::String(format!("__builtin_{}__", name))))
+            .collect();
+
+        self.execute(chunk)
+    }
+
+    fn execute(&mut self, chunk: &Chunk) -> Result<(), BccError> {
+        let mut ip = 0;
+        while let Some(&instruction) = chunk.code.get(ip) {
+            ip += 1;
+            match instruction {
+                Instruction::Constant(index) => self.stack.push(chunk.constants[index as usize].clone()),
+                Instruction::Pop => {
+                    self.pop()?;
+                }
+                Instruction::Swap => {
+                    let length = self.stack.len();
+                    if length < 2 {
+                        return Err(Self::malformed("the value stack underflowed"));
+                    }
+                    self.stack.swap(length - 1, length - 2);
+                }
+                Instruction::GetVariable { name, span } => {
+                    let value = self.get(name).ok_or_else(|| {
+                        Evaluator::undefined_variable_error(chunk.name(name), chunk.span(span), &self.visible_names(chunk))
+                    })?;
+                    self.stack.push(value);
+                }
+                Instruction::SetVariable { name } => {
+                    let value = self.peek()?.clone();
+                    self.assign(name, value);
+                }
+                Instruction::Unpack(site) => {
+                    let site = &chunk.unpacks[site as usize];
+                    let mut bound = Vec::new();
+                    Evaluator::destructure(&site.targets, self.peek()?, chunk.span(site.span), chunk.span(site.value), &mut bound)?;
+                    for (&name, value) in site.names.iter().zip(bound) {
+                        self.assign(name, value);
+                    }
+                }
+                Instruction::Binary { operator, span, left, right } => {
+                    let right_value = self.pop()?;
+                    let left_value = self.pop()?;
+                    let result = self.evaluator.labeled_binary_op(&operator, left_value, right_value, chunk.span(span), chunk.span(left), chunk.span(right))?;
+                    self.stack.push(result);
+                }
+                Instruction::ChainCompare { operator, span, left, right } => {
+                    let right_value = self.pop()?;
+                    let left_value = self.pop()?;
+                    let result = self.evaluator.labeled_binary_op(&operator, left_value, right_value.clone(), chunk.span(span), chunk.span(left), chunk.span(right))?;
+                    self.stack.push(right_value);
+                    self.stack.push(result);
+                }
+                Instruction::Unary { operator, span, operand } => {
+                    let value = self.pop()?;
+                    let operand_type = value.type_name();
+                    let result = self.evaluator.evaluate_unary_op(&operator, value, chunk.span(span)).map_err(|error| {
+                        error.with_label(chunk.span(operand).clone(), format!("this has type {}", operand_type))
+                    })?;
+                    self.stack.push(result);
+                }
+                Instruction::Jump(target) => ip = target as usize,
+                Instruction::JumpIfFalse(target) => {
+                    if !self.pop()?.is_truthy() {
+                        ip = target as usize;
+                    }
+                }
+                Instruction::JumpIfTrue(target) => {
+                    if self.pop()?.is_truthy() {
+                        ip = target as usize;
+                    }
+                }
+                Instruction::JumpIfTrueOrPop(target) => {
+                    if self.peek()?.is_truthy() {
+                        ip = target as usize;
+                    } else {
+                        self.pop()?;
+                    }
+                }
+                Instruction::JumpIfFalseOrPop(target) => {
+                    if !self.peek()?.is_truthy() {
+                        ip = target as usize;
+                    } else {
+                        self.pop()?;
+                    }
+                }
+                Instruction::JumpIfNotNilOrPop(target) => {
+                    if !matches!(self.peek()?, Value::Nil) {
+                        ip = target as usize;
+                    } else {
+                        self.pop()?;
+                    }
+                }
+                Instruction::MatchPattern(site) => {
+                    let site = &chunk.matches[site as usize];
+                    let mut bound = Vec::new();
+                    let matched = Evaluator::match_pattern(&site.pattern, self.peek()?, &mut bound);
+                    if matched {
+                        for (&name, value) in site.names.iter().zip(bound) {
+                            self.assign(name, value);
+                        }
+                    }
+                    self.stack.push(Value::Bool(matched));
+                }
+                Instruction::NoMatch { span } => {
+                    return Err(Evaluator::no_matching_arm_error(self.peek()?, chunk.span(span)));
+                }
+                Instruction::JumpIfNil(target) => {
+                    if matches!(self.peek()?, Value::Nil) {
+                        ip = target as usize;
+                    }
+                }
+                Instruction::BuildList(count) => {
+                    let elements = self.pop_many(count)?;
+                    self.stack.push(Value::list(elements));
+                }
+                Instruction::BuildTuple(count) => {
+                    let elements = self.pop_many(count)?;
+                    self.stack.push(Value::Tuple(elements));
+                }
+                Instruction::NewDict => self.stack.push(Value::dict(OrderedMap::new())),
+                Instruction::DictInsert { span, key } => {
+                    let value = self.pop()?;
+                    let key = Evaluator::dictionary_key(self.pop()?, chunk.span(span), chunk.span(key))?;
+                    if let Value::Dict(dict) = self.peek()? {
+                        dict.borrow_mut().insert(key, value);
+                    }
+                }
+                Instruction::NewSet => self.stack.push(Value::set(OrderedMap::new())),
+                Instruction::SetInsert { span, element } => {
+                    let element = Evaluator::set_element(self.pop()?, chunk.span(span), chunk.span(element))?;
+                    if let Value::Set(set) = self.peek()? {
+                        set.borrow_mut().insert(element, ());
+                    }
+                }
+                Instruction::GetProperty { name, span, object } => {
+                    let value = self.pop()?;
+                    let property = Evaluator::property(value, chunk.name(name), chunk.span(span), chunk.span(object))?;
+                    self.stack.push(property);
+                }
+                Instruction::Call(site) => {
+                    let callee = self.pop()?;
+                    self.start_call(chunk, site, callee)?;
+                }
+                Instruction::CallMethod { name, span, object, site } => {
+                    let value = self.pop()?;
+                    let name = chunk.name(name);
+                    match Evaluator::method(&value, name, chunk.span(span), chunk.span(object))? {
+                        Some(method) => {
+                            let frame = StackFrame {
+                                function: method.name().to_string(),
+                                call_site: chunk.span(chunk.calls[site as usize].span).clone(),
+                            };
+                            if let Err(error) = Self::check_method_call(chunk, site, method) {
+                                return Err(error.with_frame(frame));
+                            }
+                            self.calls.push(ActiveCall::new(site, Builtin::Method(method), Some(frame), Some(value)));
+                        }
+                        None => {
+                            let callee = Evaluator::property(value, name, chunk.span(span), chunk.span(object))?;
+                            self.start_call(chunk, site, callee)?;
+                        }
+                    }
+                }
+                Instruction::Argument { index, skip } => {
+                    let call = self.active_call()?;
+                    let evaluate = match call.builtin {
+                        // Conditions are evaluated until one matches, then only its result
+                        Builtin::Case if index % 2 == 0 => call.case == CaseState::Searching,
+                        Builtin::Case => call.case == CaseState::Matched,
+                        _ => true,
+                    };
+                    if !evaluate {
+                        ip = skip as usize;
+                    }
+                }
+                Instruction::ArgumentDone(index) => {
+                    let value = self.pop()?;
+                    let call = self.active_call()?;
+                    match call.builtin {
+                        Builtin::Print => println!("{}", value),
+                        Builtin::Case if index % 2 == 0 => {
+                            if value.is_truthy() {
+                                call.case = CaseState::Matched;
+                            }
+                        }
+                        Builtin::Case => {
+                            call.args.push(value);
+                            call.case = CaseState::Done;
+                        }
+                        _ => call.args.push(value),
+                    }
+                }
+                Instruction::Keyword(index) => {
+                    let call = self.active_call()?;
+                    let site = &chunk.calls[call.site as usize];
+                    let keyword = &site.keywords[index as usize];
+                    let name = chunk.name(keyword.name);
+                    if !DIVMOD_KEYWORDS.contains(&name) {
+                        let error = Evaluator::unknown_keyword_error(name, chunk.span(keyword.span), chunk.span(site.span));
+                        return Err(self.call_error(error));
+                    }
+                }
+                Instruction::KeywordDone(index) => {
+                    let value = self.pop()?;
+                    let call = self.active_call()?;
+                    let site = &chunk.calls[call.site as usize];
+                    let keyword = &site.keywords[index as usize];
+                    match Evaluator::round_mode(value, chunk.span(site.span), chunk.span(keyword.value)) {
+                        Ok(round_mode) => call.round_mode = round_mode,
+                        Err(error) => return Err(self.call_error(error)),
+                    }
+                }
+                Instruction::CallEnd => {
+                    let result = self.finish_call(chunk).map_err(|error| self.call_error(error))?;
+                    self.calls.pop();
+                    self.stack.push(result);
+                }
+                Instruction::AssertCompare { operator, span, skip } => {
+                    let [.., left, right] = self.stack.as_slice() else {
+                        return Err(Self::malformed("the value stack underflowed"));
+                    };
+                    let result = self.evaluator.evaluate_binary_op(&operator, left.clone(), right.clone(), chunk.span(span))?;
+                    if result.is_truthy() {
+                        self.pop_many(2)?;
+                        ip = skip as usize;
+                    }
+                }
+                Instruction::AssertFail(site) => {
+                    let site = &chunk.asserts[site as usize];
+                    let message = if site.message { Some(self.pop()?) } else { None };
+                    let mut error = Evaluator::assertion_failed(message, chunk.span(site.span));
+                    if let Some((left, right)) = site.operands {
+                        let right_value = self.pop()?;
+                        let left_value = self.pop()?;
+                        error = error
+                            .with_label(chunk.span(left).clone(), format!("left side is {}", Evaluator::describe_value(&left_value)))
+                            .with_label(chunk.span(right).clone(), format!("right side is {}", Evaluator::describe_value(&right_value)));
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn start_call(&mut self, chunk: &Chunk, site: u32, callee: Value) -> Result<(), BccError> {
+        let frame = Evaluator::builtin_name(&callee).map(|function| StackFrame {
+            function,
+            call_site: chunk.span(chunk.calls[site as usize].span).clone(),
+        });
+        match Self::check_call(chunk, site, &callee) {
+            Ok(builtin) => {
+                self.calls.push(ActiveCall::new(site, builtin, frame, None));
+                Ok(())
+            }
+            Err(error) => Err(match frame {
+                Some(frame) => error.with_frame(frame),
+                None => error,
+            }),
+        }
+    }
+
+    /// Record the innermost call's frame on an error the call itself raised. Errors from
+    /// its arguments never pass through here, as the call was not entered yet
+    fn call_error(&mut self, error: BccError) -> BccError {
+        match self.calls.pop().and_then(|call| call.frame) {
+            Some(frame) => error.with_frame(frame),
+            None => error,
+        }
+    }
+
+    /// Validate a callee and its argument counts before any argument is evaluated
+    fn check_call(chunk: &Chunk, site: u32, callee: &Value) -> Result<Builtin, BccError> {
+        let site = &chunk.calls[site as usize];
+        let span = chunk.span(site.span);
+        let Value::String(name) = callee else {
+            return Err(Evaluator::not_callable_error(span, site.with_keywords));
+        };
+        if site.with_keywords && name == "__builtin_divmod__" {
+            if site.arguments.len() != 2 {
+                return Err(Evaluator::divmod_positional_count_error(site.arguments.len(), span));
+            }
+            return Ok(Builtin::Divmod);
+        }
+        if let Some(keyword) = site.keywords.first() {
+            return Err(Evaluator::keywords_not_accepted_error(name, chunk.span(keyword.span), span));
+        }
+        Evaluator::check_arity(name, site.arguments.len(), span)?;
+        match name.as_str() {
+            "__builtin_print__" => Ok(Builtin::Print),
+            "__builtin_len__" => Ok(Builtin::Len),
+            "__builtin_type__" => Ok(Builtin::Type),
+            "__builtin_case__" => Ok(Builtin::Case),
+            "__builtin_divmod__" => Ok(Builtin::Divmod),
+            "__builtin_set__" => Ok(Builtin::Set),
+            _ => Err(Evaluator::not_callable_error(span, false)),
+        }
+    }
+
+    fn check_method_call(chunk: &Chunk, site: u32, method: Method) -> Result<(), BccError> {
+        let site = &chunk.calls[site as usize];
+        let span = chunk.span(site.span);
+        if let Some(keyword) = site.keywords.first() {
+            return Err(Evaluator::keywords_not_accepted_error(method.name(), chunk.span(keyword.span), span));
+        }
+        method.check_arity(site.arguments.len(), span)
+    }
+
+    fn finish_call(&mut self, chunk: &Chunk) -> Result<Value, BccError> {
+        let call = self.active_call()?;
+        let site = &chunk.calls[call.site as usize];
+        let span = chunk.span(site.span);
+        let mut args = std::mem::take(&mut call.args);
+        match call.builtin {
+            Builtin::Print => Ok(Value::Nil),
+            Builtin::Len | Builtin::Type if args.is_empty() => Err(Self::malformed("a call ran without its argument")),
+            Builtin::Len => Evaluator::builtin_len(args.remove(0), span, chunk.span(site.arguments[0])),
+            Builtin::Type => Ok(Value::String(args[0].type_name().to_string())),
+            Builtin::Case => Ok(Value::CaseResult(CaseResult {
+                result: Box::new(args.pop().unwrap_or(Value::Nil)),
+            })),
+            Builtin::Divmod => {
+                let round_mode = std::mem::take(&mut call.round_mode);
+                let divisor = args.pop().unwrap_or(Value::Nil);
+                let dividend = args.pop().unwrap_or(Value::Nil);
+                self.evaluator.builtin_divmod(dividend, divisor, round_mode, span)
+            }
+            Builtin::Set => match args.pop() {
+                Some(arg) => Evaluator::builtin_set(arg, span, chunk.span(site.arguments[0])),
+                None => Ok(Value::set(OrderedMap::new())),
+            },
+            Builtin::Method(method) => {
+                let receiver = call.receiver.take().ok_or_else(|| Self::malformed("a method was called without a receiver"))?;
+                let arg_spans: Vec<Span> = site.arguments.iter().map(|&arg| chunk.span(arg).clone()).collect();
+                Evaluator::call_method(receiver, method, args, span, &arg_spans)
+            }
+        }
+    }
+
+    fn active_call(&mut self) -> Result<&mut ActiveCall, BccError> {
+        self.calls.last_mut().ok_or_else(|| Self::malformed("an argument instruction ran outside a call"))
+    }
+
+    fn get(&self, name: u32) -> Option<Value> {
+        self.variables.get(name as usize).cloned().flatten()
+    }
+
+    /// Update the variable, creating it if needed; like `Environment::assign`,
+    /// every name lives in the global scope
+    fn assign(&mut self, name: u32, value: Value) {
+        if let Some(slot) = self.variables.get_mut(name as usize) {
+            *slot = Some(value);
+        }
+    }
+
+    fn visible_names(&self, chunk: &Chunk) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .variables
+            .iter()
+            .zip(&chunk.names)
+            .filter(|(value, _)| value.is_some())
+            .map(|(_, name)| name.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn peek(&self) -> Result<&Value, BccError> {
+        self.stack.last().ok_or_else(|| Self::malformed("the value stack underflowed"))
+    }
+
+    fn pop(&mut self) -> Result<Value, BccError> {
+        self.stack.pop().ok_or_else(|| Self::malformed("the value stack underflowed"))
+    }
+
+    fn pop_many(&mut self, count: u32) -> Result<Vec<Value>, BccError> {
+        let start = self.stack.len().checked_sub(count as usize).ok_or_else(|| Self::malformed("the value stack underflowed"))?;
+        Ok(self.stack.split_off(start))
+    }
+
+    /// Bytecode that passed validation can still misuse the stack; report it rather than abort
+    fn malformed(problem: &str) -> BccError {
+        BccError::runtime_error_with_help(
+            Span::single(0),
+            format!("Malformed bytecode: {}", problem),
+            "The compiled script is corrupt; recompile it from source.".to_string(),
+        )
+        .with_code(error_codes::MALFORMED_BYTECODE)
+    }
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
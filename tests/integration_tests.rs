@@ -170,8 +170,13 @@ fn run_single_test(test: &TestCase) -> TestResult {
     }
 }
 
-/// Parse input and return result
+/// Parse input and return result, keeping only the first error
 fn parse_input(input: &str) -> Result<bcc::ast::Program, BccError> {
+    parse_input_all_errors(input).map_err(|mut errors| errors.remove(0))
+}
+
+/// Parse input and return every lexical or syntax error found
+fn parse_input_all_errors(input: &str) -> Result<bcc::ast::Program, Vec<BccError>> {
     let mut lexer = Lexer::new(input.to_string());
     let tokens = lexer.scan_tokens()?;
     let mut parser = Parser::new(tokens);
@@ -362,11 +367,17 @@ fn create_literal_tests() -> TestSuite {
     suite.add_test(TestCase::should_succeed("string_literal", "\"hello\""));
     suite.add_test(TestCase::should_succeed("boolean_true", "true"));
     suite.add_test(TestCase::should_succeed("boolean_false", "false"));
+    suite.add_test(TestCase::should_succeed("leading_dot", ".42"));
+    suite.add_test(TestCase::should_succeed("hex_literal", "0xFF"));
+    suite.add_test(TestCase::should_succeed("underscored_literal", "1_000_000"));
+    suite.add_test(TestCase::should_succeed("scientific_literal", "1e-9"));
 
     // Invalid number formats
     suite.add_test(TestCase::should_fail("multiple_dots", "3.14.159"));
     suite.add_test(TestCase::should_fail("trailing_dot", "42."));
-    suite.add_test(TestCase::should_fail("leading_dot", ".42"));
+    suite.add_test(TestCase::should_fail("hex_without_digits", "0x"));
+    suite.add_test(TestCase::should_fail("doubled_underscore", "1__000"));
+    suite.add_test(TestCase::should_fail("exponent_without_digits", "1e+"));
 
     // Unterminated strings
     suite.add_test(TestCase::should_fail("unterminated_string", "\"hello"));
@@ -422,6 +433,17 @@ fn create_mixed_construct_tests() -> TestSuite {
     suite
 }
 
+fn create_assert_tests() -> TestSuite {
+    let mut suite = TestSuite::new("Assert Tests");
+
+    suite.add_test(TestCase::should_succeed("assert_condition", "assert x == 1"));
+    suite.add_test(TestCase::should_succeed("assert_with_message", "assert x < 10, \"x too big\""));
+    suite.add_test(TestCase::should_fail_with_message("assert_missing_condition", "assert", "Expected condition after 'assert'"));
+    suite.add_test(TestCase::should_fail("assert_missing_message", "assert x == 1,"));
+
+    suite
+}
+
 fn create_positive_tests() -> TestSuite {
     let mut suite = TestSuite::new("Positive Tests");
 
@@ -457,6 +479,7 @@ fn comprehensive_parser_tests() {
         create_function_call_tests(),
         create_assignment_tests(),
         create_mixed_construct_tests(),
+        create_assert_tests(),
         create_positive_tests(),
     ];
 
@@ -474,4 +497,868 @@ fn comprehensive_parser_tests() {
         // Don't panic here - let the test framework handle it
         // We'll rely on cargo test's normal failure reporting
     }
-}
\ No newline at end of file
+}
+
+// ============================================================================
+// Runtime Diagnostics Tests
+// ============================================================================
+
+/// Lex, parse and evaluate a program, returning the first error
+fn run_input(input: &str) -> Result<(), BccError> {
+    let program = parse_input(input)?;
+    let mut evaluator = bcc::Evaluator::new();
+    evaluator.evaluate_program(&program)
+}
+
+#[test]
+fn assert_failure_labels_both_operands() {
+    assert!(run_input("x = 3\nassert x > 1").is_ok());
+
+    let error = run_input("x = 3\nassert x + 1 == 6, \"off by two\"").unwrap_err();
+    assert_eq!(error.message, "Assertion failed: off by two");
+
+    let labels: Vec<&str> = error.labels.iter().map(|l| l.message.as_str()).collect();
+    assert_eq!(labels, vec!["left side is 4 (int)", "right side is 6 (int)"]);
+}
+
+#[test]
+fn unclosed_delimiters_label_the_opener() {
+    let error = parse_input("x = [1, 2").unwrap_err();
+    assert_eq!(error.labels.len(), 1);
+    assert_eq!(error.labels[0].message, "list opened here");
+    assert_eq!(error.labels[0].span.start, 4);
+
+    let error = parse_input("{ x = 1").unwrap_err();
+    assert_eq!(error.labels[0].message, "block opened here");
+}
+
+#[test]
+fn type_errors_label_operand_types() {
+    let error = run_input("x = \"a\" + 1").unwrap_err();
+    let labels: Vec<&str> = error.labels.iter().map(|l| l.message.as_str()).collect();
+    assert_eq!(labels, vec!["this has type string", "this has type int"]);
+}
+
+#[test]
+fn division_by_zero_labels_only_the_divisor() {
+    for run in [run_input, run_input_on_vm] {
+        let error = run("x = 0\ny = 10 / x").unwrap_err();
+        let labels: Vec<(usize, &str)> = error.labels.iter().map(|l| (l.span.start, l.message.as_str())).collect();
+        assert_eq!(labels, vec![(15, "divisor is zero")]);
+    }
+}
+
+#[test]
+fn runtime_errors_carry_call_trace_innermost_first() {
+    let frames = |error: BccError| -> Vec<(String, usize)> {
+        error.trace.iter().map(|frame| (frame.function.clone(), frame.call_site.start)).collect()
+    };
+    for run in [run_input, run_input_on_vm] {
+        // Only the call that raised the error was entered; print was still evaluating its argument
+        assert_eq!(frames(run("x = 1\nprint(len(5))").unwrap_err()), vec![("len".to_string(), 12)]);
+        assert_eq!(frames(run("x = [1]\nx.append(len(5))").unwrap_err()), vec![("len".to_string(), 17)]);
+
+        // Errors raised inside an argument leave the call out of the trace
+        assert!(run("print(1 / 0)").unwrap_err().trace.is_empty());
+        assert!(run("print(len(1 / 0))").unwrap_err().trace.is_empty());
+        assert!(run("divmod(7, 2, round_mode=1 / 0)").unwrap_err().trace.is_empty());
+
+        // Errors outside any call have no trace
+        assert!(run("x = 1 / 0").unwrap_err().trace.is_empty());
+    }
+}
+
+#[test]
+fn undefined_names_suggest_close_matches() {
+    let help = |input: &str| run_input(input).unwrap_err().help;
+    assert_eq!(help("pritn(1)").as_deref(), Some("Did you mean 'print'?"));
+    assert_eq!(help("count = 1\n{ print(conut) }").as_deref(), Some("Did you mean 'count'?"));
+    assert!(help("divmod(7, 2, round_mod=\"up\")").unwrap().starts_with("Did you mean 'round_mode'?"));
+    assert!(help("x = case(true, 1).reslt").unwrap().starts_with("Did you mean 'result'?"));
+    assert_eq!(help("print(zzz)"), None);
+
+    assert_eq!(bcc::suggest::edit_distance("pritn", "print"), 1);
+    assert_eq!(bcc::suggest::edit_distance("kitten", "sitting"), 3);
+}
+
+#[test]
+fn diagnostics_carry_documented_codes() {
+    let error = parse_input("\"hello").unwrap_err();
+    assert_eq!(error.code, Some("E0102"));
+
+    let error = parse_input("(1 + 2").unwrap_err();
+    assert_eq!(error.code, Some(bcc::error_codes::UNCLOSED_DELIMITER));
+
+    let error = run_input("x = 1 / 0").unwrap_err();
+    assert_eq!(error.code, Some(bcc::error_codes::DIVISION_BY_ZERO));
+
+    let codes = bcc::error_codes::ERROR_CODES;
+    for (i, info) in codes.iter().enumerate() {
+        assert!(codes[i + 1..].iter().all(|other| other.code != info.code), "duplicate code {}", info.code);
+        assert!(bcc::error_codes::lookup(&info.code.to_lowercase()).is_some());
+    }
+}
+
+#[test]
+fn json_diagnostics_include_location() {
+    let source = "x = 1\ny = \"é\" + )";
+    let error = parse_input(source).unwrap_err();
+    let json = error.to_json(source, Some("demo.bcc"), &bcc::LineIndex::new(source));
+
+    assert!(json.starts_with("{\"kind\":\"parse_error\",\"code\":\"E0207\""));
+    assert!(json.contains("\"file\":\"demo.bcc\""));
+    assert!(json.contains("\"span\":{\"start\":15,\"end\":16},\"line\":2,\"column\":9"));
+    assert!(json.contains("\"labels\":[]"));
+    assert!(!json.contains('\n'));
+}
+
+#[test]
+fn diagnostics_handle_non_ascii_source() {
+    let source = "x = 1 € 2";
+    let error = parse_input(source).unwrap_err();
+    assert_eq!(error.message, "Unexpected character: '€'");
+    assert_eq!((error.span.start, error.span.end), (6, 9));
+    let json = error.to_json(source, None, &bcc::LineIndex::new(source));
+    assert!(json.contains("\"line\":1,\"column\":7,\"end_line\":1,\"end_column\":8"));
+    error.report(source, None);
+
+    let source = "x = \"ééé\"\nprint(x,";
+    let error = parse_input(source).unwrap_err();
+    assert_eq!(error.message, "Unexpected end of input after ',' in function call");
+    let json = error.to_json(source, None, &bcc::LineIndex::new(source));
+    assert!(json.contains("\"line\":2,\"column\":9"));
+    error.report(source, None);
+
+    // Offsets inside a character snap back to its start
+    let index = bcc::LineIndex::new("é=1");
+    assert_eq!(index.line_col("é=1", 1).column, 1);
+}
+
+// ============================================================================
+// Error Recovery Tests
+// ============================================================================
+
+fn error_messages(input: &str) -> Vec<String> {
+    match parse_input_all_errors(input) {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors.into_iter().map(|e| e.message).collect(),
+    }
+}
+
+#[test]
+fn parser_reports_errors_after_semicolons() {
+    let messages = error_messages("x = 1 +; y = (2; z = 3");
+    assert_eq!(messages, vec!["Expected expression after '+'", "Expected ')' after expression"]);
+}
+
+#[test]
+fn parser_recovers_at_statement_keywords() {
+    let messages = error_messages("x = * 2\nif (x) { y = 1 }\nwhile (true { z = 2 }\nassert x ==");
+    assert_eq!(
+        messages,
+        vec![
+            "Expected expression, found '*'",
+            "Expected ')' after while condition",
+            "Expected expression after '=='",
+        ]
+    );
+}
+
+#[test]
+fn missing_operands_leave_the_next_statement_intact() {
+    // The keyword on the next line starts a statement, so its own errors are reported too
+    let expected = ["Expected expression after '+'", "Expected expression, found ')'"];
+    assert_eq!(error_messages("x = 1 +\nif (true) { y = ) }"), expected);
+    assert_eq!(error_messages("x = 1 +\nwhile (true) { y = ) }"), expected);
+    assert_eq!(error_messages("x = 1 +;\nif (true) { y = ) }"), expected);
+}
+
+#[test]
+fn parser_recovers_inside_blocks() {
+    // The inner errors must not swallow the block's closing brace
+    let messages = error_messages("{ a = ; b = 1 }\n{ c = }\nd = 2 }");
+    assert_eq!(
+        messages,
+        vec![
+            "Expected expression, found ';'",
+            "Expected expression, found '}'",
+            "Expected expression, found '}'",
+        ]
+    );
+}
+
+#[test]
+fn lexer_reports_every_bad_character() {
+    let errors = parse_input_all_errors("x = 1 @ 2\ny = $\nz = \"open").unwrap_err();
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(messages, vec!["Unexpected character: '@'", "Unexpected character: '$'", "Unterminated string"]);
+}
+
+#[test]
+fn line_index_maps_offsets_to_lines_and_char_columns() {
+    let source = "a = 1\nb = \"é\" + c\n\nd";
+    let index = bcc::LineIndex::new(source);
+
+    assert_eq!(index.line_count(), 4);
+    assert_eq!(index.line_col(source, 0), bcc::LineCol { line: 1, column: 1 });
+    assert_eq!(index.line_col(source, 5), bcc::LineCol { line: 1, column: 6 });
+    assert_eq!(index.line_col(source, 6), bcc::LineCol { line: 2, column: 1 });
+    // 'é' is two bytes but one column
+    assert_eq!(index.line_col(source, source.find('+').unwrap()), bcc::LineCol { line: 2, column: 9 });
+    assert_eq!(index.line_col(source, source.len()), bcc::LineCol { line: 4, column: 2 });
+    assert_eq!(index.line_start(3), Some(source.find("\n\n").unwrap() + 1));
+}
+
+fn resolve_input(input: &str) -> bcc::Resolution {
+    let program = parse_input(input).expect("input should parse");
+    bcc::Resolver::new().resolve(&program)
+}
+
+fn warning_messages(input: &str) -> Vec<String> {
+    resolve_input(input).warnings.into_iter().map(|warning| warning.message).collect()
+}
+
+#[test]
+fn resolver_reports_definitely_undefined_names() {
+    let source = "x = 1\nprint(y)";
+    let resolution = resolve_input(source);
+    assert_eq!(resolution.errors.len(), 1);
+    assert_eq!(resolution.errors[0].message, "Undefined variable 'y'");
+    // Found before the program runs, so it is not a runtime error
+    let json = resolution.errors[0].to_json(source, None, &bcc::LineIndex::new(source));
+    assert!(json.starts_with("{\"kind\":\"resolve_error\",\"code\":\"E0301\""), "{}", json);
+
+    // New names outlive the block that created them
+    assert!(resolve_input("{ z = 1 }\nprint(z)").errors.is_empty());
+
+    // A conditional or later-iteration assignment might have run, so these are left to runtime
+    assert!(resolve_input("if (true) w = 1\nprint(w)").errors.is_empty());
+    assert!(resolve_input("for (i = 0; i < 2; i = i + 1) if (i > 0) print(v) else v = i").errors.is_empty());
+}
+
+#[test]
+fn resolver_warns_about_unused_and_dead_assignments() {
+    assert_eq!(warning_messages("{ t = 1 }"), vec!["Unused variable 't'"]);
+    assert_eq!(
+        warning_messages("x = 1\nx = 2\nprint(x)"),
+        vec!["Value assigned to 'x' is never read"]
+    );
+    assert_eq!(
+        warning_messages("_ = 3\nprint(_)"),
+        vec!["'_' is read here, but it is meant for values that are ignored"]
+    );
+
+    // Conditional overwrites, loop-carried values and underscore names are fine
+    assert!(warning_messages("x = 1\nif (len(\"a\") > 0) x = 2\nprint(x)").is_empty());
+    assert!(warning_messages("i = 0\nwhile (i < 3) { i = i + 1 }").is_empty());
+    assert!(warning_messages("_unused = 1\na, _ = (1, 2)\nprint(a)").is_empty());
+
+    let warning = &resolve_input("{ t = 1 }").warnings[0];
+    assert!(matches!(warning.kind, bcc::error::ErrorKind::Warning));
+    assert_eq!((warning.span.start, warning.span.end), (2, 3));
+    assert_eq!(warning.code, Some(bcc::error_codes::UNUSED_VARIABLE));
+}
+
+#[test]
+fn resolver_computes_scope_depths() {
+    let source = "x = 1\n{ y = 2\n{ print(x, y) } }";
+    let resolution = resolve_input(source);
+    let depth_at = |needle: &str| {
+        let start = source.rfind(needle).unwrap();
+        resolution.depth(&bcc::Span::single(start))
+    };
+    assert_eq!(depth_at("x, y"), Some(2));
+    // New names are created in the global scope, even inside a block
+    assert_eq!(depth_at("y)"), Some(2));
+    assert_eq!(depth_at("y = 2"), Some(1));
+
+    // Only assigned on one path, so whether the read finds a variable is not known statically
+    let source = "if (true) a = 1\n{ print(a) }";
+    let resolution = resolve_input(source);
+    assert_eq!(resolution.depth(&bcc::Span::single(source.rfind('a').unwrap())), None);
+
+    // The evaluator reads variables at the resolved depths
+    let source = "x = 1\n{ y = 2\n{ assert x + y == 3 } }\nif (x > 5) z = 1\n{ { z = 3 } }\nassert z == 3";
+    let program = parse_input(source).unwrap();
+    let resolution = bcc::Resolver::new().resolve(&program);
+    assert!(bcc::Evaluator::new().evaluate_resolved(&program, &resolution).is_ok());
+}
+
+#[test]
+fn end_of_input_errors_render_without_panicking() {
+    for source in ["[", "x = (1 +", "if (true) {"] {
+        for error in parse_input_all_errors(source).unwrap_err() {
+            error.report(source, None);
+        }
+    }
+}
+
+fn optimize_input(input: &str) -> bcc::Program {
+    bcc::Optimizer::new().optimize(parse_input(input).expect("input should parse"))
+}
+
+#[test]
+fn optimizer_folds_constants_and_prunes_dead_branches() {
+    let program = optimize_input("x = 60 * 60 * (12 + 12)\nif (false) { print(1) }\nwhile (1 > 2) { print(2) }");
+    assert_eq!(program.statements.len(), 1);
+    match &program.statements[0] {
+        bcc::Stmt::Expression { expr: bcc::Expr::Assign { value, .. }, .. } => {
+            assert!(matches!(**value, bcc::Expr::Literal { value: bcc::Value::Int(86400), .. }));
+        }
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+
+    let program = optimize_input("if (\"yes\") print(1) else print(2)");
+    assert!(matches!(program.statements[0], bcc::Stmt::Expression { .. }));
+}
+
+#[test]
+fn optimizer_preserves_runtime_errors() {
+    for source in ["x = 1 / 0", "x = 2 * (\"a\" + 1)", "x = -\"a\"", "assert 1 + 1 == 3"] {
+        let plain = run_input(source).unwrap_err();
+        let mut evaluator = bcc::Evaluator::new();
+        let optimized = evaluator.evaluate_program(&optimize_input(source)).unwrap_err();
+        assert_eq!(plain.message, optimized.message, "{}", source);
+        assert_eq!((plain.span.start, plain.span.end), (optimized.span.start, optimized.span.end));
+        let labels = |error: &BccError| error.labels.iter().map(|l| l.message.clone()).collect::<Vec<_>>();
+        assert_eq!(labels(&plain), labels(&optimized), "{}", source);
+    }
+}
+
+// ============================================================================
+// Bytecode VM Tests
+// ============================================================================
+
+fn run_input_on_vm(input: &str) -> Result<(), BccError> {
+    let program = parse_input(input)?;
+    bcc::Vm::new().run(&bcc::Compiler::compile(&program))
+}
+
+#[test]
+fn vm_reports_the_same_errors_as_the_evaluator() {
+    let sources = [
+        "x = 1\nprint(len(5))",
+        "x = \"a\" + 1",
+        "x = -\"a\"",
+        "x = 3\nassert x + 1 == 6, \"off by two\"",
+        "assert len([]), \"empty\"",
+        "a, b, c = (1, 2)",
+        "x = {[1]: 2}",
+        "x = {(1, (2, 1.5)): 2}",
+        "x = [1] in {}",
+        "x = case(true, 1).reslt",
+        "divmod(7, 2, round_mod=\"up\")",
+        "divmod(7, 2, round_mode=1)",
+        "len(1, 2)",
+        "print(len([1], round_mode=\"up\"))",
+        "print(1, len(1 / 0))",
+        "divmod(7, 2, round_mode=\"sideways\")",
+        "count = 1\n{ print(conut) }",
+        "x = case(false, 1 / 0, true, 2, true, 1 / 0).result\nx = x / 0",
+        "x = [1]\nx.apend(2)",
+        "x = [1]\nx.append(1, 2)",
+        "x = [1]\nx.append(value=1)",
+        "x = {}\nx.update([1])",
+        "x = case(true, 1).result()",
+        "x = {1, [2]}",
+        "x = {1}\nx.remove(2)",
+        "x = {1}\nx.add({})",
+        "x = {1} | [1]",
+        "x = [1] in {1}",
+        "x = set(1)",
+        "x = 1 < 2 < \"a\"",
+        "x = 1 not in 5",
+        "x = 1 if 1 / 0 else 2",
+        "x = false ? 1 : 2 / 0",
+        "x = nil ?? 1 / 0",
+        "x = nil?.a.b",
+        "x = [1]?.apend(2)",
+        "x = match 3 { 1 => 1, n if n > 5 => n }",
+        "x = match [1, 2] { [a, b] if a > b => a, _ => 1 / 0 }",
+        "a, (b, c) = (1, [2])",
+        "first, *rest, last = [1]",
+        "a, b = (1, 2, 3)",
+    ];
+    for source in sources {
+        let tree = run_input(source).expect_err(source);
+        let vm = run_input_on_vm(source).expect_err(source);
+        assert_eq!(tree.message, vm.message, "{}", source);
+        assert_eq!(tree.help, vm.help, "{}", source);
+        assert_eq!(tree.code, vm.code, "{}", source);
+        assert_eq!(tree.span, vm.span, "{}", source);
+        let labels = |error: &BccError| error.labels.iter().map(|l| (l.span.clone(), l.message.clone())).collect::<Vec<_>>();
+        assert_eq!(labels(&tree), labels(&vm), "{}", source);
+        let trace = |error: &BccError| error.trace.iter().map(|f| (f.function.clone(), f.call_site.clone())).collect::<Vec<_>>();
+        assert_eq!(trace(&tree), trace(&vm), "{}", source);
+    }
+
+    assert!(run_input_on_vm("x = 0\nwhile (x < 10) { x = x + 1 }\nassert x == 10").is_ok());
+    // New names created in a block outlive it, as in the evaluator
+    assert!(run_input_on_vm("{ inner = 1 }\nassert inner == 1").is_ok());
+}
+
+#[test]
+fn every_sample_behaves_the_same_on_both_backends() {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let mut samples: Vec<_> = std::fs::read_dir(root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "bcc"))
+        .collect();
+    samples.sort();
+    assert!(!samples.is_empty());
+
+    for sample in samples {
+        for optimize in [false, true] {
+            let run = |backend: &str| {
+                let mut command = std::process::Command::new(env!("CARGO_BIN_EXE_bcc"));
+                command.arg(format!("--backend={}", backend)).arg(&sample);
+                if optimize {
+                    command.arg("--optimize");
+                }
+                let output = command.output().unwrap();
+                (output.stdout, output.stderr, output.status.code())
+            };
+            assert_eq!(run("tree"), run("vm"), "{} (optimize: {})", sample.display(), optimize);
+        }
+    }
+}
+
+fn compile_input(input: &str) -> bcc::CompiledScript {
+    bcc::CompiledScript {
+        filename: "input.bcc".to_string(),
+        source: input.to_string(),
+        chunk: bcc::Compiler::compile(&parse_input(input).expect("input should parse")),
+    }
+}
+
+#[test]
+fn compiled_scripts_round_trip_through_bytes() {
+    let script = compile_input("x = 1.5\n{ a, _ = divmod(7, 2, round_mode=\"up\") }\nassert a in [4, \"s\", nil], \"no\"\nprint(-x or case(true, {\"k\": x}).result)");
+    let bytes = script.to_bytes();
+    assert!(bcc::CompiledScript::is_compiled(&bytes));
+    assert_eq!(bcc::CompiledScript::from_bytes(&bytes), Ok(script));
+
+    use bcc::compiled::LoadError;
+    assert_eq!(bcc::CompiledScript::from_bytes(b"print(1)"), Err(LoadError::NotCompiled));
+    assert_eq!(bcc::CompiledScript::from_bytes(&bytes[..bytes.len() - 1]), Err(LoadError::Truncated));
+    let mut newer = bytes.clone();
+    newer[4] = newer[4].wrapping_add(1);
+    assert!(matches!(bcc::CompiledScript::from_bytes(&newer), Err(LoadError::UnsupportedVersion(_))));
+}
+
+#[test]
+fn malformed_bytecode_is_an_error_not_a_crash() {
+    let mut script = compile_input("x = 1\nprint(len(x))");
+    // Dropping the first constant leaves SetVariable and Pop nothing to work on
+    assert!(matches!(script.chunk.code[0], bcc::bytecode::Instruction::Constant(_)));
+    script.chunk.code.remove(0);
+    script.chunk.locations.remove(0);
+    let script = bcc::CompiledScript::from_bytes(&script.to_bytes()).expect("the script should still load");
+
+    let error = bcc::Vm::new().run(&script.chunk).unwrap_err();
+    assert_eq!(error.code, Some(bcc::error_codes::MALFORMED_BYTECODE));
+    assert_eq!(error.message, "Malformed bytecode: the value stack underflowed");
+}
+
+#[test]
+fn disassembly_maps_instructions_to_source_lines() {
+    let script = compile_input("x = 1\nprint(x)");
+    let listing = bcc::disassembler::disassemble(&script.chunk, &script.source, &script.filename);
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines[0], "== input.bcc ==");
+    assert_eq!(lines[1], "0000     1:5  Constant         #0 1");
+    assert_eq!(lines[2], "0001       |  SetVariable      x");
+    assert!(lines[4].starts_with("0003     2:1  GetVariable      print"), "{}", lines[4]);
+}
+
+#[test]
+fn compiled_scripts_run_like_their_source() {
+    let directory = std::env::temp_dir().join(format!("bcc-compile-test-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let source = directory.join("script.bcc");
+    let compiled = directory.join("script.bcco");
+    std::fs::write(&source, "total = 0\nfor (i = 0; i < 4; i = i + 1) total = total + i\nprint(total)\nprint(len(total))\n").unwrap();
+
+    let bcc = |args: &[&std::ffi::OsStr]| std::process::Command::new(env!("CARGO_BIN_EXE_bcc")).args(args).output().unwrap();
+    let status = bcc(&["compile".as_ref(), source.as_os_str(), "-o".as_ref(), compiled.as_os_str()]).status;
+    assert!(status.success());
+
+    // The compiled script needs neither the source file nor the parser
+    let expected = bcc(&[source.as_os_str()]);
+    std::fs::remove_file(&source).unwrap();
+    let actual = bcc(&[compiled.as_os_str()]);
+    let output = String::from_utf8_lossy(&actual.stdout);
+    assert!(output.starts_with("6\n"), "{}", output);
+    assert!(output.contains("len() not supported for type int"), "{}", output);
+    assert_eq!(actual.stdout, expected.stdout);
+    assert_eq!(actual.stderr, expected.stderr);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn warnings_name_their_kind_once() {
+    let path = std::env::temp_dir().join(format!("bcc-warning-test-{}.bcc", std::process::id()));
+    std::fs::write(&path, "x = 1\n").unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_bcc")).arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let report = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(report.contains("Unused variable 'x'"), "{}", report);
+    assert_eq!(report.matches("Warning").count(), 1, "{}", report);
+}
+
+#[test]
+fn blocks_update_outer_variables_at_any_depth() {
+    let nested = |depth: usize, body: &str| format!("{}{}{}", "{ ".repeat(depth), body, " }".repeat(depth));
+
+    // Existing variables are updated in place and new ones outlive their block
+    let source = format!("total = 0\n{}\nassert total == 5\nassert created == 1", nested(64, "total = total + 5\ncreated = 1"));
+    assert!(run_input(&source).is_ok());
+    assert!(run_input_on_vm(&source).is_ok());
+
+    // A failing block still closes its scopes, so later evaluation sees the same variables
+    let program = parse_input(&nested(8, "x = 1\ny = 1 / 0")).unwrap();
+    let mut evaluator = bcc::Evaluator::new();
+    assert!(evaluator.evaluate_program(&program).is_err());
+    let names = evaluator.global_names();
+    assert!(names.contains(&"x".to_string()) && !names.contains(&"y".to_string()));
+    assert!(evaluator.evaluate_program(&parse_input("{ x = x + 1 }\nassert x == 2").unwrap()).is_ok());
+}
+
+#[test]
+fn lists_and_dicts_are_shared_references() {
+    let source = "\
+a = [1, 2]
+b = a
+b.append(3)
+assert a == [1, 2, 3]
+assert a is b
+assert not (a is [1, 2, 3])
+assert (1, \"x\") is (1, \"x\")
+d = {\"k\": 1}
+e = d
+e.update({\"j\": 2})
+assert d == {\"j\": 2, \"k\": 1}
+a.append(a)
+c = [1, 2, 3]
+c.append(c)
+assert a == c
+assert a in a";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    use bcc::value::Value;
+    let list = Value::list(vec![Value::Int(1)]);
+    if let Value::List(items) = &list {
+        items.borrow_mut().push(list.clone());
+    }
+    assert_eq!(list.to_string(), "[1, [...]]");
+    let dict = Value::dict(bcc::ordered_map::OrderedMap::new());
+    if let Value::Dict(entries) = &dict {
+        entries.borrow_mut().insert(bcc::value::Key::String("self".to_string()), Value::Tuple(vec![dict.clone()]));
+    }
+    assert_eq!(dict.to_string(), "{\"self\": ({...},)}");
+}
+
+#[test]
+fn dictionaries_keep_insertion_order() {
+    let source = "\
+d = {\"zebra\": 1, \"apple\": 2, \"mango\": 3}
+d.update({\"apple\": 20, \"kiwi\": 4})
+assert len(d) == 4
+assert d == {\"kiwi\": 4, \"mango\": 3, \"apple\": 20, \"zebra\": 1}";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    let program = parse_input("x = {\"zebra\": 1, \"apple\": 2, \"zebra\": 3, \"mango\": {\"b\": 1, \"a\": 2}}").unwrap();
+    let bcc::Stmt::Expression { expr: bcc::Expr::Assign { value, .. }, .. } = &program.statements[0] else {
+        panic!("expected an assignment");
+    };
+    let dict = bcc::Evaluator::new().evaluate_expression(value).unwrap();
+    assert_eq!(dict.to_string(), "{\"zebra\": 3, \"apple\": 2, \"mango\": {\"b\": 1, \"a\": 2}}");
+}
+
+#[test]
+fn dictionaries_accept_hashable_keys() {
+    let source = "\
+d = {(1, 2): \"a\", 3: \"b\", \"3\": \"c\", true: \"d\", nil: \"e\"}
+assert len(d) == 5
+assert (1, 2) in d and 3 in d and \"3\" in d and nil in d
+assert not (4 in d) and not ((2, 1) in d)
+d.update({3: \"f\"})
+assert d == {nil: \"e\", true: \"d\", \"3\": \"c\", 3: \"f\", (1, 2): \"a\"}";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    let error = run_input("x = {(1, [2]): 1}").unwrap_err();
+    assert_eq!(error.message, "Cannot use tuple containing a list as a dictionary key");
+    assert_eq!(error.code, Some("E0308"));
+    assert_eq!(run_input("x = {\"k\": 1}\ny = {1: 2} in x").unwrap_err().message, "Cannot look up dict in a dictionary");
+
+    use bcc::value::{Key, Value};
+    let dict: bcc::ordered_map::OrderedMap<Key, Value> = [
+        (Key::String("1".to_string()), Value::Int(1)),
+        (Key::Int(1), Value::String("one".to_string())),
+        (Key::Tuple(vec![Key::Int(1), Key::String("a".to_string())]), Value::Nil),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(Value::dict(dict).to_string(), "{\"1\": 1, 1: one, (1, \"a\"): nil}");
+}
+
+#[test]
+fn sets_support_literals_and_algebra() {
+    let source = "\
+a = {1, 2, 3, 2}
+b = set([3, 4])
+assert len(a) == 3 and len(set()) == 0
+assert a | b == {1, 2, 3, 4} and a & b == {3}
+assert a - b == {1, 2} and a ^ b == {1, 2, 4}
+assert 2 in a and not (4 in a) and (1, \"x\") in {(1, \"x\")}
+a.add(4)
+a.remove(1)
+assert a == {2, 3, 4} and a != b
+x, y, z = a
+assert x == 2 and z == 4
+pair = (1, 2)
+{ p, q = pair }
+assert p == 1 and q == 2";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    let error = run_input("x = {1}\nx.remove(2)").unwrap_err();
+    assert_eq!(error.message, "2 is not in the set");
+    assert_eq!(error.code, Some("E0314"));
+    assert_eq!(run_input("x = {1, [2]}").unwrap_err().message, "Cannot use list as a set element");
+    assert_eq!(run_input("x = 1 | 2").unwrap_err().message, "Cannot apply '|' to int and int");
+
+    use bcc::value::{Key, Value};
+    let set = [(Key::Int(1), ()), (Key::String("a".to_string()), ())].into_iter().collect();
+    assert_eq!(Value::set(set).to_string(), "{1, \"a\"}");
+    assert_eq!(Value::set(Default::default()).to_string(), "set()");
+}
+
+#[test]
+fn braces_holding_statements_open_blocks() {
+    let source = "\
+x = 1
+{ assert x == 1, \"x is one\" }
+c = true
+if (c) { assert c, \"c holds\" }
+while (x < 3) { assert x > 0, \"positive\"
+  x = x + 1 }
+{
+  y = x
+  print(x, y)
+}
+assert y == 3";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+    assert_eq!(run_input("{ assert false, \"in a block\" }").unwrap_err().message, "Assertion failed: in a block");
+
+    // Sets are recognized however long they are or however many lines they span
+    let source = "\
+s = {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22}
+assert len(s) == 22
+{
+  1,
+  2
+}
+assert {1 if c else 2, 3} == {1, 3}";
+    assert!(run_input(&format!("c = true\n{}", source)).is_ok());
+    let program = parse_input("{\n  1,\n  2\n}").unwrap();
+    assert!(matches!(&program.statements[..], [bcc::ast::Stmt::Expression { expr: bcc::ast::Expr::Set { .. }, .. }]));
+}
+
+#[test]
+fn ints_grow_past_64_bits() {
+    let source = "\
+max = 9223372036854775807
+big = max + 1
+assert big - 1 == max and type(big) == \"int\"
+assert -(-9223372036854775808) == big and big > max and -big < -max
+assert big * big == 85070591730234615865843651857942052864
+assert 100000000000000000000 - 99999999999999999999 == 1
+assert divmod(123456789012345678901234567890, 97) == (1272750402189130710322005854, 52)
+assert divmod(-7, -2, round_mode=\"up\") == (4, 1) and divmod(7, 2, round_mode=\"nearest\") == (4, -1)
+assert big / 2 == 4611686018427387904.0 and big * 2.0 == 18446744073709551616.0
+seen = {big: 1}
+assert max + 1 in seen";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    let program = optimize_input("x = 9223372036854775807 * 2");
+    match &program.statements[0] {
+        bcc::Stmt::Expression { expr: bcc::Expr::Assign { value, .. }, .. } => match &**value {
+            bcc::Expr::Literal { value, .. } => assert_eq!(value.to_string(), "18446744073709551614"),
+            other => panic!("expected a folded literal, got {:?}", other),
+        },
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+
+    let script = compile_input("x = -123456789012345678901234567890");
+    assert_eq!(bcc::CompiledScript::from_bytes(&script.to_bytes()), Ok(script));
+}
+
+#[test]
+fn numeric_literals_support_bases_separators_and_exponents() {
+    let source = "\
+assert 0xFF == 255 and 0XfF == 255 and 0o755 == 493 and 0b1010 == 10
+assert 1_000_000 == 1000000 and 0xFFFF_FFFF_FFFF_FFFF_FF == 4722366482869645213695
+assert 1e3 == 1000.0 and 2.5E-1 == 0.25 and .5 == 0.5 and 1_0.2_5 == 10.25
+assert type(1e3) == \"double\" and type(0x10) == \"int\"";
+    assert!(run_input(source).is_ok());
+
+    let lex_error = |source: &str| {
+        let errors = bcc::Lexer::new(source.to_string()).scan_tokens().unwrap_err();
+        (errors[0].message.clone(), errors[0].span.start, errors[0].span.end, errors[0].code)
+    };
+    assert_eq!(lex_error("x = 0x"), ("Hexadecimal literal has no digits".to_string(), 4, 6, Some("E0103")));
+    assert_eq!(lex_error("x = 0b102"), ("Invalid digit '2' in binary literal".to_string(), 8, 9, Some("E0103")));
+    assert_eq!(lex_error("x = 1__0"), ("Misplaced '_' in numeric literal".to_string(), 5, 6, Some("E0103")));
+    assert_eq!(lex_error("x = 10_"), ("Misplaced '_' in numeric literal".to_string(), 6, 7, Some("E0103")));
+    assert_eq!(lex_error("x = 0x_F"), ("Misplaced '_' in numeric literal".to_string(), 6, 7, Some("E0103")));
+    assert_eq!(lex_error("x = 1e-"), ("Exponent has no digits".to_string(), 5, 7, Some("E0103")));
+    assert_eq!(lex_error("x = 1.5.2"), ("Number has more than one decimal point".to_string(), 7, 8, Some("E0103")));
+    assert_eq!(lex_error("x = 1e999"), ("Double literal 1e999 is too large".to_string(), 4, 9, Some("E0103")));
+}
+
+#[test]
+fn comparisons_chain_and_negate() {
+    let source = "\
+x = 5
+assert 1 < x < 10 and not (1 < x > 6) and 1 <= 1 < 2 <= 2
+assert 3 > 2 == 2 and not (1 == 1 < 0)
+calls = []
+assert not (2 < 1 < calls.append(1)) and len(calls) == 0
+assert 0 < len(calls.append(1) or calls) < 2 and len(calls) == 1
+items = [1]
+assert items is not [1] and not (items is not items)
+assert 2 not in items and not (1 not in items) and \"k\" not in {\"j\": 1}";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    // The failing comparison labels its own operands
+    let error = run_input("x = 1 < 2 < \"a\"").unwrap_err();
+    assert_eq!(error.message, "Cannot compare int and string");
+    assert_eq!((error.span.start, error.span.end), (8, 15));
+
+    let script = compile_input("x = 1\nprint(0 < x < 2 <= 3)");
+    assert_eq!(bcc::CompiledScript::from_bytes(&script.to_bytes()), Ok(script));
+}
+
+#[test]
+fn conditional_expressions_and_nil_coalescing() {
+    let source = "\
+x = 5
+assert (\"big\" if x > 3 else \"small\") == \"big\"
+assert (x > 10 ? \"big\" : x > 3 ? \"mid\" : \"small\") == \"mid\"
+assert (1 if false else 2 if false else 3) == 3
+calls = []
+y = 1 if true else calls.append(1)
+z = false ? calls.append(1) : 2
+assert y == 1 and z == 2 and len(calls) == 0
+none = nil
+assert (none ?? \"default\") == \"default\" and (0 ?? 1) == 0 and (nil ?? nil ?? 7) == 7
+assert (1 ?? calls.append(1)) == 1 and len(calls) == 0
+assert none?.length == nil and none?.append(calls.append(1)) == nil and len(calls) == 0
+calls?.append(1)
+assert len(calls) == 1
+braces = {x > 3 ? 1 : 2, 4}
+assert braces == {1, 4}
+if (x) y = 10
+assert y == 10";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    // A postfix `if` at the start of a line is an if statement
+    assert!(run_input("x = 1\nif (x) x = 2\nassert x == 2").is_ok());
+
+    let error = run_input("x = 1 if true").unwrap_err();
+    assert_eq!(error.message, "Expected 'else' after the condition of a conditional expression.");
+
+    let script = compile_input("x = nil\nprint(x ?? 1, x?.a, true ? 1 : 2)");
+    assert_eq!(bcc::CompiledScript::from_bytes(&script.to_bytes()), Ok(script));
+}
+
+#[test]
+fn match_selects_the_first_matching_arm() {
+    let source = "\
+assert match (1, [2, 3]) { (0, _) => false, (a, [b, c]) if a + b + c == 6 => true, _ => false }
+assert a == 1 and b == 2 and c == 3
+point = {\"x\": 1, \"y\": 2, \"z\": 3}
+assert match point { {\"x\": 0} => 0, {\"x\": x, \"y\": y} => x + y, _ => nil } == 3
+signs = []
+for (i = -2; i < 3; i = i + 1) signs.append(match i { 0 => \"zero\", -1 => \"minus one\", n if n > 0 => n * 10, _ => \"small\" })
+assert signs == [\"small\", \"minus one\", \"zero\", 10, 20]
+assert match \"hi\" { \"hi\" => true, _ => false } and match nil { nil => true, _ => false }
+assert match [1] { (x,) => x == 1, _ => false } and match 1 { 1.0 => true, _ => false }
+calls = []
+match 1 { 2 => calls.append(2), 1 => calls.append(1), _ => calls.append(0) }
+assert calls == [1]";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    let error = run_input("x = match 3 { 1 => \"one\" }").unwrap_err();
+    assert_eq!(error.message, "No match arm matches this int");
+    assert_eq!(error.code, Some("E0315"));
+
+    assert_eq!(warning_messages("print(match 3 { 1 => 1, n if n > 1 => n })"), vec!["This match has no arm that accepts every value"]);
+    assert!(warning_messages("print(match 3 { 1 => 1, _ => 2 })").is_empty());
+
+    let script = compile_input("x = match (1, 2) { (a, 2) => a, {\"k\": [v]} => v, _ => nil }\nprint(x)");
+    assert_eq!(bcc::CompiledScript::from_bytes(&script.to_bytes()), Ok(script));
+}
+
+#[test]
+fn multi_assignment_destructures_nested_and_starred_targets() {
+    let source = "\
+(a, (b, c)) = (1, [2, 3])
+assert a == 1 and b == 2 and c == 3
+first, *rest = [1, 2, 3]
+assert first == 1 and rest == [2, 3]
+x = 0
+*init, last = (1, 2, 3)
+assert init == [1, 2] and last == 3
+head, *middle, tail = (1, 2)
+assert middle == []
+[p, [_, *q]] = [1, (2, 3, 4)]
+assert p == 1 and q == [3, 4]
+assert match [1, 2, 3] { [h, *t] if h == 1 => t == [2, 3], _ => false }";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+
+    let error = run_input("a, (b, c) = (1, (2, 3, 4))").unwrap_err();
+    assert_eq!(error.message, "Too many values to unpack (expected 2, got 3) at position 2");
+    let error = run_input("a, [[b, c]] = (1, [[2]])").unwrap_err();
+    assert_eq!(error.message, "Not enough values to unpack (expected 2, got 1) at position 2.1");
+    let error = run_input("a, *b, c = [1]").unwrap_err();
+    assert_eq!(error.message, "Not enough values to unpack (expected at least 2, got 1)");
+    let error = run_input("*a, *b = [1]").unwrap_err();
+    assert_eq!(error.message, "Only one starred target is allowed in a target list");
+
+    let script = compile_input("a, (b, *c) = (1, [2, 3])\nprint(a, b, c)");
+    assert_eq!(bcc::CompiledScript::from_bytes(&script.to_bytes()), Ok(script));
+}
+
+#[test]
+fn assignment_statements_take_unparenthesized_tuples() {
+    let source = "\
+a, b = 1, 2
+a, b = b, a
+assert a == 2 and b == 1
+x = y = 3, 4 + 1
+assert x == (3, 5) and y == x
+first, *rest = 1, 2, 3
+assert rest == [2, 3]
+total = 0
+for (i, step = 0, 2; i < 6; i = i + step) total = total + i
+assert total == 6
+z = match 1 { 1 => w = 5, _ => 6 }
+assert z == 5 and w == 5
+assert [v = 1, 2] == [1, 2]";
+    assert!(run_input(source).is_ok());
+    assert!(run_input_on_vm(source).is_ok());
+}